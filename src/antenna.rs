@@ -0,0 +1,193 @@
+// Radar antenna descriptions: gain patterns, elevation limits and blanked sectors
+
+/// Antenna of a radar station
+///
+/// Gains in the patterns are one-way and relative to the boresight gain (`Radar::gain_dbi`),
+/// so they are applied twice in the range equation (transmit and receive). Angles are in
+/// degrees: azimuths clockwise from true north, elevations above the local horizontal
+/// (curvature-corrected, see `Radar::look_angles`).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Antenna {
+    /// (azimuth, relative gain dB), sorted by azimuth; empty for an omnidirectional
+    /// (or continuously rotating) antenna. Interpolated linearly and wrapped around north.
+    pub azimuth_pattern: Vec<(f64, f64)>,
+    /// (elevation, relative gain dB), sorted by elevation; empty for a flat pattern.
+    /// Interpolated linearly, and held at the end values outside the table.
+    pub elevation_pattern: Vec<(f64, f64)>,
+    /// Lowest elevation covered by the beam
+    pub min_elevation_deg: f64,
+    /// Highest elevation covered; above it is the cone of silence
+    pub max_elevation_deg: f64,
+    /// Azimuth sectors where the radar doesn't transmit, (from, to) clockwise
+    pub blanked_sectors: Vec<(f64, f64)>,
+    /// Half-power width of the main beam in elevation, for beam blockage
+    pub beamwidth_deg: f64,
+    /// Elevation of the beam axis, for beam blockage
+    pub tilt_deg: f64,
+}
+
+impl Antenna {
+    /// Ideal antenna: same gain in every direction, no blanking
+    /// Beam blockage still needs a beam: a 1 degree pencil beam at 0.5 degrees, like the
+    /// lowest elevation scan of a weather radar.
+    pub fn isotropic() -> Self {
+        Self {
+            azimuth_pattern: Vec::new(),
+            elevation_pattern: Vec::new(),
+            min_elevation_deg: -90.0,
+            max_elevation_deg: 90.0,
+            blanked_sectors: Vec::new(),
+            beamwidth_deg: 1.0,
+            tilt_deg: 0.5,
+        }
+    }
+
+    /// Typical rotating en-route surveillance antenna
+    /// Cosecant-squared elevation beam peaking at 3 degrees (constant echo strength for a
+    /// target at constant altitude), covering -5 to 40 degrees.
+    pub fn en_route() -> Self {
+        Self {
+            azimuth_pattern: Vec::new(),
+            elevation_pattern: vec![
+                (-5.0, -25.0),
+                (-2.0, -10.0),
+                (0.0, -3.0),
+                (3.0, 0.0),
+                (10.0, -10.4),
+                (20.0, -16.3),
+                (30.0, -19.6),
+                (40.0, -21.8),
+            ],
+            min_elevation_deg: -5.0,
+            max_elevation_deg: 40.0,
+            blanked_sectors: Vec::new(),
+            // Main lobe of the table: -3 dB at 0 and about 5 degrees
+            beamwidth_deg: 5.0,
+            tilt_deg: 3.0,
+        }
+    }
+
+    /// Whether the beam reaches the direction (azimuth, elevation) at all
+    pub fn covers(&self, azimuth_deg: f64, elevation_deg: f64) -> bool {
+        elevation_deg >= self.min_elevation_deg
+            && elevation_deg <= self.max_elevation_deg
+            && !self.is_blanked(azimuth_deg)
+    }
+
+    /// Whether an azimuth falls in a blanked sector
+    pub fn is_blanked(&self, azimuth_deg: f64) -> bool {
+        let azimuth = azimuth_deg.rem_euclid(360.0);
+        self.blanked_sectors.iter().any(|(from, to)| {
+            let (from, to) = (from.rem_euclid(360.0), to.rem_euclid(360.0));
+            if from <= to {
+                (from..=to).contains(&azimuth)
+            } else {
+                // Sector across north (e.g. 350 -> 10)
+                azimuth >= from || azimuth <= to
+            }
+        })
+    }
+
+    /// One-way gain relative to boresight in a direction (dB, 0 for an isotropic antenna)
+    pub fn relative_gain_db(&self, azimuth_deg: f64, elevation_deg: f64) -> f64 {
+        azimuth_gain(&self.azimuth_pattern, azimuth_deg) + interpolate(&self.elevation_pattern, elevation_deg)
+    }
+
+    /// Every coverage parameter as numbers, for cache keys
+    /// (the beamwidth and tilt only matter for beam blockage)
+    pub fn key_values(&self) -> Vec<f64> {
+        let mut values = vec![self.min_elevation_deg, self.max_elevation_deg];
+        for (a, b) in self.azimuth_pattern.iter().chain(&self.elevation_pattern).chain(&self.blanked_sectors) {
+            values.extend([*a, *b]);
+        }
+        // Separators, so moving an entry from one table to another changes the key
+        values.extend([
+            self.azimuth_pattern.len() as f64,
+            self.elevation_pattern.len() as f64,
+            self.blanked_sectors.len() as f64,
+        ]);
+        values
+    }
+}
+
+impl Default for Antenna {
+    fn default() -> Self {
+        Self::isotropic()
+    }
+}
+
+/// Linear interpolation in a sorted table, held at the end values (0 dB when empty)
+fn interpolate(table: &[(f64, f64)], x: f64) -> f64 {
+    let (Some(first), Some(last)) = (table.first(), table.last()) else {
+        return 0.0;
+    };
+    if x <= first.0 {
+        return first.1;
+    }
+    if x >= last.0 {
+        return last.1;
+    }
+    let i = table.partition_point(|(angle, _)| *angle <= x);
+    let ((x0, y0), (x1, y1)) = (table[i - 1], table[i]);
+    y0 + (y1 - y0) * (x - x0) / (x1 - x0)
+}
+
+/// Azimuth pattern lookup, wrapping from the last entry back to the first across north
+fn azimuth_gain(table: &[(f64, f64)], azimuth_deg: f64) -> f64 {
+    let (Some(&first), Some(&last)) = (table.first(), table.last()) else {
+        return 0.0;
+    };
+    let azimuth = azimuth_deg.rem_euclid(360.0);
+    if azimuth >= first.0 && azimuth <= last.0 {
+        return interpolate(table, azimuth);
+    }
+
+    // Between the last entry and the first one, one turn later
+    let span = first.0 + 360.0 - last.0;
+    let offset = if azimuth > last.0 { azimuth - last.0 } else { azimuth + 360.0 - last.0 };
+    if span <= 0.0 {
+        return last.1;
+    }
+    last.1 + (first.1 - last.1) * offset / span
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_patterns() {
+        let antenna = Antenna::en_route();
+        assert_eq!(antenna.relative_gain_db(123.0, 3.0), 0.0);
+        assert!((antenna.relative_gain_db(0.0, 1.5) + 1.5).abs() < 1e-9);
+        // Held beyond the table
+        assert_eq!(antenna.relative_gain_db(0.0, 60.0), -21.8);
+
+        // Azimuth pattern wraps around north
+        let mut sector = Antenna::isotropic();
+        sector.azimuth_pattern = vec![(10.0, 0.0), (90.0, -20.0), (350.0, -10.0)];
+        assert!((sector.relative_gain_db(50.0, 0.0) + 10.0).abs() < 1e-9);
+        assert!((sector.relative_gain_db(0.0, 0.0) + 5.0).abs() < 1e-9);
+        assert!((sector.relative_gain_db(355.0, 0.0) + 7.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_coverage_limits() {
+        let mut antenna = Antenna::en_route();
+        antenna.blanked_sectors = vec![(350.0, 10.0), (90.0, 100.0)];
+
+        assert!(antenna.covers(45.0, 1.0));
+        // Cone of silence and below the beam
+        assert!(!antenna.covers(45.0, 41.0));
+        assert!(!antenna.covers(45.0, -6.0));
+        // Blanked sectors, including one across north
+        assert!(!antenna.covers(355.0, 1.0));
+        assert!(!antenna.covers(5.0, 1.0));
+        assert!(!antenna.covers(-5.0, 1.0));
+        assert!(!antenna.covers(95.0, 1.0));
+        assert!(antenna.covers(101.0, 1.0));
+
+        assert!(Antenna::isotropic().covers(0.0, 89.0));
+        assert_ne!(antenna.key_values(), Antenna::en_route().key_values());
+    }
+}
@@ -0,0 +1,278 @@
+// Partial beam blockage of a radar's scan over the terrain (Bech et al. 2003)
+use crate::coverage::CoverageSettings;
+use crate::geodesy::GeodesicLine;
+use crate::radar::{Radar, Radars};
+use crate::tile::{TileCoord, TileData};
+use bevy::prelude::*;
+use bevy::tasks::AsyncComputeTaskPool;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+/// Azimuth bins of the polar raster (1 degree, like a weather radar PPI)
+const AZIMUTHS: usize = 360;
+
+/// Length of a range gate (meters)
+const GATE_LENGTH: f64 = 1_000.0;
+
+/// Terrain samples along the radial within each gate
+const SAMPLES_PER_GATE: usize = 10;
+
+/// The raster never extends beyond this range (meters)
+const MAX_RANGE: f64 = 250_000.0;
+
+/// Side of the exported PPI image (pixels)
+const PPI_SIZE: usize = 801;
+
+/// Fraction of a circular beam cross-section of radius `radius` below terrain at `y` above
+/// the beam axis
+///
+/// ALGORITHM: Partial Beam Blockage (Bech et al. 2003, eq. 5)
+/// The beam at range r is modeled as a disc of radius a = r tan(theta/2), theta the
+/// half-power beamwidth, centered on the beam axis. Terrain at height y relative to the
+/// axis hides the circular segment below it:
+///     PBB = (y sqrt(a^2 - y^2) + a^2 asin(y / a) + pi a^2 / 2) / (pi a^2)
+/// 0 when the terrain is below the beam (y <= -a), 1/2 at the axis, 1 above the beam.
+pub fn partial_blockage(y: f64, radius: f64) -> f64 {
+    if radius <= 0.0 || y >= radius {
+        return if y >= 0.0 { 1.0 } else { 0.0 };
+    }
+    if y <= -radius {
+        return 0.0;
+    }
+    let a2 = radius * radius;
+    (y * (a2 - y * y).sqrt() + a2 * (y / radius).asin() + std::f64::consts::FRAC_PI_2 * a2) / (std::f64::consts::PI * a2)
+}
+
+/// Cumulative beam blockage of one radar, on a polar raster of range gates
+///
+/// The cumulative blockage of a gate is the largest partial blockage anywhere in front of
+/// it along the radial: power blocked by a ridge stays lost behind it.
+pub struct BeamBlockage {
+    /// Gates along each azimuth
+    pub gates: usize,
+    /// Cumulative blockage fraction (0..1), [azimuth * gates + gate], azimuth 0 = north
+    pub values: Vec<f32>,
+    /// Tiles crossed by the scan that weren't loaded (treated as unobstructed)
+    pub missing: HashSet<TileCoord>,
+}
+
+impl BeamBlockage {
+    /// Blockage of the radar's beam (`Antenna::beamwidth_deg` and `tilt_deg`) out to its
+    /// maximum range for a target of `rcs_m2`
+    ///
+    /// Over the effective Earth, the beam axis at ground distance d is at
+    ///     h(d) = h_r + d tan(tilt) + d^2 / (2 R_eff)
+    /// above sea level (the curvature correction of `Radar::elevation_angle`), and its
+    /// radius is d tan(beamwidth / 2). Each gate takes the worst of its terrain samples.
+    pub fn compute(radar: &Radar, rcs_m2: f64, cache_snapshot: &HashMap<TileCoord, Arc<TileData>>) -> Self {
+        use rayon::prelude::*;
+
+        let r_eff = radar.refraction.effective_radius();
+        let (lat, lon, height) = (radar.position.x, radar.position.y, radar.position.z);
+        let tilt = radar.antenna.tilt_deg.to_radians().tan();
+        let spread = (radar.antenna.beamwidth_deg.to_radians() / 2.0).tan();
+        let gates = (radar.calculate_max_range(rcs_m2).min(MAX_RANGE) / GATE_LENGTH).ceil().max(1.0) as usize;
+        let step = GATE_LENGTH / SAMPLES_PER_GATE as f64;
+
+        let radials: Vec<(Vec<f32>, HashSet<TileCoord>)> = (0..AZIMUTHS)
+            .into_par_iter()
+            .map(|az| {
+                // Center of the azimuth bin
+                let angle = (az as f64 + 0.5) / AZIMUTHS as f64 * std::f64::consts::TAU;
+                let line = GeodesicLine::new(lat, lon, angle);
+
+                let mut values = Vec::with_capacity(gates);
+                let mut missing = HashSet::new();
+                let mut cumulative = 0.0_f64;
+                // Same access optimization as the raycast: keep the current tile at hand
+                let mut current: Option<(TileCoord, Option<&TileData>)> = None;
+
+                for k in 1..=gates * SAMPLES_PER_GATE {
+                    let d = step * k as f64;
+                    let (cur_lat, cur_lon) = line.position(d);
+                    let coord = TileCoord::from_world_coords(cur_lat, cur_lon);
+                    if current.map(|(c, _)| c) != Some(coord) {
+                        current = Some((coord, cache_snapshot.get(&coord).map(|d| d.as_ref())));
+                    }
+
+                    match current.and_then(|(_, data)| data) {
+                        Some(data) => {
+                            let ny = (1.0 - (cur_lat - coord.lat as f64)) as f32;
+                            let nx = (cur_lon - coord.lon as f64) as f32;
+                            let terrain = data.get_height_normalized(nx, ny) as f64;
+
+                            let axis = height + d * tilt + d * d / (2.0 * r_eff);
+                            cumulative = cumulative.max(partial_blockage(terrain - axis, d * spread));
+                        }
+                        None => {
+                            missing.insert(coord);
+                        }
+                    }
+                    if k % SAMPLES_PER_GATE == 0 {
+                        values.push(cumulative as f32);
+                    }
+                }
+                (values, missing)
+            })
+            .collect();
+
+        let mut values = Vec::with_capacity(AZIMUTHS * gates);
+        let mut missing = HashSet::new();
+        for (radial, tiles) in radials {
+            values.extend(radial);
+            missing.extend(tiles);
+        }
+        Self { gates, values, missing }
+    }
+
+    /// Cumulative blockage at an azimuth (degrees) and ground range (meters); None beyond
+    /// the raster
+    pub fn at(&self, azimuth_deg: f64, range_m: f64) -> Option<f32> {
+        let gate = (range_m / GATE_LENGTH) as usize;
+        if range_m < 0.0 || gate >= self.gates {
+            return None;
+        }
+        let az = (azimuth_deg.rem_euclid(360.0) / 360.0 * AZIMUTHS as f64) as usize % AZIMUTHS;
+        Some(self.values[az * self.gates + gate])
+    }
+
+    /// Polar raster as CSV: one row per azimuth bin, one column per gate (named by its far
+    /// end in meters), blockage in percent
+    pub fn to_csv(&self) -> String {
+        let header: Vec<String> = (0..self.gates).map(|gate| format!("{:.0}", (gate + 1) as f64 * GATE_LENGTH)).collect();
+        let mut out = format!("azimuth_deg,{}\n", header.join(","));
+        for (az, radial) in self.values.chunks(self.gates).enumerate() {
+            let values: Vec<String> = radial.iter().map(|value| format!("{:.1}", value * 100.0)).collect();
+            out.push_str(&format!("{},{}\n", az, values.join(",")));
+        }
+        out
+    }
+
+    /// PPI image (binary PGM, north up, radar at the center): black unblocked, white fully
+    /// blocked, mid-gray beyond the raster
+    pub fn to_ppi_pgm(&self) -> Vec<u8> {
+        let mut out = format!("P5\n{} {}\n255\n", PPI_SIZE, PPI_SIZE).into_bytes();
+        let center = (PPI_SIZE / 2) as f64;
+        let meters_per_pixel = self.gates as f64 * GATE_LENGTH / center;
+        for row in 0..PPI_SIZE {
+            for col in 0..PPI_SIZE {
+                let east = (col as f64 - center) * meters_per_pixel;
+                let north = (center - row as f64) * meters_per_pixel;
+                let azimuth = east.atan2(north).to_degrees();
+                out.push(match self.at(azimuth, east.hypot(north)) {
+                    Some(value) => (value.clamp(0.0, 1.0) * 255.0).round() as u8,
+                    None => 128,
+                });
+            }
+        }
+        out
+    }
+
+    /// Share of the gates at least `fraction` blocked
+    pub fn share_above(&self, fraction: f32) -> f32 {
+        self.values.iter().filter(|value| **value >= fraction).count() as f32 / self.values.len().max(1) as f32
+    }
+}
+
+/// System to export the beam blockage of every enabled radar (Shift+X)
+/// Files go to `exports/blockage_<radar>.csv` (polar raster) and `.pgm` (PPI image).
+pub fn export_blockage_system(
+    keys: Res<ButtonInput<KeyCode>>,
+    cache: Res<crate::cache::TileCache>,
+    radars: Res<Radars>,
+    settings: Res<CoverageSettings>,
+) {
+    let shift_pressed = keys.pressed(KeyCode::ShiftLeft) || keys.pressed(KeyCode::ShiftRight);
+    if !keys.just_pressed(KeyCode::KeyX) || !shift_pressed {
+        return;
+    }
+
+    let snapshot = cache.get_snapshot();
+    let radars = radars.clone();
+    let rcs_m2 = settings.detection.target.rcs_m2();
+
+    // Runs in the background; the result is only reported in the log
+    AsyncComputeTaskPool::get()
+        .spawn(async move {
+            let dir = std::env::current_dir()
+                .expect("Could not determine current directory")
+                .join("exports");
+            if let Err(e) = std::fs::create_dir_all(&dir) {
+                error!("Failed to create export directory ({:?}): {}", dir, e);
+                return;
+            }
+
+            for radar in radars.stations.iter().filter(|radar| radar.enabled) {
+                let blockage = BeamBlockage::compute(radar, rcs_m2, &snapshot);
+                let stem: String = radar
+                    .name
+                    .chars()
+                    .map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_lowercase() } else { '_' })
+                    .collect();
+                let csv = dir.join(format!("blockage_{}.csv", stem));
+                let pgm = dir.join(format!("blockage_{}.pgm", stem));
+                for (path, result) in [
+                    (&csv, std::fs::write(&csv, blockage.to_csv())),
+                    (&pgm, std::fs::write(&pgm, blockage.to_ppi_pgm())),
+                ] {
+                    if let Err(e) = result {
+                        error!("Failed to write {:?}: {}", path, e);
+                    }
+                }
+                info!(
+                    "Beam blockage of '{}' ({:.1} deg beam at {:.1} deg): {:.0}% of gates over 10% blocked, {:.0}% over 50%{}",
+                    radar.name,
+                    radar.antenna.beamwidth_deg,
+                    radar.antenna.tilt_deg,
+                    blockage.share_above(0.1) * 100.0,
+                    blockage.share_above(0.5) * 100.0,
+                    if blockage.missing.is_empty() { "" } else { " (some terrain not loaded)" }
+                );
+            }
+            info!("Exported beam blockage to {:?}", dir);
+        })
+        .detach();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{radar_at, ridge_snapshot, TARGET_RCS};
+
+    #[test]
+    fn test_partial_blockage() {
+        assert_eq!(partial_blockage(-10.0, 10.0), 0.0);
+        assert!((partial_blockage(0.0, 10.0) - 0.5).abs() < 1e-12);
+        assert_eq!(partial_blockage(10.0, 10.0), 1.0);
+        // Symmetric about the axis, and growing with the terrain
+        assert!((partial_blockage(4.0, 10.0) + partial_blockage(-4.0, 10.0) - 1.0).abs() < 1e-12);
+        assert!(partial_blockage(2.0, 10.0) < partial_blockage(4.0, 10.0));
+    }
+
+    #[test]
+    fn test_ridge_blockage() {
+        let snapshot = ridge_snapshot();
+        // ~21 km west of the ridge
+        let mut radar = radar_at(43.5, 7.375, 400.0);
+        let blockage = BeamBlockage::compute(&radar, TARGET_RCS, &snapshot);
+
+        // West: flat terrain well below the beam
+        assert_eq!(blockage.at(270.0, 20_000.0), Some(0.0));
+        // East: nothing before the ridge, (almost) fully blocked behind it (800 m against a beam
+        // centered ~610 m high with a ~185 m radius), and the blockage never recovers
+        assert_eq!(blockage.at(90.0, 20_000.0), Some(0.0));
+        let behind = blockage.at(90.0, 30_000.0).unwrap();
+        assert!(behind > 0.99, "{}", behind);
+        assert_eq!(blockage.at(90.0, 60_000.0), Some(behind));
+
+        // Tilting the beam up clears part of the ridge
+        radar.antenna.tilt_deg = 1.2;
+        let tilted = BeamBlockage::compute(&radar, TARGET_RCS, &snapshot);
+        let partial = tilted.at(90.0, 30_000.0).unwrap();
+        assert!(partial > 0.0 && partial < 1.0, "{}", partial);
+
+        assert_eq!(blockage.at(90.0, 1e9), None);
+        assert_eq!(blockage.to_csv().lines().count(), AZIMUTHS + 1);
+        assert_eq!(blockage.to_ppi_pgm().len(), format!("P5\n{} {}\n255\n", PPI_SIZE, PPI_SIZE).len() + PPI_SIZE * PPI_SIZE);
+    }
+}
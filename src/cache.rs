@@ -8,6 +8,8 @@ use bevy::prelude::*;
 #[derive(Resource)]
 pub struct TileCache {
     pub tiles: HashMap<TileCoord, TileState>,
+    /// (min, max) elevation of each loaded tile, computed once on insertion
+    height_ranges: HashMap<TileCoord, (i16, i16)>,
     cache_dir: PathBuf,
 }
 
@@ -24,6 +26,7 @@ impl TileCache {
         
         Self {
             tiles: HashMap::new(),
+            height_ranges: HashMap::new(),
             cache_dir,
         }
     }
@@ -53,12 +56,22 @@ impl TileCache {
 
     /// Insert or update a tile
     pub fn insert_tile(&mut self, coord: TileCoord, state: TileState) {
+        if let TileState::Loaded(data) = &state {
+            self.height_ranges.insert(coord, data.height_range());
+        } else {
+            self.height_ranges.remove(&coord);
+        }
         self.tiles.insert(coord, state);
     }
     
     /// Insert loaded tile data (helper)
     pub fn insert_data(&mut self, coord: TileCoord, data: TileData) {
-        self.tiles.insert(coord, TileState::Loaded(std::sync::Arc::new(data)));
+        self.insert_tile(coord, TileState::Loaded(std::sync::Arc::new(data)));
+    }
+
+    /// Get (min, max) elevation of a loaded tile
+    pub fn height_range(&self, coord: &TileCoord) -> Option<(i16, i16)> {
+        self.height_ranges.get(coord).copied()
     }
 
     /// Mark a tile as loading
@@ -205,6 +218,7 @@ impl TileCache {
     /// Clear all tiles from memory (keeps disk cache)
     pub fn clear_memory(&mut self) {
         self.tiles.clear();
+        self.height_ranges.clear();
     }
 }
 
@@ -1,6 +1,14 @@
 // Camera controller for terrain navigation
 use bevy::prelude::*;
 use bevy::input::mouse::{MouseMotion, MouseWheel};
+use crate::world::{FloatingOrigin, WorldMode, WorldPosition};
+
+/// Home position (Mont Agel, N43.77528 E7.42639)
+const HOME_LAT: f64 = 43.77528;
+const HOME_LON: f64 = 7.42639;
+
+/// Minimum camera altitude above the ellipsoid/sea level (meters)
+const MIN_ALTITUDE: f64 = 100.0;
 
 /// Camera controller component
 #[derive(Component)]
@@ -21,12 +29,15 @@ impl Default for TerrainCamera {
 }
 
 /// Update camera based on input
+#[allow(clippy::too_many_arguments)]
 pub fn camera_flight_system(
     time: Res<Time>,
     keys: Res<ButtonInput<KeyCode>>,
     mouse_button: Res<ButtonInput<MouseButton>>,
     mut mouse_motion: EventReader<MouseMotion>,
     mut scroll_events: EventReader<MouseWheel>,
+    origin: Res<FloatingOrigin>,
+    mode: Res<WorldMode>,
     mut query: Query<(&mut Transform, &TerrainCamera)>,
 ) {
     let Ok((mut transform, camera)) = query.single_mut() else {
@@ -35,11 +46,16 @@ pub fn camera_flight_system(
 
     let dt = time.delta_secs();
 
+    // Local vertical: world Y on the flat map, ellipsoid normal on the globe
+    let up = mode.up(origin.to_world(transform.translation)).as_vec3();
+    // Distances are tuned for flat-mode units; scale them for the globe (meters)
+    let unit_scale = mode.unit_scale() as f32;
+
     // Mouse rotation (right-click drag)
     if mouse_button.pressed(MouseButton::Right) {
         for event in mouse_motion.read() {
-            // Rotate around Y axis (yaw)
-            let yaw = Quat::from_rotation_y(-event.delta.x * camera.rotate_speed);
+            // Rotate around local vertical axis (yaw)
+            let yaw = Quat::from_axis_angle(up, -event.delta.x * camera.rotate_speed);
             // Rotate around local X axis (pitch)
             let pitch = Quat::from_rotation_x(-event.delta.y * camera.rotate_speed);
             
@@ -55,12 +71,7 @@ pub fn camera_flight_system(
     
     // Reset Camera (R)
     if keys.just_pressed(KeyCode::KeyR) {
-        let tile_size = 3601.0;
-        let center_x = 7.42639 * tile_size;
-        let center_z = -43.77528 * tile_size;
-        
-        *transform = Transform::from_xyz(center_x, 15000.0, center_z + 5000.0)
-            .looking_at(Vec3::new(center_x, 0.0, center_z - 5000.0), Vec3::Y);
+        *transform = home_transform(&origin, *mode);
         info!("Camera reset to home position");
         return;
     }
@@ -100,11 +111,11 @@ pub fn camera_flight_system(
         
         // Up = Translate Up (Altitude +)
         if keys.pressed(KeyCode::ArrowUp) {
-            direction.y += 1.0;
+            direction += up;
         }
         // Down = Translate Down (Altitude -)
         if keys.pressed(KeyCode::ArrowDown) {
-            direction.y -= 1.0;
+            direction -= up;
         }
         // Left = Translate Left
         if keys.pressed(KeyCode::ArrowLeft) {
@@ -118,13 +129,13 @@ pub fn camera_flight_system(
 
     // Apply Rotation
     if rotation_yaw != 0.0 || rotation_pitch != 0.0 {
-        let yaw = Quat::from_rotation_y(rotation_yaw * camera.rotate_speed * 10.0);
+        let yaw = Quat::from_axis_angle(up, rotation_yaw * camera.rotate_speed * 10.0);
         let pitch = Quat::from_rotation_x(rotation_pitch * camera.rotate_speed * 10.0);
         
         // Yaw is global (applied before current rotation), Pitch is local (applied after)
         // transform.rotation = yaw * transform.rotation * pitch; 
         
-        // Actually, for free cam, we often want yaw to be around global Y.
+        // Actually, for free cam, we often want yaw to be around the local vertical.
         // And pitch around local X.
         transform.rotation = yaw * transform.rotation * pitch;
     }
@@ -136,31 +147,57 @@ pub fn camera_flight_system(
         // Boost speed with Control
         let speed_mult = if keys.pressed(KeyCode::ControlLeft) { 5.0 } else { 1.0 };
         
-        transform.translation += direction * camera.move_speed * speed_mult * dt;
+        transform.translation += direction * camera.move_speed * unit_scale * speed_mult * dt;
     }
     
     // Mouse wheel zoom (move forward/backward) along view vector
     for event in scroll_events.read() {
         let forward = transform.forward().as_vec3();
-        transform.translation += forward * event.y * camera.zoom_speed;
+        transform.translation += forward * event.y * camera.zoom_speed * unit_scale;
     }
     
-    // Keep camera above ground
-    transform.translation.y = transform.translation.y.max(100.0);
+    // Keep camera above ground (altitude is absolute, translation is origin-relative)
+    let world_pos = origin.to_world(transform.translation);
+    let geo = mode.world_to_geo(world_pos);
+    if geo.z < MIN_ALTITUDE {
+        transform.translation = origin.to_local(mode.geo_to_world(geo.x, geo.y, MIN_ALTITUDE));
+    }
+
+    // ALGORITHM: Orbiting the globe
+    // The local vertical changes as we fly around the planet. Rotating the camera by
+    // the same amount keeps the horizon level, so flying "forward" circles the Earth.
+    // On the flat map `up` is constant and this is a no-op.
+    let new_up = mode.up(origin.to_world(transform.translation)).as_vec3();
+    transform.rotation = Quat::from_rotation_arc(up, new_up) * transform.rotation;
+}
+
+/// Camera transform for the home position, relative to the current origin
+fn home_transform(origin: &FloatingOrigin, mode: WorldMode) -> Transform {
+    // Position camera SOUTH of target, looking NORTH, 15 km up
+    // (5000 flat units = 5000 arc-seconds of latitude either side)
+    let offset_deg = 5000.0 / crate::world::TILE_SIZE;
+    let eye = mode.geo_to_world(HOME_LAT - offset_deg, HOME_LON, 15000.0);
+    let target = mode.geo_to_world(HOME_LAT + offset_deg, HOME_LON, 0.0);
+
+    Transform::from_translation(origin.to_local(eye))
+        .looking_at(origin.to_local(target), mode.up(eye).as_vec3())
 }
 
 /// Setup camera
-pub fn setup_camera(mut commands: Commands) {
+pub fn setup_camera(
+    mut commands: Commands,
+    mut origin: ResMut<FloatingOrigin>,
+    mode: Res<WorldMode>,
+) {
     // Center on Mont Agel (N43.76 E7.42)
-    let tile_size = 3601.0;
-    let center_x = 7.42639 * tile_size;  // Longitude 7.42639E
-    let center_z = -43.77528 * tile_size; // Latitude 43.77528N
-    
-    // Position camera SOUTH of target (More Positive Z), looking NORTH (Negative Z)
+    // The render origin starts on the home position so the camera begins near (0, 0, 0)
+    origin.origin = mode.geo_to_world(HOME_LAT, HOME_LON, 0.0);
+
+    let transform = home_transform(&origin, *mode);
     commands.spawn((
         Camera3d::default(),
-        Transform::from_xyz(center_x, 15000.0, center_z + 5000.0) // South of target, looking down/north
-            .looking_at(Vec3::new(center_x, 0.0, center_z - 5000.0), Vec3::Y),
+        WorldPosition(origin.to_world(transform.translation)),
+        transform,
         TerrainCamera::default(),
     ));
 }
@@ -31,6 +31,51 @@ impl ColorMap {
         }
     }
 
+    /// Colormap for minimum detectable altitudes, in feet
+    /// Green (covered down to the ground) -> Yellow -> Orange -> Red -> Purple (FL300+)
+    pub fn minimum_altitude() -> Self {
+        Self::custom(vec![
+            (0.0, Color::srgb(0.0, 0.8, 0.2)),        // Green (low coverage)
+            (2000.0, Color::srgb(0.6, 0.9, 0.1)),     // Yellow-green
+            (5000.0, Color::srgb(1.0, 0.9, 0.0)),     // Yellow (FL050)
+            (10000.0, Color::srgb(1.0, 0.5, 0.0)),    // Orange (FL100)
+            (20000.0, Color::srgb(0.9, 0.1, 0.1)),    // Red (FL200)
+            (30000.0, Color::srgb(0.6, 0.0, 0.6)),    // Purple (FL300 and above)
+        ])
+    }
+
+    /// Colormap for probabilities of detection (0..1)
+    pub fn probability_of_detection() -> Self {
+        Self::custom(vec![
+            (0.0, Color::srgb(0.9, 0.1, 0.1)),        // Red (not detected)
+            (0.5, Color::srgb(1.0, 0.5, 0.0)),        // Orange
+            (0.8, Color::srgb(1.0, 0.9, 0.0)),        // Yellow
+            (0.9, Color::srgb(0.6, 0.9, 0.1)),        // Yellow-green
+            (0.99, Color::srgb(0.0, 0.8, 0.2)),       // Green (reliable detection)
+        ])
+    }
+
+    /// Colormap for signal margins (dB above the receiver sensitivity)
+    pub fn signal_margin() -> Self {
+        Self::custom(vec![
+            (0.0, Color::srgb(1.0, 0.5, 0.0)),        // Orange (just detected)
+            (3.0, Color::srgb(1.0, 0.9, 0.0)),        // Yellow
+            (10.0, Color::srgb(0.6, 0.9, 0.1)),       // Yellow-green
+            (20.0, Color::srgb(0.0, 0.8, 0.2)),       // Green (robust)
+        ])
+    }
+
+    /// Colormap for horizontal dilution of precision (lower is better)
+    pub fn dilution_of_precision() -> Self {
+        Self::custom(vec![
+            (1.0, Color::srgb(0.0, 0.8, 0.2)),        // Green (ideal geometry)
+            (2.0, Color::srgb(0.6, 0.9, 0.1)),        // Yellow-green
+            (5.0, Color::srgb(1.0, 0.9, 0.0)),        // Yellow
+            (10.0, Color::srgb(1.0, 0.5, 0.0)),       // Orange
+            (20.0, Color::srgb(0.9, 0.1, 0.1)),       // Red (unusable)
+        ])
+    }
+
     /// Get color for a given elevation
     pub fn get_color(&self, elevation: f32) -> Color {
         // Handle edge cases
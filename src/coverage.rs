@@ -0,0 +1,1037 @@
+// Per-tile radar coverage rasters, cached in memory and on disk
+use crate::detection::{DetectionSettings, PdGrid, TargetClass};
+use crate::horizon::HorizonProfile;
+use crate::mda::{MdaGrid, FEET_TO_METERS};
+use crate::mlat::{MlatGrid, MlatSettings};
+use crate::placement::tile_distance;
+use crate::profile::MarginGrid;
+use crate::radar::{Radar, Radars};
+use crate::refraction::Refraction;
+use crate::station::StationType;
+use crate::tile::{TileCoord, TileData};
+use bevy::prelude::*;
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock, RwLock};
+
+/// Default number of samples along each side of a tile (30 arc-seconds, ~900 m)
+pub const DEFAULT_RESOLUTION: usize = 121;
+
+/// Grids kept in memory (~120 MB at the default resolution); the least recently used go first
+const MAX_GRIDS: usize = 1024;
+
+/// Bump when the coverage computation changes, so stale disk caches are ignored
+const FORMAT_VERSION: u32 = 3;
+
+/// File magic of a coverage grid on disk
+const MAGIC: &[u8; 4] = b"COVG";
+
+/// Flight level steps offered by the UI (FL10, FL30, FL50, ...)
+const FL_FIRST: u32 = 10;
+const FL_STEP: u32 = 20;
+const FL_MAX: u32 = 450;
+
+/// Height steps above ground offered by the UI (meters, ~500 ft)
+const AGL_STEP: i32 = 150;
+
+/// Altitude at which targets are evaluated for coverage
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum CoverageAltitude {
+    /// On the terrain surface
+    #[default]
+    Ground,
+    /// Fixed altitude above mean sea level (meters)
+    Amsl(i32),
+    /// Flight level (hundreds of feet, pressure-standard)
+    FlightLevel(u32),
+    /// Fixed height above the terrain (meters)
+    Agl(i32),
+}
+
+impl CoverageAltitude {
+    /// Target altitude above mean sea level (meters) over terrain at `ground` meters
+    /// Flight levels are converted with the standard atmosphere (QNH 1013.25 hPa), where
+    /// pressure altitude equals altitude above mean sea level.
+    pub fn target_altitude(self, ground: f32) -> f32 {
+        match self {
+            CoverageAltitude::Ground => ground,
+            CoverageAltitude::Amsl(meters) => meters as f32,
+            CoverageAltitude::FlightLevel(fl) => fl as f32 * 100.0 * FEET_TO_METERS,
+            CoverageAltitude::Agl(meters) => ground + meters as f32,
+        }
+    }
+
+    /// Next layer up: ground -> FL10 -> FL30 -> ..., or the next AGL step
+    pub fn step_up(self) -> Self {
+        match self {
+            CoverageAltitude::Ground => CoverageAltitude::FlightLevel(FL_FIRST),
+            CoverageAltitude::FlightLevel(fl) => CoverageAltitude::FlightLevel((fl + FL_STEP).min(FL_MAX)),
+            CoverageAltitude::Agl(meters) => CoverageAltitude::Agl(meters + AGL_STEP),
+            CoverageAltitude::Amsl(meters) => CoverageAltitude::Amsl(meters + AGL_STEP),
+        }
+    }
+
+    /// Next layer down, back to the ground
+    pub fn step_down(self) -> Self {
+        match self {
+            CoverageAltitude::FlightLevel(fl) if fl > FL_FIRST => CoverageAltitude::FlightLevel(fl - FL_STEP),
+            CoverageAltitude::Agl(meters) if meters > AGL_STEP => CoverageAltitude::Agl(meters - AGL_STEP),
+            CoverageAltitude::Amsl(meters) if meters > AGL_STEP => CoverageAltitude::Amsl(meters - AGL_STEP),
+            _ => CoverageAltitude::Ground,
+        }
+    }
+
+    /// Short name used in file names (e.g. "fl050")
+    fn key(self) -> String {
+        match self {
+            CoverageAltitude::Ground => "gnd".to_string(),
+            CoverageAltitude::Amsl(meters) => format!("amsl{}", meters),
+            CoverageAltitude::FlightLevel(fl) => format!("fl{:03}", fl),
+            CoverageAltitude::Agl(meters) => format!("agl{}", meters),
+        }
+    }
+}
+
+impl std::fmt::Display for CoverageAltitude {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CoverageAltitude::Ground => write!(f, "Ground"),
+            CoverageAltitude::Amsl(meters) => write!(f, "{} m AMSL", meters),
+            CoverageAltitude::FlightLevel(fl) => {
+                write!(f, "FL{:03} ({:.0} m)", fl, self.target_altitude(0.0))
+            }
+            CoverageAltitude::Agl(meters) => write!(f, "{} m AGL", meters),
+        }
+    }
+}
+
+/// What the terrain colors show
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CoverageView {
+    /// Visible/hidden at the selected altitude layer, in the colors of the radars
+    #[default]
+    Visibility,
+    /// Number of radars that see each point at the selected layer (0/1/2/3+)
+    Overlap,
+    /// Areas seen by a single station, in its color
+    UniqueCoverage,
+    /// Areas lost if one radar fails (seen by exactly one)
+    SinglePointOfFailure,
+    /// Probability that the network detects the selected target at the selected layer
+    DetectionProbability,
+    /// Best signal margin at the selected layer, with the terrain's diffraction loss
+    SignalMargin,
+    /// Multilateration receivers in view and HDOP at the selected layer
+    Multilateration,
+    /// Lowest altitude at which any radar sees a target (feet, independent of the layer)
+    MinimumAltitude,
+    /// Visibility at the selected layer under the scenario refraction vs the comparison one
+    RefractionComparison,
+}
+
+impl CoverageView {
+    /// Next view (cycled with the V key)
+    pub fn next(self) -> Self {
+        match self {
+            CoverageView::Visibility => CoverageView::Overlap,
+            CoverageView::Overlap => CoverageView::UniqueCoverage,
+            CoverageView::UniqueCoverage => CoverageView::SinglePointOfFailure,
+            CoverageView::SinglePointOfFailure => CoverageView::DetectionProbability,
+            CoverageView::DetectionProbability => CoverageView::SignalMargin,
+            CoverageView::SignalMargin => CoverageView::Multilateration,
+            CoverageView::Multilateration => CoverageView::MinimumAltitude,
+            CoverageView::MinimumAltitude => CoverageView::RefractionComparison,
+            CoverageView::RefractionComparison => CoverageView::Visibility,
+        }
+    }
+}
+
+impl std::fmt::Display for CoverageView {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CoverageView::Visibility => write!(f, "Visibility"),
+            CoverageView::Overlap => write!(f, "Radar overlap (1 orange, 2 yellow, 3+ green)"),
+            CoverageView::UniqueCoverage => write!(f, "Unique coverage per station"),
+            CoverageView::SinglePointOfFailure => write!(f, "Single point of failure (seen by one radar)"),
+            CoverageView::DetectionProbability => write!(f, "Probability of detection"),
+            CoverageView::SignalMargin => write!(f, "Signal margin (dB)"),
+            CoverageView::Multilateration => write!(f, "MLAT HDOP"),
+            CoverageView::MinimumAltitude => write!(f, "Minimum detectable altitude (ft)"),
+            CoverageView::RefractionComparison => write!(f, "Refraction comparison"),
+        }
+    }
+}
+
+/// Coverage settings
+#[derive(Resource, Clone, Copy, Debug)]
+pub struct CoverageSettings {
+    /// Samples along each side of a tile (independent of the mesh LOD)
+    pub resolution: usize,
+    /// Altitude layer that is evaluated and draped on the terrain
+    pub altitude: CoverageAltitude,
+    /// What the terrain colors show
+    pub view: CoverageView,
+    /// Refraction model compared against the scenario's in `CoverageView::RefractionComparison`
+    pub comparison: Refraction,
+    /// Target and detector for `CoverageView::DetectionProbability`
+    pub detection: DetectionSettings,
+    /// Receiver requirements for `CoverageView::Multilateration`
+    pub mlat: MlatSettings,
+}
+
+impl Default for CoverageSettings {
+    fn default() -> Self {
+        Self {
+            resolution: DEFAULT_RESOLUTION,
+            altitude: CoverageAltitude::Ground,
+            view: CoverageView::Visibility,
+            comparison: Refraction::SUB_REFRACTIVE,
+            detection: DetectionSettings::default(),
+            mlat: MlatSettings::default(),
+        }
+    }
+}
+
+/// System to change the coverage layer and view from the keyboard
+/// PageUp/PageDown step flight levels (with Shift, heights above ground; with Ctrl,
+/// altitudes above sea level; Home goes back to the ground), V cycles the view and
+/// Shift+K the comparison refraction model.
+pub fn coverage_input_system(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut settings: ResMut<CoverageSettings>,
+) {
+    let shift_pressed = keys.pressed(KeyCode::ShiftLeft) || keys.pressed(KeyCode::ShiftRight);
+
+    if keys.just_pressed(KeyCode::KeyV) {
+        settings.view = settings.view.next();
+        info!("Coverage view: {}", settings.view);
+    }
+    if keys.just_pressed(KeyCode::KeyK) && shift_pressed {
+        settings.comparison = settings.comparison.next_preset();
+        info!("Comparison refraction: {}", settings.comparison);
+    }
+
+    let ctrl_pressed = keys.pressed(KeyCode::ControlLeft) || keys.pressed(KeyCode::ControlRight);
+    let current = settings.altitude;
+
+    let altitude = if keys.just_pressed(KeyCode::Home) {
+        CoverageAltitude::Ground
+    } else if keys.just_pressed(KeyCode::PageUp) {
+        match current {
+            CoverageAltitude::Amsl(_) if ctrl_pressed => current.step_up(),
+            _ if ctrl_pressed => CoverageAltitude::Amsl(AGL_STEP),
+            CoverageAltitude::Agl(_) if shift_pressed => current.step_up(),
+            _ if shift_pressed => CoverageAltitude::Agl(AGL_STEP),
+            CoverageAltitude::FlightLevel(_) | CoverageAltitude::Ground => current.step_up(),
+            _ => CoverageAltitude::FlightLevel(FL_FIRST),
+        }
+    } else if keys.just_pressed(KeyCode::PageDown) {
+        current.step_down()
+    } else {
+        return;
+    };
+
+    // Only touch the resource on a real change: it triggers a recolor of every tile
+    if altitude != current {
+        settings.altitude = altitude;
+        info!("Coverage layer: {}", altitude);
+    }
+}
+
+/// Coverage of a single ground sample
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CoverageCell {
+    /// At least one radar sees this point
+    pub visible: bool,
+    /// Best vertical margin above the terrain horizon (meters, see `HorizonProfile::clearance`):
+    /// negative when occluded, -inf when out of range of every radar
+    pub clearance_m: f32,
+    /// Number of radars that see this point
+    pub radar_count: u8,
+}
+
+impl CoverageCell {
+    /// Not reached by any radar
+    pub const NONE: Self = Self {
+        visible: false,
+        clearance_m: f32::NEG_INFINITY,
+        radar_count: 0,
+    };
+}
+
+/// Coverage raster of one tile (for one radar, or merged for several)
+/// Samples are laid out like `TileData`: row 0 is the north edge, column 0 the west edge.
+#[derive(Debug, Clone)]
+pub struct CoverageGrid {
+    pub coord: TileCoord,
+    /// Samples along each side
+    pub resolution: usize,
+    pub cells: Vec<CoverageCell>,
+}
+
+impl CoverageGrid {
+    /// Compute the coverage of `tile` by one radar, for targets of `rcs_m2` at `altitude`
+    pub fn compute(
+        radar: &Radar,
+        tile: &TileData,
+        resolution: usize,
+        altitude: CoverageAltitude,
+        rcs_m2: f64,
+        horizon: &HorizonProfile,
+    ) -> Self {
+        use rayon::prelude::*;
+
+        let coord = tile.coord;
+        let max_coord = tile.size - 1;
+        let cells = (0..resolution * resolution)
+            .into_par_iter()
+            .map(|i| {
+                let (x, y) = (i % resolution, i / resolution);
+                let (lat, lon) = Self::sample_geo(coord, resolution, x, y);
+                // Nearest elevation sample
+                let tx = (x * max_coord + (resolution - 1) / 2) / (resolution - 1);
+                let ty = (y * max_coord + (resolution - 1) / 2) / (resolution - 1);
+                let ground = tile.get_height(tx, ty).unwrap_or(0) as f32;
+                let height = altitude.target_altitude(ground);
+
+                // A layer below the terrain (e.g. low FL over mountains) is not covered
+                if height < ground {
+                    return CoverageCell::NONE;
+                }
+
+                // Range and radio horizon first, then the terrain horizon (O(1))
+                if !radar.is_visible(lat, lon, height, rcs_m2) {
+                    return CoverageCell::NONE;
+                }
+                let clearance = horizon.clearance(lat, lon, height);
+                CoverageCell {
+                    visible: clearance >= 0.0,
+                    clearance_m: clearance as f32,
+                    radar_count: (clearance >= 0.0) as u8,
+                }
+            })
+            .collect();
+
+        Self {
+            coord,
+            resolution,
+            cells,
+        }
+    }
+
+    /// Combine per-radar grids: visible by any, best clearance, radars summed
+    pub fn merge(coord: TileCoord, resolution: usize, grids: &[&CoverageGrid]) -> Self {
+        assert!(
+            grids.iter().all(|g| g.coord == coord && g.resolution == resolution),
+            "merged coverage grids must share the tile and resolution"
+        );
+        let mut cells = vec![CoverageCell::NONE; resolution * resolution];
+        for grid in grids {
+            for (cell, other) in cells.iter_mut().zip(&grid.cells) {
+                cell.visible |= other.visible;
+                cell.clearance_m = cell.clearance_m.max(other.clearance_m);
+                cell.radar_count = cell.radar_count.saturating_add(other.radar_count);
+            }
+        }
+        Self {
+            coord,
+            resolution,
+            cells,
+        }
+    }
+
+    /// Latitude/longitude of sample (x, y)
+    pub fn sample_geo(coord: TileCoord, resolution: usize, x: usize, y: usize) -> (f64, f64) {
+        let step = 1.0 / (resolution - 1) as f64;
+        (coord.lat as f64 + 1.0 - y as f64 * step, coord.lon as f64 + x as f64 * step)
+    }
+
+    /// Nearest cell to a normalized tile position (0..1 from the NW corner, as in `TileData`)
+    pub fn sample(&self, nx: f32, ny: f32) -> CoverageCell {
+        let max = (self.resolution - 1) as f32;
+        let x = (nx.clamp(0.0, 1.0) * max).round() as usize;
+        let y = (ny.clamp(0.0, 1.0) * max).round() as usize;
+        self.cells[y * self.resolution + x]
+    }
+
+    /// Serialize for the disk cache (little-endian)
+    fn to_bytes(&self) -> Vec<u8> {
+        use byteorder::{LittleEndian, WriteBytesExt};
+
+        let mut buffer = Vec::with_capacity(12 + self.cells.len() * 6);
+        buffer.extend_from_slice(MAGIC);
+        buffer.write_u32::<LittleEndian>(FORMAT_VERSION).unwrap();
+        buffer.write_u32::<LittleEndian>(self.resolution as u32).unwrap();
+        for cell in &self.cells {
+            buffer.write_u8(cell.visible as u8).unwrap();
+            buffer.write_f32::<LittleEndian>(cell.clearance_m).unwrap();
+            buffer.write_u8(cell.radar_count).unwrap();
+        }
+        buffer
+    }
+
+    /// Parse a grid written by `to_bytes`
+    fn from_bytes(coord: TileCoord, data: &[u8]) -> Result<Self, String> {
+        use byteorder::{LittleEndian, ReadBytesExt};
+        use std::io::{Cursor, Read};
+
+        let mut cursor = Cursor::new(data);
+        let mut magic = [0u8; 4];
+        cursor.read_exact(&mut magic).map_err(|e| e.to_string())?;
+        if &magic != MAGIC {
+            return Err("Not a coverage grid".to_string());
+        }
+        let version = cursor.read_u32::<LittleEndian>().map_err(|e| e.to_string())?;
+        if version != FORMAT_VERSION {
+            return Err(format!("Unsupported coverage format version {}", version));
+        }
+        let resolution = cursor.read_u32::<LittleEndian>().map_err(|e| e.to_string())? as usize;
+
+        let mut cells = Vec::with_capacity(resolution * resolution);
+        for _ in 0..resolution * resolution {
+            let read_cell = |cursor: &mut Cursor<&[u8]>| -> std::io::Result<CoverageCell> {
+                Ok(CoverageCell {
+                    visible: cursor.read_u8()? != 0,
+                    clearance_m: cursor.read_f32::<LittleEndian>()?,
+                    radar_count: cursor.read_u8()?,
+                })
+            };
+            cells.push(read_cell(&mut cursor).map_err(|e| format!("Truncated coverage grid: {}", e))?);
+        }
+
+        Ok(Self {
+            coord,
+            resolution,
+            cells,
+        })
+    }
+}
+
+/// Coverage of a tile by every enabled radar
+pub struct TileCoverage {
+    /// View the coverage was computed for
+    pub view: CoverageView,
+    /// (station index in `Radars`, grid) for each enabled radar, in station order
+    pub per_radar: Vec<(usize, Arc<CoverageGrid>)>,
+    pub merged: CoverageGrid,
+    /// Minimum detectable altitude (only computed for `CoverageView::MinimumAltitude`)
+    pub minimum_altitude: Option<MdaGrid>,
+    /// Network probability of detection (only computed for `CoverageView::DetectionProbability`)
+    pub detection: Option<PdGrid>,
+    /// Best signal margin with diffraction (only computed for `CoverageView::SignalMargin`)
+    pub margin: Option<MarginGrid>,
+    /// Receivers in view and HDOP (only computed for `CoverageView::Multilateration`)
+    pub mlat: Option<MlatGrid>,
+    /// Merged coverage under the comparison refraction model
+    /// (only computed for `CoverageView::RefractionComparison`)
+    pub comparison: Option<CoverageGrid>,
+}
+
+/// A cached grid and the tiles that were missing from the terrain when it was computed
+struct CachedGrid {
+    grid: Arc<CoverageGrid>,
+    missing: Vec<TileCoord>,
+    /// Tick of the last lookup (see `CoverageCache::clock`)
+    last_used: AtomicU64,
+}
+
+/// Terrain horizon of a radar, swept once by the first task that needs it
+type HorizonSlot = Arc<OnceLock<Arc<HorizonProfile>>>;
+
+/// Key of a cached grid: tile, radar parameters, resolution, altitude layer
+type GridKey = (TileCoord, u64, usize, CoverageAltitude);
+
+/// Key of a shared all-`NONE` grid: tile, resolution
+type UnreachedKey = (TileCoord, usize);
+
+/// Resource caching coverage grids in memory and on disk
+/// Clones share the same memory cache, so background tasks can fill it.
+#[derive(Resource, Clone)]
+pub struct CoverageCache {
+    grids: Arc<RwLock<HashMap<GridKey, CachedGrid>>>,
+    /// Most grids kept in `grids`
+    capacity: usize,
+    /// Lookup counter ordering the grids for LRU eviction
+    clock: Arc<AtomicU64>,
+    /// All-`NONE` grid of each tile and resolution, shared by the radars out of range of it
+    unreached: Arc<Mutex<HashMap<UnreachedKey, Arc<CoverageGrid>>>>,
+    /// Terrain horizon of each radar (by horizon key), shared by all its tiles
+    horizons: Arc<Mutex<HashMap<u64, HorizonSlot>>>,
+    /// Radar key -> horizon key of the candidate stations computed so far (memory only,
+    /// see `evict_candidates`)
+    candidates: Arc<Mutex<HashMap<u64, u64>>>,
+    cache_dir: PathBuf,
+}
+
+impl CoverageCache {
+    /// Create a new coverage cache (stored under assets/coverage)
+    pub fn new() -> Self {
+        let cache_dir = std::env::current_dir()
+            .expect("Could not determine current directory")
+            .join("assets")
+            .join("coverage");
+        if let Err(e) = std::fs::create_dir_all(&cache_dir) {
+            warn!("Failed to create coverage cache directory ({:?}): {}", cache_dir, e);
+        }
+
+        Self {
+            grids: Arc::new(RwLock::new(HashMap::new())),
+            capacity: MAX_GRIDS,
+            clock: Arc::new(AtomicU64::new(0)),
+            unreached: Arc::new(Mutex::new(HashMap::new())),
+            horizons: Arc::new(Mutex::new(HashMap::new())),
+            candidates: Arc::new(Mutex::new(HashMap::new())),
+            cache_dir,
+        }
+    }
+
+    /// Coverage of a tile by every enabled radar, from the caches where possible
+    /// `candidate` is the index of a station being placed: its grids stay off the disk.
+    pub fn tile_coverage(
+        &self,
+        radars: &Radars,
+        candidate: Option<usize>,
+        tile: &TileData,
+        settings: &CoverageSettings,
+        cache_snapshot: &HashMap<TileCoord, Arc<TileData>>,
+    ) -> TileCoverage {
+        let persist = |index: usize| candidate != Some(index);
+        let per_radar: Vec<(usize, Arc<CoverageGrid>)> = radars
+            .stations
+            .iter()
+            .enumerate()
+            .filter(|(_, radar)| radar.enabled)
+            .map(|(index, radar)| (index, self.radar_coverage(radar, tile, settings, cache_snapshot, persist(index))))
+            .collect();
+
+        let grids: Vec<&CoverageGrid> = per_radar.iter().map(|(_, grid)| grid.as_ref()).collect();
+        let merged = CoverageGrid::merge(tile.coord, settings.resolution, &grids);
+
+        // Cheap from the horizon profiles (O(1) per sample), so not cached
+        let horizons = matches!(settings.view, CoverageView::MinimumAltitude | CoverageView::DetectionProbability)
+            .then(|| {
+                per_radar
+                    .iter()
+                    .map(|(index, _)| &radars.stations[*index])
+                    .map(|radar| (radar, self.horizon(radar, cache_snapshot)))
+                    .collect::<Vec<(&Radar, Arc<HorizonProfile>)>>()
+            });
+        let profiles: Vec<(&Radar, &HorizonProfile)> = horizons
+            .iter()
+            .flatten()
+            .map(|(radar, horizon)| (*radar, horizon.as_ref()))
+            .collect();
+
+        let minimum_altitude = (settings.view == CoverageView::MinimumAltitude)
+            .then(|| MdaGrid::compute(&profiles, tile, settings.resolution, settings.detection.target.rcs_m2()));
+
+        let detection = (settings.view == CoverageView::DetectionProbability).then(|| {
+            PdGrid::compute(&profiles, tile, settings.resolution, settings.altitude, &settings.detection)
+        });
+
+        // Diffraction grades the rays the grids call blocked, so every enabled station counts
+        let margin = (settings.view == CoverageView::SignalMargin).then(|| {
+            let enabled: Vec<&Radar> = per_radar.iter().map(|(index, _)| &radars.stations[*index]).collect();
+            MarginGrid::compute(&enabled, tile, settings.resolution, settings.altitude, &settings.detection, cache_snapshot)
+        });
+
+        // Every enabled station is a receiver, in view where its grid is clear
+        let mlat = (settings.view == CoverageView::Multilateration).then(|| {
+            let receivers: Vec<(&Radar, &CoverageGrid)> = per_radar
+                .iter()
+                .map(|(index, grid)| (&radars.stations[*index], grid.as_ref()))
+                .collect();
+            MlatGrid::compute(&receivers, tile, settings.resolution, settings.altitude, &settings.mlat)
+        });
+
+        // The refraction is part of the radar key, so these grids are cached separately
+        let comparison = (settings.view == CoverageView::RefractionComparison).then(|| {
+            let compared = radars.with_refraction(settings.comparison);
+            let grids: Vec<Arc<CoverageGrid>> = compared
+                .stations
+                .iter()
+                .enumerate()
+                .filter(|(_, radar)| radar.enabled)
+                .map(|(index, radar)| self.radar_coverage(radar, tile, settings, cache_snapshot, persist(index)))
+                .collect();
+            let grids: Vec<&CoverageGrid> = grids.iter().map(|grid| grid.as_ref()).collect();
+            CoverageGrid::merge(tile.coord, settings.resolution, &grids)
+        });
+
+        TileCoverage {
+            view: settings.view,
+            per_radar,
+            merged,
+            minimum_altitude,
+            detection,
+            margin,
+            mlat,
+            comparison,
+        }
+    }
+
+    /// Coverage of a tile by one radar: memory cache, then disk cache, then computed
+    /// Without `persist` (a candidate station), the disk cache is neither read nor written,
+    /// and the radar's grids and horizon are dropped by the next `evict_candidates`.
+    /// A tile out of the radar's range gets the tile's shared all-`NONE` grid, neither
+    /// computed nor cached per radar.
+    pub fn radar_coverage(
+        &self,
+        radar: &Radar,
+        tile: &TileData,
+        settings: &CoverageSettings,
+        cache_snapshot: &HashMap<TileCoord, Arc<TileData>>,
+        persist: bool,
+    ) -> Arc<CoverageGrid> {
+        let coord = tile.coord;
+        let resolution = settings.resolution;
+        let rcs_m2 = settings.detection.target.rcs_m2();
+        if tile_distance(coord, radar.position.x, radar.position.y) > radar.calculate_max_range(rcs_m2) {
+            return self.unreached_grid(coord, resolution);
+        }
+        let key = (coord, radar_key(radar, settings.detection.target), resolution, settings.altitude);
+        if !persist {
+            self.candidates.lock().unwrap().insert(key.1, horizon_key(radar));
+        }
+
+        // A grid computed while terrain on its rays was missing is only reused
+        // until some of that terrain arrives
+        if let Some(cached) = self.grids.read().unwrap().get(&key) {
+            if cached.missing.iter().all(|dep| !cache_snapshot.contains_key(dep)) {
+                cached.last_used.store(self.clock.fetch_add(1, Ordering::Relaxed), Ordering::Relaxed);
+                return cached.grid.clone();
+            }
+        }
+
+        let path = self.grid_path(&key);
+        let stored = if persist {
+            std::fs::read(&path).map_err(|e| e.to_string())
+        } else {
+            Err("candidate station".to_string())
+        };
+        let (grid, missing) = match stored.and_then(|data| CoverageGrid::from_bytes(coord, &data)) {
+            Ok(grid) if grid.resolution == resolution => (Arc::new(grid), Vec::new()),
+            _ => {
+                let missing: Vec<TileCoord> = radar
+                    .tiles_between(coord, rcs_m2)
+                    .into_iter()
+                    .filter(|dep| !cache_snapshot.contains_key(dep))
+                    .collect();
+                let horizon = self.horizon(radar, cache_snapshot);
+                let grid = Arc::new(CoverageGrid::compute(radar, tile, resolution, settings.altitude, rcs_m2, &horizon));
+
+                // Only complete results of actual stations are worth keeping across sessions
+                if persist && missing.is_empty() {
+                    if let Err(e) = std::fs::write(&path, grid.to_bytes()) {
+                        error!("Failed to write coverage grid ({:?}): {}", path, e);
+                    }
+                }
+                (grid, missing)
+            }
+        };
+
+        let last_used = AtomicU64::new(self.clock.fetch_add(1, Ordering::Relaxed));
+        let mut grids = self.grids.write().unwrap();
+        grids.insert(key, CachedGrid { grid: grid.clone(), missing, last_used });
+        // ALGORITHM: Batched LRU
+        // Finding the oldest grid is a scan of the map, so once over capacity the oldest
+        // eighth goes at once instead of one grid per insertion.
+        if grids.len() > self.capacity {
+            let mut ticks: Vec<u64> = grids.values().map(|cached| cached.last_used.load(Ordering::Relaxed)).collect();
+            let excess = grids.len() - self.capacity + self.capacity / 8;
+            let (_, cutoff, _) = ticks.select_nth_unstable(excess - 1);
+            let cutoff = *cutoff;
+            grids.retain(|_, cached| cached.last_used.load(Ordering::Relaxed) > cutoff);
+        }
+        grid
+    }
+
+    /// Shared all-`NONE` grid of a tile
+    fn unreached_grid(&self, coord: TileCoord, resolution: usize) -> Arc<CoverageGrid> {
+        self.unreached
+            .lock()
+            .unwrap()
+            .entry((coord, resolution))
+            .or_insert_with(|| {
+                Arc::new(CoverageGrid {
+                    coord,
+                    resolution,
+                    cells: vec![CoverageCell::NONE; resolution * resolution],
+                })
+            })
+            .clone()
+    }
+
+    /// Drop the grids of an evicted tile (the disk cache keeps those of actual stations)
+    pub fn evict_tile(&self, coord: TileCoord) {
+        self.grids.write().unwrap().retain(|(tile, _, _, _), _| *tile != coord);
+        self.unreached.lock().unwrap().retain(|(tile, _), _| *tile != coord);
+    }
+
+    /// Terrain horizon of a radar, swept again once terrain it was missing has loaded
+    /// Stations that only differ in their antenna or link budget share one
+    pub fn horizon(&self, radar: &Radar, cache_snapshot: &HashMap<TileCoord, Arc<TileData>>) -> Arc<HorizonProfile> {
+        let key = horizon_key(radar);
+        // The map is only locked to find the slot: tasks of other radars never wait for this sweep
+        let slot = {
+            let mut horizons = self.horizons.lock().unwrap();
+            let stale = horizons
+                .get(&key)
+                .and_then(|slot| slot.get())
+                .is_some_and(|profile| profile.missing.iter().any(|coord| cache_snapshot.contains_key(coord)));
+            if stale {
+                horizons.remove(&key);
+            }
+            horizons.entry(key).or_default().clone()
+        };
+
+        // Concurrent tiles of the same radar wait for one sweep instead of repeating it
+        slot.get_or_init(|| {
+            let profile = Arc::new(HorizonProfile::compute(radar, cache_snapshot));
+            info!("Computed terrain horizon for radar '{}'", radar.name);
+            profile
+        })
+        .clone()
+    }
+
+    /// Drop the horizons and grids of the candidate stations computed so far, but `keep`'s
+    /// Each move of a candidate leaves a site nobody asks for again; without this they
+    /// would pile up in memory for the whole session.
+    pub fn evict_candidates(&self, keep: Option<&Radar>) {
+        let keep_horizon = keep.map(horizon_key);
+        let keep = keep.map(target_keys).unwrap_or_default();
+        let evicted: HashMap<u64, u64> = {
+            let mut candidates = self.candidates.lock().unwrap();
+            let evicted = candidates.iter().map(|(k, h)| (*k, *h)).filter(|(key, _)| !keep.contains(key)).collect();
+            candidates.retain(|key, _| keep.contains(key));
+            evicted
+        };
+        if evicted.is_empty() {
+            return;
+        }
+
+        // A candidate whose antenna was edited in place still uses its horizon
+        let horizons: HashSet<u64> = evicted.values().copied().filter(|key| Some(*key) != keep_horizon).collect();
+        self.horizons.lock().unwrap().retain(|key, _| !horizons.contains(key));
+        self.grids.write().unwrap().retain(|(_, key, _, _), _| !evicted.contains_key(key));
+    }
+
+    /// Drop the horizons of every site but those of `stations`
+    /// A moved station, a new refraction model or a new range (B/K/P presets) sweeps a new
+    /// horizon; the superseded ones (~20 MB each at long range) would otherwise stay.
+    pub fn retain_horizons<'a>(&self, stations: impl IntoIterator<Item = &'a Radar>) {
+        let live: HashSet<u64> = stations.into_iter().map(horizon_key).collect();
+        let mut horizons = self.horizons.lock().unwrap();
+        let before = horizons.len();
+        horizons.retain(|key, _| live.contains(key));
+        if horizons.len() < before {
+            info!("Dropped {} superseded terrain horizons", before - horizons.len());
+        }
+    }
+
+    /// A committed candidate becomes an ordinary station: keep its horizon and grids
+    /// (the other candidates are dropped)
+    pub fn commit_candidate(&self, radar: &Radar) {
+        self.evict_candidates(Some(radar));
+        self.candidates.lock().unwrap().retain(|key, _| !target_keys(radar).contains(key));
+    }
+
+    /// File path of a grid in the disk cache
+    fn grid_path(&self, key: &GridKey) -> PathBuf {
+        let (coord, radar_key, resolution, altitude) = key;
+        let stem = coord.filename().trim_end_matches(".hgt").to_string();
+        self.cache_dir.join(format!("{}_{}_r{}_{:016x}.cov", stem, altitude.key(), resolution, radar_key))
+    }
+}
+
+impl Default for CoverageCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Stable hash of the radar parameters that affect coverage of `target`
+/// Used as a disk cache key, so it must not change between runs or builds.
+/// Cooperative stations don't see the target's RCS, so they get one key for every class.
+fn radar_key(radar: &Radar, target: TargetClass) -> u64 {
+    let rcs_m2 = if radar.station_type == StationType::Primary { target.rcs_m2() } else { 0.0 };
+    let values = [
+        radar.position.x,
+        radar.position.y,
+        radar.position.z,
+        radar.frequency,
+        radar.transmit_power_dbm,
+        radar.gain_dbi,
+        radar.sensitivity_dbm,
+        radar.refraction.k,
+        rcs_m2,
+    ];
+    let antenna = radar.antenna.key_values();
+    let station = radar.station_type.key_values();
+    stable_hash(values.iter().chain(&antenna).chain(&station))
+}
+
+/// Radar keys of a station for every target class
+fn target_keys(radar: &Radar) -> HashSet<u64> {
+    TargetClass::ALL.iter().map(|target| radar_key(radar, *target)).collect()
+}
+
+/// Stable hash of the parameters a terrain horizon depends on (see `HorizonProfile::sweep_range`)
+fn horizon_key(radar: &Radar) -> u64 {
+    let values = [
+        radar.position.x,
+        radar.position.y,
+        radar.position.z,
+        radar.refraction.k,
+        HorizonProfile::sweep_range(radar),
+    ];
+    stable_hash(&values)
+}
+
+/// FNV-1a hash of the cache format version and `values`
+fn stable_hash<'a>(values: impl IntoIterator<Item = &'a f64>) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    let bytes = FORMAT_VERSION
+        .to_le_bytes()
+        .into_iter()
+        .chain(values.into_iter().flat_map(|v| v.to_le_bytes()));
+    for byte in bytes {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{default_radar, TARGET_RCS};
+
+    #[test]
+    fn test_grid_roundtrip() {
+        let coord = TileCoord::new(43, 7);
+        let grid = CoverageGrid {
+            coord,
+            resolution: 3,
+            cells: vec![
+                CoverageCell { visible: true, clearance_m: 12.5, radar_count: 1 },
+                CoverageCell::NONE,
+                CoverageCell { visible: false, clearance_m: -40.0, radar_count: 0 },
+            ]
+            .into_iter()
+            .cycle()
+            .take(9)
+            .collect(),
+        };
+
+        let parsed = CoverageGrid::from_bytes(coord, &grid.to_bytes()).unwrap();
+        assert_eq!(parsed.resolution, 3);
+        assert_eq!(parsed.cells, grid.cells);
+        assert!(CoverageGrid::from_bytes(coord, b"COVG").is_err());
+    }
+
+    #[test]
+    fn test_merge() {
+        let coord = TileCoord::new(43, 7);
+        let seen = CoverageCell { visible: true, clearance_m: 10.0, radar_count: 1 };
+        let hidden = CoverageCell { visible: false, clearance_m: -5.0, radar_count: 0 };
+        let a = CoverageGrid { coord, resolution: 1, cells: vec![seen] };
+        let b = CoverageGrid { coord, resolution: 1, cells: vec![hidden] };
+
+        let merged = CoverageGrid::merge(coord, 1, &[&a, &b, &a]);
+        assert_eq!(merged.cells[0], CoverageCell { visible: true, clearance_m: 10.0, radar_count: 2 });
+
+        let none = CoverageGrid::merge(coord, 1, &[&b]);
+        assert!(!none.cells[0].visible);
+        assert_eq!(none.cells[0].clearance_m, -5.0);
+    }
+
+    #[test]
+    fn test_candidates_stay_in_memory() {
+        use crate::test_support::{radar_at, ridge_snapshot};
+
+        let cache_dir = std::env::temp_dir().join(format!("srtm_viewer_candidates_{}", std::process::id()));
+        std::fs::create_dir_all(&cache_dir).unwrap();
+        let cache = CoverageCache { cache_dir: cache_dir.clone(), ..CoverageCache::new() };
+        let snapshot = ridge_snapshot();
+        let tile = snapshot[&TileCoord::new(43, 7)].clone();
+        let settings = CoverageSettings { resolution: 9, ..CoverageSettings::default() };
+        let files = || std::fs::read_dir(&cache_dir).unwrap().count();
+
+        // A short-range station and a candidate further east
+        let mut station = radar_at(43.5, 7.3, 400.0);
+        station.transmit_power_dbm = 40.0;
+        let mut candidate = radar_at(43.5, 7.5, 400.0);
+        candidate.transmit_power_dbm = 40.0;
+        let radars = Radars { stations: vec![station.clone(), candidate.clone()] };
+
+        // Only the station's grid reaches the disk
+        cache.tile_coverage(&radars, Some(1), &tile, &settings, &snapshot);
+        assert_eq!(files(), 1);
+        assert_eq!(cache.grids.read().unwrap().len(), 2);
+        assert_eq!(cache.horizons.lock().unwrap().len(), 2);
+
+        // Moving the candidate drops its previous site, and keeps the stations
+        let mut moved = candidate.clone();
+        moved.position.y = 7.6;
+        let radars = Radars { stations: vec![station.clone(), moved.clone()] };
+        cache.tile_coverage(&radars, Some(1), &tile, &settings, &snapshot);
+        cache.evict_candidates(Some(&moved));
+        let keys: HashSet<u64> = cache.grids.read().unwrap().keys().map(|key| key.1).collect();
+        assert_eq!(keys, HashSet::from([radar_key(&station, TargetClass::default()), radar_key(&moved, TargetClass::default())]));
+        assert!(!cache.horizons.lock().unwrap().contains_key(&horizon_key(&candidate)));
+
+        // Committed, it is an ordinary station; a discarded candidate leaves nothing behind
+        cache.commit_candidate(&moved);
+        let mut next = candidate.clone();
+        next.position.y = 7.4;
+        let radars = Radars { stations: vec![station.clone(), moved.clone(), next] };
+        cache.tile_coverage(&radars, Some(2), &tile, &settings, &snapshot);
+        cache.evict_candidates(None);
+        let keys: HashSet<u64> = cache.horizons.lock().unwrap().keys().copied().collect();
+        assert_eq!(keys, HashSet::from([horizon_key(&station), horizon_key(&moved)]));
+        assert_eq!(cache.grids.read().unwrap().len(), 2);
+        assert_eq!(files(), 1);
+
+        std::fs::remove_dir_all(&cache_dir).unwrap();
+    }
+
+    #[test]
+    fn test_grid_cache_bounded() {
+        use crate::test_support::{radar_at, ridge_snapshot};
+
+        let cache_dir = std::env::temp_dir().join(format!("srtm_viewer_bounded_{}", std::process::id()));
+        std::fs::create_dir_all(&cache_dir).unwrap();
+        let cache = CoverageCache { cache_dir: cache_dir.clone(), capacity: 8, ..CoverageCache::new() };
+        let snapshot = ridge_snapshot();
+        let tile = snapshot[&TileCoord::new(43, 7)].clone();
+        let files = || std::fs::read_dir(&cache_dir).unwrap().count();
+
+        // Stations out of range of the tile share its empty grid: nothing computed or written
+        let mut far = radar_at(43.5, 9.5, 400.0);
+        far.transmit_power_dbm = 40.0;
+        let mut farther = radar_at(45.5, 7.5, 400.0);
+        farther.transmit_power_dbm = 40.0;
+        assert!(tile_distance(tile.coord, 43.5, 9.5) > far.calculate_max_range(TARGET_RCS));
+        let settings = CoverageSettings { resolution: 5, ..CoverageSettings::default() };
+        let coverage = cache.tile_coverage(&Radars { stations: vec![far, farther] }, None, &tile, &settings, &snapshot);
+        assert!(Arc::ptr_eq(&coverage.per_radar[0].1, &coverage.per_radar[1].1));
+        assert!(coverage.merged.cells.iter().all(|cell| *cell == CoverageCell::NONE));
+        assert_eq!((cache.grids.read().unwrap().len(), files()), (0, 0));
+
+        // Past the capacity the least recently used grids go, in a batch
+        let mut station = radar_at(43.5, 7.3, 400.0);
+        station.transmit_power_dbm = 40.0;
+        let layer = |meters: i32| CoverageSettings { altitude: CoverageAltitude::Agl(meters), ..settings };
+        for meters in (10..=80).step_by(10) {
+            cache.radar_coverage(&station, &tile, &layer(meters), &snapshot, true);
+        }
+        cache.radar_coverage(&station, &tile, &layer(10), &snapshot, true);
+        cache.radar_coverage(&station, &tile, &layer(90), &snapshot, true);
+        let layers: HashSet<CoverageAltitude> = cache.grids.read().unwrap().keys().map(|key| key.3).collect();
+        let kept = [10, 40, 50, 60, 70, 80, 90].map(CoverageAltitude::Agl);
+        assert_eq!(layers, HashSet::from(kept));
+
+        // Evicting the tile drops its grids from memory only
+        cache.evict_tile(tile.coord);
+        assert!(cache.grids.read().unwrap().is_empty());
+        assert!(cache.unreached.lock().unwrap().is_empty());
+        assert_eq!(files(), 9);
+
+        std::fs::remove_dir_all(&cache_dir).unwrap();
+    }
+
+    #[test]
+    fn test_altitude_layers() {
+        assert_eq!(CoverageAltitude::Ground.target_altitude(850.0), 850.0);
+        assert_eq!(CoverageAltitude::Agl(300).target_altitude(850.0), 1150.0);
+        assert_eq!(CoverageAltitude::Amsl(2000).target_altitude(850.0), 2000.0);
+        // FL050 = 5000 ft
+        assert!((CoverageAltitude::FlightLevel(50).target_altitude(850.0) - 1524.0).abs() < 0.01);
+
+        let mut layer = CoverageAltitude::Ground;
+        let mut levels = Vec::new();
+        for _ in 0..3 {
+            layer = layer.step_up();
+            levels.push(layer);
+        }
+        use CoverageAltitude::FlightLevel;
+        assert_eq!(levels, vec![FlightLevel(10), FlightLevel(30), FlightLevel(50)]);
+        assert_eq!(FlightLevel(10).step_down(), CoverageAltitude::Ground);
+        assert_eq!(FlightLevel(FL_MAX).step_up(), FlightLevel(FL_MAX));
+    }
+
+    #[test]
+    fn test_radar_key() {
+        let radar = default_radar();
+        let mut moved = radar.clone();
+        moved.position.z += 10.0;
+        let mut renamed = radar.clone();
+        renamed.name = "Other".to_string();
+        renamed.enabled = false;
+        let mut refracted = radar.clone();
+        refracted.refraction = Refraction::SUPER_REFRACTIVE;
+        let mut blanked = radar.clone();
+        blanked.antenna.blanked_sectors.push((90.0, 120.0));
+        let mut secondary = radar.clone();
+        secondary.station_type = StationType::ssr();
+
+        assert_ne!(radar_key(&radar, TargetClass::default()), radar_key(&moved, TargetClass::default()));
+        assert_ne!(radar_key(&radar, TargetClass::default()), radar_key(&refracted, TargetClass::default()));
+        assert_ne!(radar_key(&radar, TargetClass::default()), radar_key(&blanked, TargetClass::default()));
+        assert_ne!(radar_key(&radar, TargetClass::default()), radar_key(&secondary, TargetClass::default()));
+        assert_eq!(radar_key(&radar, TargetClass::default()), radar_key(&renamed, TargetClass::default()));
+        // The target class sets the range of a primary radar only
+        assert_ne!(radar_key(&radar, TargetClass::default()), radar_key(&radar, TargetClass::Airliner));
+        assert_eq!(radar_key(&secondary, TargetClass::default()), radar_key(&secondary, TargetClass::Airliner));
+
+        // Horizons only follow the site, the refraction and the sweep range
+        let mut weaker = radar.clone();
+        weaker.transmit_power_dbm -= 40.0;
+        assert_ne!(horizon_key(&radar), horizon_key(&moved));
+        assert_ne!(horizon_key(&radar), horizon_key(&refracted));
+        assert_ne!(horizon_key(&radar), horizon_key(&weaker));
+        assert_eq!(horizon_key(&radar), horizon_key(&blanked));
+        assert_eq!(horizon_key(&radar), horizon_key(&renamed));
+    }
+
+    #[test]
+    fn test_superseded_horizons_dropped() {
+        use crate::test_support::{radar_at, ridge_snapshot};
+
+        let cache = CoverageCache::new();
+        let snapshot = ridge_snapshot();
+        let mut station = radar_at(43.5, 7.3, 400.0);
+        station.transmit_power_dbm = 40.0;
+        let mut blanked = station.clone();
+        blanked.antenna.blanked_sectors.push((90.0, 120.0));
+        let refracted = Radars { stations: vec![station.clone()] }.with_refraction(Refraction::SUPER_REFRACTIVE);
+
+        // An antenna edit reuses the horizon; a refraction change sweeps another one
+        let first = cache.horizon(&station, &snapshot);
+        assert!(Arc::ptr_eq(&first, &cache.horizon(&blanked, &snapshot)));
+        cache.horizon(&refracted.stations[0], &snapshot);
+        assert_eq!(cache.horizons.lock().unwrap().len(), 2);
+
+        // Back to the scenario refraction: only the live station's horizon stays
+        cache.retain_horizons(&[blanked]);
+        let keys: Vec<u64> = cache.horizons.lock().unwrap().keys().copied().collect();
+        assert_eq!(keys, vec![horizon_key(&station)]);
+    }
+
+    #[test]
+    fn test_flat_terrain_coverage() {
+        // A radar in the middle of a flat tile sees the whole tile
+        let mut tile = TileData::new(TileCoord::new(43, 7), 361);
+        tile.heights.fill(100);
+        let mut radar = default_radar();
+        radar.position = bevy::math::DVec3::new(43.5, 7.5, 500.0);
+
+        let snapshot = HashMap::from([(tile.coord, Arc::new(tile.clone()))]);
+        let horizon = HorizonProfile::compute(&radar, &snapshot);
+        let grid = CoverageGrid::compute(&radar, &tile, 5, CoverageAltitude::Ground, TARGET_RCS, &horizon);
+        assert!(grid.cells.iter().all(|c| c.visible && c.radar_count == 1));
+        assert!(grid.sample(0.5, 0.5).clearance_m >= 0.0);
+    }
+}
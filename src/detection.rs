@@ -0,0 +1,434 @@
+// Target classes and probability of detection
+use crate::coverage::{CoverageAltitude, CoverageGrid, CoverageSettings};
+use crate::horizon::HorizonProfile;
+use crate::radar::{Radar, Radars};
+use crate::tile::TileData;
+use bevy::prelude::*;
+
+/// Pd levels drawn as contours in the detection view (lowest first)
+pub const PD_CONTOURS: [f32; 4] = [0.5, 0.8, 0.9, 0.99];
+
+/// Kind of aircraft the coverage is evaluated for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TargetClass {
+    /// Light general aviation aircraft (e.g. Cessna 172)
+    #[default]
+    LightAircraft,
+    /// Commercial airliner
+    Airliner,
+    /// Small multirotor drone
+    Drone,
+    /// Light helicopter
+    Helicopter,
+}
+
+impl TargetClass {
+    pub const ALL: [Self; 4] = [
+        TargetClass::LightAircraft,
+        TargetClass::Airliner,
+        TargetClass::Drone,
+        TargetClass::Helicopter,
+    ];
+
+    /// Typical radar cross-section at L-band, nose-on (m^2)
+    pub fn rcs_m2(self) -> f64 {
+        match self {
+            TargetClass::LightAircraft => 2.0,
+            TargetClass::Airliner => 40.0,
+            TargetClass::Drone => 0.01,
+            TargetClass::Helicopter => 3.0,
+        }
+    }
+
+    /// Next class (cycled with the T key)
+    pub fn next(self) -> Self {
+        match self {
+            TargetClass::LightAircraft => TargetClass::Airliner,
+            TargetClass::Airliner => TargetClass::Drone,
+            TargetClass::Drone => TargetClass::Helicopter,
+            TargetClass::Helicopter => TargetClass::LightAircraft,
+        }
+    }
+}
+
+impl std::fmt::Display for TargetClass {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            TargetClass::LightAircraft => "Light aircraft",
+            TargetClass::Airliner => "Airliner",
+            TargetClass::Drone => "Drone",
+            TargetClass::Helicopter => "Helicopter",
+        };
+        write!(f, "{} ({} m2)", name, self.rcs_m2())
+    }
+}
+
+/// Swerling models of RCS fluctuation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SwerlingModel {
+    /// Constant RCS (Swerling 0/5)
+    Zero,
+    /// Many comparable scatterers, fluctuating scan to scan
+    #[default]
+    One,
+    /// Many comparable scatterers, fluctuating pulse to pulse
+    Two,
+    /// One dominant scatterer, fluctuating scan to scan
+    Three,
+    /// One dominant scatterer, fluctuating pulse to pulse
+    Four,
+}
+
+impl SwerlingModel {
+    pub const ALL: [Self; 5] = [
+        SwerlingModel::Zero,
+        SwerlingModel::One,
+        SwerlingModel::Two,
+        SwerlingModel::Three,
+        SwerlingModel::Four,
+    ];
+
+    /// Next model (cycled with Shift+T)
+    pub fn next(self) -> Self {
+        match self {
+            SwerlingModel::Zero => SwerlingModel::One,
+            SwerlingModel::One => SwerlingModel::Two,
+            SwerlingModel::Two => SwerlingModel::Three,
+            SwerlingModel::Three => SwerlingModel::Four,
+            SwerlingModel::Four => SwerlingModel::Zero,
+        }
+    }
+}
+
+impl std::fmt::Display for SwerlingModel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let index = match self {
+            SwerlingModel::Zero => 0,
+            SwerlingModel::One => 1,
+            SwerlingModel::Two => 2,
+            SwerlingModel::Three => 3,
+            SwerlingModel::Four => 4,
+        };
+        write!(f, "Swerling {}", index)
+    }
+}
+
+/// Target and detector used for the probability of detection
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DetectionSettings {
+    pub target: TargetClass,
+    pub swerling: SwerlingModel,
+    /// Probability of false alarm of the detector
+    pub pfa: f64,
+}
+
+impl Default for DetectionSettings {
+    fn default() -> Self {
+        Self {
+            target: TargetClass::default(),
+            swerling: SwerlingModel::default(),
+            pfa: 1e-6,
+        }
+    }
+}
+
+impl std::fmt::Display for DetectionSettings {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}, {}, Pfa {:.0e}", self.target, self.swerling, self.pfa)
+    }
+}
+
+/// Complementary error function
+/// Abramowitz & Stegun 7.1.26 (absolute error below 1.5e-7), extended to x < 0.
+fn erfc(x: f64) -> f64 {
+    let z = x.abs();
+    let t = 1.0 / (1.0 + 0.3275911 * z);
+    let poly = t * (0.254829592 + t * (-0.284496736 + t * (1.421413741 + t * (-1.453152027 + t * 1.061405429))));
+    let value = poly * (-z * z).exp();
+    if x >= 0.0 { value } else { 2.0 - value }
+}
+
+/// Probability of detecting a target with the given SNR (linear, after processing)
+///
+/// ALGORITHM: Square-law detection of a single (integrated) pulse
+/// The threshold T = -ln(Pfa) in noise power units sets the false-alarm rate.
+/// - Swerling 1/2 (exponential RCS): Pd = exp(-T / (1 + SNR)), exact.
+/// - Swerling 3/4 (chi-square, 4 degrees of freedom):
+///   Pd = exp(-T / (1 + SNR/2)) (1 + T / (1 + SNR/2) + 2/SNR) SNR / (SNR + 2), exact.
+/// - Swerling 0 (constant RCS): Marcum's Q function, with North's approximation
+///   Pd = erfc(sqrt(T) - sqrt(SNR + 1/2)) / 2.
+///
+/// Pulse-to-pulse models (2 and 4) only differ from 1 and 3 when several detections are
+/// combined; integration is folded into the processing gain, so they match here.
+pub fn probability_of_detection(snr: f64, pfa: f64, swerling: SwerlingModel) -> f64 {
+    let threshold = -pfa.ln();
+    if snr <= 0.0 {
+        return pfa;
+    }
+
+    let pd = match swerling {
+        SwerlingModel::Zero => 0.5 * erfc(threshold.sqrt() - (snr + 0.5).sqrt()),
+        SwerlingModel::One | SwerlingModel::Two => (-threshold / (1.0 + snr)).exp(),
+        SwerlingModel::Three | SwerlingModel::Four => {
+            let scaled = threshold / (1.0 + snr / 2.0);
+            (-scaled).exp() * (1.0 + scaled + 2.0 / snr) * snr / (snr + 2.0)
+        }
+    };
+    pd.clamp(0.0, 1.0)
+}
+
+/// SNR (linear) at which the target is detected with probability `pd`: the detection
+/// threshold the signal margin is measured against
+pub fn required_snr(settings: &DetectionSettings, pd: f64) -> f64 {
+    let pd_at = |snr_db: f64| probability_of_detection(10.0_f64.powf(snr_db / 10.0), settings.pfa, settings.swerling);
+
+    // Invariant: Pd < pd at lo, >= pd at hi (bisection in dB, to 0.01 dB)
+    let (mut lo, mut hi) = (-30.0_f64, 60.0_f64);
+    while hi - lo > 0.01 {
+        let mid = 0.5 * (lo + hi);
+        if pd_at(mid) >= pd {
+            hi = mid;
+        } else {
+            lo = mid;
+        }
+    }
+    10.0_f64.powf(hi / 10.0)
+}
+
+/// Probability that `radar` detects the target at (lat, lon, alt), line of sight assumed
+/// Includes the antenna pattern gain in the target's direction.
+pub fn radar_pd(radar: &Radar, settings: &DetectionSettings, lat: f64, lon: f64, alt: f32) -> f64 {
+    let snr = radar.snr_at(lat, lon, alt, settings.target.rcs_m2());
+    probability_of_detection(snr, settings.pfa, settings.swerling)
+}
+
+/// Slant range at which `radar` detects the target with probability `pd` (meters)
+/// Pd only decreases with range, so this is the radius of the Pd contour without terrain,
+/// along the antenna's boresight.
+pub fn range_at_pd(radar: &Radar, settings: &DetectionSettings, pd: f64) -> f64 {
+    let pd_at = |range: f64| {
+        probability_of_detection(radar.snr(range, settings.target.rcs_m2()), settings.pfa, settings.swerling)
+    };
+
+    // Invariant: Pd >= pd at lo, < pd at hi (bisection in log range, to ~1 m)
+    let (mut lo, mut hi) = (1.0_f64, 10_000_000.0_f64);
+    if pd_at(lo) < pd {
+        return 0.0;
+    }
+    while hi - lo > 1.0 {
+        let mid = (lo * hi).sqrt();
+        if pd_at(mid) >= pd {
+            lo = mid;
+        } else {
+            hi = mid;
+        }
+    }
+    lo
+}
+
+/// Farthest range at which the detection view draws a target: the lowest Pd contour of the
+/// largest target class, under the most favourable fluctuation model (meters)
+pub fn max_detection_range(radar: &Radar) -> f64 {
+    let defaults = DetectionSettings::default();
+    TargetClass::ALL
+        .iter()
+        .flat_map(|target| SwerlingModel::ALL.map(|swerling| DetectionSettings { target: *target, swerling, ..defaults }))
+        .map(|settings| range_at_pd(radar, &settings, PD_CONTOURS[0] as f64))
+        .fold(0.0, f64::max)
+}
+
+/// Probability of detection over a tile, by the whole network
+/// Laid out like `CoverageGrid` (row 0 = north edge, column 0 = west edge).
+#[derive(Debug, Clone)]
+pub struct PdGrid {
+    pub resolution: usize,
+    pub values: Vec<f32>,
+}
+
+impl PdGrid {
+    /// Network Pd at each sample of the layer: 1 - prod(1 - Pd_i) over the radars that see
+    /// the sample (detections by different radars are independent)
+    /// The mask is the antenna coverage, the radio horizon and the terrain horizon only:
+    /// range is graded by the Pd itself, so the bands follow `range_at_pd` for the selected
+    /// target.
+    pub fn compute(
+        radars: &[(&Radar, &HorizonProfile)],
+        tile: &TileData,
+        resolution: usize,
+        altitude: CoverageAltitude,
+        settings: &DetectionSettings,
+    ) -> Self {
+        use rayon::prelude::*;
+
+        let max_coord = tile.size - 1;
+        let values = (0..resolution * resolution)
+            .into_par_iter()
+            .map(|i| {
+                let (x, y) = (i % resolution, i / resolution);
+                let (lat, lon) = CoverageGrid::sample_geo(tile.coord, resolution, x, y);
+                let tx = (x * max_coord + (resolution - 1) / 2) / (resolution - 1);
+                let ty = (y * max_coord + (resolution - 1) / 2) / (resolution - 1);
+                let ground = tile.get_height(tx, ty).unwrap_or(0) as f32;
+                let height = altitude.target_altitude(ground);
+                if height < ground {
+                    return 0.0;
+                }
+
+                let missed: f64 = radars
+                    .iter()
+                    .filter(|(radar, horizon)| {
+                        radar.in_view(lat, lon, height) && horizon.clearance(lat, lon, height) >= 0.0
+                    })
+                    .map(|(radar, _)| 1.0 - radar_pd(radar, settings, lat, lon, height))
+                    .product();
+                (1.0 - missed) as f32
+            })
+            .collect();
+
+        Self { resolution, values }
+    }
+
+    /// Nearest sample to a normalized tile position (0..1 from the NW corner)
+    pub fn sample(&self, nx: f32, ny: f32) -> f32 {
+        let max = (self.resolution - 1) as f32;
+        let x = (nx.clamp(0.0, 1.0) * max).round() as usize;
+        let y = (ny.clamp(0.0, 1.0) * max).round() as usize;
+        self.values[y * self.resolution + x]
+    }
+}
+
+/// System to pick the target class (T) and fluctuation model (Shift+T)
+/// Logs the free-space Pd contours of every enabled radar for the new target.
+pub fn detection_input_system(
+    keys: Res<ButtonInput<KeyCode>>,
+    radars: Res<Radars>,
+    mut settings: ResMut<CoverageSettings>,
+) {
+    if !keys.just_pressed(KeyCode::KeyT) {
+        return;
+    }
+
+    let shift_pressed = keys.pressed(KeyCode::ShiftLeft) || keys.pressed(KeyCode::ShiftRight);
+    let detection = &mut settings.detection;
+    if shift_pressed {
+        detection.swerling = detection.swerling.next();
+    } else {
+        detection.target = detection.target.next();
+    }
+    let detection = *detection;
+    info!("Detection target: {}", detection);
+
+    for radar in radars.stations.iter().filter(|radar| radar.enabled) {
+        let contours: Vec<String> = PD_CONTOURS
+            .iter()
+            .map(|pd| format!("Pd {}: {:.0} km", pd, range_at_pd(radar, &detection, *pd as f64) / 1000.0))
+            .collect();
+        info!("  {}: {}", radar.name, contours.join(", "));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::default_radar;
+
+    #[test]
+    fn test_erfc() {
+        assert!((erfc(0.0) - 1.0).abs() < 1e-6);
+        assert!((erfc(1.0) - 0.157_299_207).abs() < 1e-6);
+        assert!((erfc(-1.0) - 1.842_700_793).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_probability_of_detection() {
+        let pfa = 1e-6;
+        // Textbook single-pulse SNR for Pd = 0.9 at Pfa = 1e-6: ~13.2 dB steady, ~21 dB Swerling 1
+        let db = |x: f64| 10.0_f64.powf(x / 10.0);
+        assert!((probability_of_detection(db(13.2), pfa, SwerlingModel::Zero) - 0.9).abs() < 0.02);
+        assert!((probability_of_detection(db(21.0), pfa, SwerlingModel::One) - 0.9).abs() < 0.02);
+        // Swerling 3 lies between the steady target and Swerling 1
+        let s3 = probability_of_detection(db(17.0), pfa, SwerlingModel::Three);
+        assert!(s3 > probability_of_detection(db(17.0), pfa, SwerlingModel::One));
+        assert!(s3 < probability_of_detection(db(17.0), pfa, SwerlingModel::Zero));
+
+        // No signal: only false alarms
+        for model in [SwerlingModel::Zero, SwerlingModel::One, SwerlingModel::Three] {
+            assert!(probability_of_detection(1e-9, pfa, model) < 1e-3);
+            assert!(probability_of_detection(1e6, pfa, model) > 0.999);
+        }
+    }
+
+    #[test]
+    fn test_required_snr() {
+        for swerling in SwerlingModel::ALL {
+            let settings = DetectionSettings { swerling, ..DetectionSettings::default() };
+            for pd in PD_CONTOURS {
+                let snr = required_snr(&settings, pd as f64);
+                let reached = probability_of_detection(snr, settings.pfa, swerling);
+                assert!((reached - pd as f64).abs() < 2e-3, "{:?} {}: {}", swerling, pd, reached);
+            }
+        }
+        // Fluctuating targets need more signal at high Pd (Swerling 1, Pfa 1e-6: ~21 dB for 0.9)
+        let fluctuating = required_snr(&DetectionSettings::default(), 0.9);
+        assert!((10.0 * fluctuating.log10() - 21.2).abs() < 0.2, "{}", fluctuating);
+    }
+
+    #[test]
+    fn test_contours_by_target() {
+        let radar = default_radar();
+        let settings = DetectionSettings::default();
+        let airliner = DetectionSettings { target: TargetClass::Airliner, ..settings };
+        let drone = DetectionSettings { target: TargetClass::Drone, ..settings };
+
+        // Contours are nested and grow with the RCS (R^4 law)
+        let r50 = range_at_pd(&radar, &settings, 0.5);
+        let r90 = range_at_pd(&radar, &settings, 0.9);
+        assert!(r90 < r50);
+        let ratio = range_at_pd(&radar, &airliner, 0.9) / r90;
+        assert!((ratio - 20.0_f64.powf(0.25)).abs() < 0.01, "{}", ratio);
+        assert!(range_at_pd(&radar, &drone, 0.9) < r90 / 3.0);
+
+        // The contour is where Pd crosses the level
+        let pd = probability_of_detection(radar.snr(r90, 2.0), settings.pfa, settings.swerling);
+        assert!((pd - 0.9).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_airliner_beyond_default_range() {
+        use crate::geodesy;
+        use crate::test_support::{radar_at, snapshot};
+        use crate::tile::{TileCoord, TileData};
+
+        // A 25 km radar (light aircraft) on flat terrain at the west edge of the tile
+        let light = TargetClass::default().rcs_m2();
+        let mut radar = radar_at(43.5, 7.02, 400.0);
+        radar.transmit_power_dbm -= 40.0 * (radar.calculate_max_range(light) / 25_000.0).log10();
+        let mut tile = TileData::new(TileCoord::new(43, 7), 361);
+        tile.heights.fill(100);
+        let horizon = HorizonProfile::compute(&radar, &snapshot(&tile));
+
+        let airliner = DetectionSettings { target: TargetClass::Airliner, ..DetectionSettings::default() };
+        let reach = range_at_pd(&radar, &airliner, 0.5);
+        assert!(reach > 1.2 * radar.calculate_max_range(light), "{} m", reach);
+        assert!(max_detection_range(&radar) >= reach);
+
+        // The map draws the airliner out to its own Pd contour, not the light aircraft's range
+        let resolution = 65;
+        let altitude = CoverageAltitude::Amsl(3000);
+        let grid = PdGrid::compute(&[(&radar, &horizon)], &tile, resolution, altitude, &airliner);
+        let mut beyond = 0;
+        for y in 0..resolution {
+            for x in 0..resolution {
+                let (lat, lon) = CoverageGrid::sample_geo(tile.coord, resolution, x, y);
+                let distance = geodesy::inverse(43.5, 7.02, lat, lon).distance;
+                let pd = radar_pd(&radar, &airliner, lat, lon, 3000.0) as f32;
+                let value = grid.values[y * resolution + x];
+                assert!((value - pd).abs() < 1e-6, "({}, {}): {} != {}", x, y, value, pd);
+                if distance > radar.calculate_max_range(light) && value >= 0.5 {
+                    beyond += 1;
+                }
+            }
+        }
+        assert!(beyond > 0);
+    }
+}
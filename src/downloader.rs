@@ -103,6 +103,7 @@ impl Default for TileDownloader {
 pub fn process_downloads(
     downloader: Res<TileDownloader>,
     mut cache: ResMut<crate::cache::TileCache>,
+    mut manager: ResMut<crate::tile_manager::TileManager>,
 ) {
     use crate::tile::TileState;
     
@@ -117,15 +118,18 @@ pub fn process_downloads(
                 }
                 
                 // Update cache with Arc
+                manager.on_data_loaded(tile_data.coord, true);
                 cache.insert_tile(tile_data.coord, TileState::Loaded(std::sync::Arc::new(tile_data)));
             }
             DownloadResult::Missing(coord) => {
                 //warn!("Tile not found: {:?}", coord);
                 cache.insert_tile(coord, TileState::Missing);
+                manager.on_data_loaded(coord, false);
             }
             DownloadResult::Error(coord, err) => {
                 error!("Failed to download tile {:?}: {}", coord, err);
                 cache.insert_tile(coord, TileState::Error(err));
+                manager.on_data_loaded(coord, false);
             }
         }
     }
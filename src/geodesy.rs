@@ -0,0 +1,341 @@
+// Geodesy on the WGS84 ellipsoid: distances and paths between radars and targets
+use crate::radar::EARTH_RADIUS;
+use bevy::math::DVec3;
+use crate::world::{WGS84_A, WGS84_F};
+
+/// WGS84 semi-minor axis (meters)
+const WGS84_B: f64 = WGS84_A * (1.0 - WGS84_F);
+
+/// Convergence threshold of the Vincenty iterations (radians, ~0.06 mm)
+const TOLERANCE: f64 = 1e-12;
+
+/// Iteration cap; the inverse problem only fails to converge for nearly antipodal points
+const MAX_ITERATIONS: usize = 200;
+
+/// Solution of the inverse problem between two points
+#[derive(Debug, Clone, Copy)]
+pub struct Inverse {
+    /// Length of the geodesic (meters)
+    pub distance: f64,
+    /// Azimuth of the geodesic at the first point (radians clockwise from north)
+    pub azimuth: f64,
+}
+
+/// Geodesic distance and initial azimuth between two points on WGS84 (degrees)
+///
+/// ALGORITHM: Vincenty's inverse formula
+/// Iterates on the longitude difference on the auxiliary sphere until it converges,
+/// accurate to well under a millimeter. For nearly antipodal points, where it doesn't
+/// converge, we fall back to the great circle on a sphere of mean radius.
+pub fn inverse(lat1: f64, lon1: f64, lat2: f64, lon2: f64) -> Inverse {
+    let f = WGS84_F;
+    let l = (lon2 - lon1).to_radians();
+    let (sin_u1, cos_u1) = ((1.0 - f) * lat1.to_radians().tan()).atan().sin_cos();
+    let (sin_u2, cos_u2) = ((1.0 - f) * lat2.to_radians().tan()).atan().sin_cos();
+
+    let mut lambda = l;
+    for _ in 0..MAX_ITERATIONS {
+        let (sin_lambda, cos_lambda) = lambda.sin_cos();
+        let sin_sigma = (cos_u2 * sin_lambda).hypot(cos_u1 * sin_u2 - sin_u1 * cos_u2 * cos_lambda);
+        if sin_sigma == 0.0 {
+            // Coincident points
+            return Inverse { distance: 0.0, azimuth: 0.0 };
+        }
+        let cos_sigma = sin_u1 * sin_u2 + cos_u1 * cos_u2 * cos_lambda;
+        let sigma = sin_sigma.atan2(cos_sigma);
+        let sin_alpha = cos_u1 * cos_u2 * sin_lambda / sin_sigma;
+        let cos2_alpha = 1.0 - sin_alpha * sin_alpha;
+        // Equatorial lines have cos2_alpha = 0
+        let cos_2sigma_m = if cos2_alpha != 0.0 {
+            cos_sigma - 2.0 * sin_u1 * sin_u2 / cos2_alpha
+        } else {
+            0.0
+        };
+        let c = f / 16.0 * cos2_alpha * (4.0 + f * (4.0 - 3.0 * cos2_alpha));
+        let previous = lambda;
+        lambda = l + (1.0 - c) * f * sin_alpha
+            * (sigma + c * sin_sigma * (cos_2sigma_m + c * cos_sigma * (-1.0 + 2.0 * cos_2sigma_m * cos_2sigma_m)));
+
+        if (lambda - previous).abs() < TOLERANCE {
+            let (a, b) = series_coefficients(cos2_alpha);
+            let delta_sigma = delta_sigma(b, sin_sigma, cos_sigma, cos_2sigma_m);
+            let (sin_lambda, cos_lambda) = lambda.sin_cos();
+            return Inverse {
+                distance: WGS84_B * a * (sigma - delta_sigma),
+                azimuth: (cos_u2 * sin_lambda).atan2(cos_u1 * sin_u2 - sin_u1 * cos_u2 * cos_lambda),
+            };
+        }
+    }
+
+    spherical_inverse(lat1, lon1, lat2, lon2)
+}
+
+/// Geodesic distance between two points on WGS84 (meters)
+pub fn distance(lat1: f64, lon1: f64, lat2: f64, lon2: f64) -> f64 {
+    inverse(lat1, lon1, lat2, lon2).distance
+}
+
+/// Earth-centered, Earth-fixed coordinates of a point on WGS84 (degrees, meters above the ellipsoid)
+/// X towards (0N, 0E), Y towards (0N, 90E), Z towards the North pole.
+pub fn to_ecef(lat: f64, lon: f64, alt: f64) -> DVec3 {
+    scaled_to_ecef(1.0, lat, lon, alt)
+}
+
+/// `to_ecef` on the WGS84 ellipsoid with both radii multiplied by `scale` (same flattening)
+/// The globe view draws the terrain on the effective Earth this way.
+pub fn scaled_to_ecef(scale: f64, lat: f64, lon: f64, alt: f64) -> DVec3 {
+    let (sin_lat, cos_lat) = lat.to_radians().sin_cos();
+    let (sin_lon, cos_lon) = lon.to_radians().sin_cos();
+    let e2 = WGS84_F * (2.0 - WGS84_F);
+    // Prime vertical radius of curvature
+    let n = WGS84_A * scale / (1.0 - e2 * sin_lat * sin_lat).sqrt();
+    DVec3::new(
+        (n + alt) * cos_lat * cos_lon,
+        (n + alt) * cos_lat * sin_lon,
+        (n * (1.0 - e2) + alt) * sin_lat,
+    )
+}
+
+/// Inverse of `scaled_to_ecef`: (lat, lon, alt) in degrees and meters
+///
+/// ALGORITHM: Fixed-point iteration on geodetic latitude
+/// Converges to sub-millimeter in a few passes for terrain altitudes.
+pub fn scaled_from_ecef(scale: f64, ecef: DVec3) -> DVec3 {
+    let a = WGS84_A * scale;
+    let e2 = WGS84_F * (2.0 - WGS84_F);
+
+    let lon = ecef.y.atan2(ecef.x);
+    let p = ecef.x.hypot(ecef.y);
+    let mut lat = ecef.z.atan2(p * (1.0 - e2));
+    let mut alt = 0.0;
+    for _ in 0..5 {
+        let sin_lat = lat.sin();
+        let n = a / (1.0 - e2 * sin_lat * sin_lat).sqrt();
+        alt = p / lat.cos() - n;
+        lat = ecef.z.atan2(p * (1.0 - e2 * n / (n + alt)));
+    }
+
+    DVec3::new(lat.to_degrees(), lon.to_degrees(), alt)
+}
+
+/// East, north and up unit vectors (ECEF) of the local horizontal frame at a point
+pub fn enu_basis(lat: f64, lon: f64) -> (DVec3, DVec3, DVec3) {
+    let (phi, lambda) = (lat.to_radians(), lon.to_radians());
+    let east = DVec3::new(-lambda.sin(), lambda.cos(), 0.0);
+    let north = DVec3::new(-phi.sin() * lambda.cos(), -phi.sin() * lambda.sin(), phi.cos());
+    let up = DVec3::new(phi.cos() * lambda.cos(), phi.cos() * lambda.sin(), phi.sin());
+    (east, north, up)
+}
+
+/// Great circle on a sphere of mean radius (fallback of the inverse problem)
+fn spherical_inverse(lat1: f64, lon1: f64, lat2: f64, lon2: f64) -> Inverse {
+    let (phi1, phi2) = (lat1.to_radians(), lat2.to_radians());
+    let d_lat = phi2 - phi1;
+    let d_lon = (lon2 - lon1).to_radians();
+    let a = (d_lat / 2.0).sin().powi(2) + phi1.cos() * phi2.cos() * (d_lon / 2.0).sin().powi(2);
+    Inverse {
+        distance: EARTH_RADIUS * 2.0 * a.sqrt().min(1.0).asin(),
+        azimuth: (d_lon.sin() * phi2.cos()).atan2(phi1.cos() * phi2.sin() - phi1.sin() * phi2.cos() * d_lon.cos()),
+    }
+}
+
+/// Vincenty's A and B series coefficients for a line with the given cos^2(alpha)
+fn series_coefficients(cos2_alpha: f64) -> (f64, f64) {
+    let u2 = cos2_alpha * (WGS84_A * WGS84_A - WGS84_B * WGS84_B) / (WGS84_B * WGS84_B);
+    let a = 1.0 + u2 / 16384.0 * (4096.0 + u2 * (-768.0 + u2 * (320.0 - 175.0 * u2)));
+    let b = u2 / 1024.0 * (256.0 + u2 * (-128.0 + u2 * (74.0 - 47.0 * u2)));
+    (a, b)
+}
+
+/// Difference between the arc length on the auxiliary sphere and the scaled distance
+fn delta_sigma(b: f64, sin_sigma: f64, cos_sigma: f64, cos_2sigma_m: f64) -> f64 {
+    let c2 = cos_2sigma_m * cos_2sigma_m;
+    b * sin_sigma
+        * (cos_2sigma_m
+            + b / 4.0
+                * (cos_sigma * (-1.0 + 2.0 * c2)
+                    - b / 6.0 * cos_2sigma_m * (-3.0 + 4.0 * sin_sigma * sin_sigma) * (-3.0 + 4.0 * c2)))
+}
+
+/// A geodesic leaving a point with a given azimuth, for sampling points along it
+///
+/// ALGORITHM: Vincenty's direct formula
+/// Everything that depends only on the start point and azimuth is computed once, so
+/// each `position` call is a short fixed-point iteration. Rays are sampled hundreds of
+/// times per target (and thousands of times per horizon sweep), which keeps that cheap.
+#[derive(Debug, Clone, Copy)]
+pub struct GeodesicLine {
+    lon1: f64,
+    sin_u1: f64,
+    cos_u1: f64,
+    sin_alpha1: f64,
+    cos_alpha1: f64,
+    /// Angular distance on the auxiliary sphere from the equator crossing to the start
+    sigma1: f64,
+    sin_alpha: f64,
+    cos2_alpha: f64,
+    a: f64,
+    b: f64,
+}
+
+impl GeodesicLine {
+    /// Geodesic from (lat, lon) in degrees with `azimuth` in radians clockwise from north
+    pub fn new(lat: f64, lon: f64, azimuth: f64) -> Self {
+        let (sin_alpha1, cos_alpha1) = azimuth.sin_cos();
+        let tan_u1 = (1.0 - WGS84_F) * lat.to_radians().tan();
+        let cos_u1 = 1.0 / (1.0 + tan_u1 * tan_u1).sqrt();
+        let sin_u1 = tan_u1 * cos_u1;
+        let sigma1 = tan_u1.atan2(cos_alpha1);
+        let sin_alpha = cos_u1 * sin_alpha1;
+        let cos2_alpha = 1.0 - sin_alpha * sin_alpha;
+        let (a, b) = series_coefficients(cos2_alpha);
+
+        Self {
+            lon1: lon,
+            sin_u1,
+            cos_u1,
+            sin_alpha1,
+            cos_alpha1,
+            sigma1,
+            sin_alpha,
+            cos2_alpha,
+            a,
+            b,
+        }
+    }
+
+    /// Geodesic from the first point towards the second, and its length (meters)
+    pub fn between(lat1: f64, lon1: f64, lat2: f64, lon2: f64) -> (Self, f64) {
+        let Inverse { distance, azimuth } = inverse(lat1, lon1, lat2, lon2);
+        (Self::new(lat1, lon1, azimuth), distance)
+    }
+
+    /// Latitude and longitude (degrees) at `distance` meters along the line
+    pub fn position(&self, distance: f64) -> (f64, f64) {
+        let f = WGS84_F;
+        let sigma0 = distance / (WGS84_B * self.a);
+
+        // Converges in a few steps; bounded so a NaN distance or azimuth can't spin forever
+        let mut sigma = sigma0;
+        for _ in 0..MAX_ITERATIONS {
+            let cos_2sigma_m = (2.0 * self.sigma1 + sigma).cos();
+            let (sin_sigma, cos_sigma) = sigma.sin_cos();
+            let next = sigma0 + delta_sigma(self.b, sin_sigma, cos_sigma, cos_2sigma_m);
+            let converged = (next - sigma).abs() < TOLERANCE;
+            sigma = next;
+            if converged {
+                break;
+            }
+        }
+        let cos_2sigma_m = (2.0 * self.sigma1 + sigma).cos();
+        let (sin_sigma, cos_sigma) = sigma.sin_cos();
+
+        let x = self.sin_u1 * sin_sigma - self.cos_u1 * cos_sigma * self.cos_alpha1;
+        let lat = (self.sin_u1 * cos_sigma + self.cos_u1 * sin_sigma * self.cos_alpha1)
+            .atan2((1.0 - f) * self.sin_alpha.hypot(x));
+        let lambda = (sin_sigma * self.sin_alpha1)
+            .atan2(self.cos_u1 * cos_sigma - self.sin_u1 * sin_sigma * self.cos_alpha1);
+        let c = f / 16.0 * self.cos2_alpha * (4.0 + f * (4.0 - 3.0 * self.cos2_alpha));
+        let l = lambda
+            - (1.0 - c) * f * self.sin_alpha
+                * (sigma + c * sin_sigma * (cos_2sigma_m + c * cos_sigma * (-1.0 + 2.0 * cos_2sigma_m * cos_2sigma_m)));
+
+        // Back to [-180, 180) past the antimeridian, where the tiles are
+        let lon = (self.lon1 + l.to_degrees() + 180.0).rem_euclid(360.0) - 180.0;
+        (lat.to_degrees(), lon)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dms(d: f64, m: f64, s: f64) -> f64 {
+        d.signum() * (d.abs() + m / 60.0 + s / 3600.0)
+    }
+
+    #[test]
+    fn test_vincenty_reference() {
+        // Flinders Peak -> Buninyong, Vincenty (1975) / Geoscience Australia worked example
+        let (lat1, lon1) = (dms(-37.0, 57.0, 3.72030), dms(144.0, 25.0, 29.52440));
+        let (lat2, lon2) = (dms(-37.0, 39.0, 10.15610), dms(143.0, 55.0, 35.38390));
+
+        let result = inverse(lat1, lon1, lat2, lon2);
+        assert!((result.distance - 54_972.271).abs() < 0.01, "{}", result.distance);
+        let azimuth = result.azimuth.to_degrees().rem_euclid(360.0);
+        assert!((azimuth - dms(306.0, 52.0, 5.37)).abs() < 1e-4, "{}", azimuth);
+
+        let (lat, lon) = GeodesicLine::new(lat1, lon1, result.azimuth).position(result.distance);
+        assert!((lat - lat2).abs() < 1e-8 && (lon - lon2).abs() < 1e-8);
+    }
+
+    #[test]
+    fn test_degenerate_paths() {
+        assert_eq!(distance(43.7, 7.4, 43.7, 7.4), 0.0);
+
+        // Equator: a quarter of the circumference of the ellipsoid
+        let quarter = distance(0.0, 0.0, 0.0, 90.0);
+        assert!((quarter - WGS84_A * std::f64::consts::FRAC_PI_2).abs() < 1e-3);
+
+        // Nearly antipodal points fall back to the sphere instead of failing
+        let antipodal = distance(0.0, 0.0, 0.5, 179.7);
+        assert!(antipodal.is_finite() && antipodal > 19_900_000.0);
+
+        // Invalid input gives NaN instead of hanging the direct iteration
+        let (lat, lon) = GeodesicLine::new(43.7, 7.4, 0.5).position(f64::NAN);
+        assert!(lat.is_nan() && lon.is_nan());
+        assert!(GeodesicLine::new(43.7, 7.4, f64::NAN).position(1_000.0).0.is_nan());
+    }
+
+    #[test]
+    fn test_path_bends_poleward() {
+        // An east-west path at 45N over 470 km: the geodesic bulges ~4 km north of the
+        // parallel, where a linear lat/lon interpolation would stay on it
+        let (line, length) = GeodesicLine::between(45.0, 2.0, 45.0, 8.0);
+        let (mid_lat, mid_lon) = line.position(length / 2.0);
+        assert!((mid_lon - 5.0).abs() < 1e-6);
+        assert!(mid_lat > 45.035, "{}", mid_lat);
+
+        // Points along the line are at the expected distance from the start
+        for k in 1..10 {
+            let s = length * k as f64 / 10.0;
+            let (lat, lon) = line.position(s);
+            assert!((distance(45.0, 2.0, lat, lon) - s).abs() < 1e-3);
+        }
+    }
+
+    #[test]
+    fn test_path_crosses_antimeridian() {
+        // Eastward from 179.9E along the equator: past 180 the longitudes are west
+        let line = GeodesicLine::new(0.0, 179.9, std::f64::consts::FRAC_PI_2);
+        let (lat, lon) = line.position(50_000.0);
+        assert!(lat.abs() < 1e-9 && (-180.0..-179.0).contains(&lon), "{}", lon);
+        assert!((distance(0.0, 179.9, lat, lon) - 50_000.0).abs() < 1e-3);
+        assert_eq!(crate::tile::TileCoord::from_world_coords(lat, lon), crate::tile::TileCoord::new(0, -180));
+
+        // and the inverse problem agrees across it
+        let (line, length) = GeodesicLine::between(10.0, 179.5, 10.0, -179.5);
+        assert!(length < 120_000.0, "{}", length);
+        let (_, mid_lon) = line.position(length / 2.0);
+        assert!((mid_lon.abs() - 180.0).abs() < 1e-6, "{}", mid_lon);
+    }
+
+    #[test]
+    fn test_ecef() {
+        // Equator and pole land on the semi-axes
+        assert!((to_ecef(0.0, 0.0, 0.0) - DVec3::new(WGS84_A, 0.0, 0.0)).length() < 1e-6);
+        assert!((to_ecef(90.0, 0.0, 0.0) - DVec3::new(0.0, 0.0, WGS84_B)).length() < 1e-6);
+
+        // Up is the ellipsoid normal: climbing 1 km moves 1 km along it
+        let (east, north, up) = enu_basis(43.5, 7.2);
+        let climb = to_ecef(43.5, 7.2, 1_000.0) - to_ecef(43.5, 7.2, 0.0);
+        assert!((climb - up * 1_000.0).length() < 1e-6);
+        assert!(east.dot(north).abs() < 1e-12 && east.cross(north).dot(up) > 0.999_999);
+
+        // The scaled ellipsoid of the globe view, and back
+        let scaled = scaled_to_ecef(4.0 / 3.0, 0.0, 90.0, 0.0);
+        assert!((scaled - DVec3::new(0.0, WGS84_A * 4.0 / 3.0, 0.0)).length() < 1e-6);
+        let geo = scaled_from_ecef(1.0, to_ecef(43.5, 7.2, 1_000.0));
+        assert!((geo - DVec3::new(43.5, 7.2, 1_000.0)).abs().max_element() < 1e-6);
+    }
+}
@@ -0,0 +1,1350 @@
+// Longley-Rice Irregular Terrain Model (ITM 1.2.2), point-to-point mode
+//
+// Port of the NTIA/ITS reference implementation (G. Hufford, "The ITS Irregular Terrain
+// Model, version 1.2.2: The Algorithm"). Routine names follow the original (qlrps, qlrpfl,
+// hzns, dlthx, zlsq1, lrprop, alos, adiff, ascat, avar) so the two can be read side by side;
+// the static locals of the original live in `Model`.
+use crate::profile::TerrainProfile;
+
+/// Radio climate (ITM `klim`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RadioClimate {
+    Equatorial = 1,
+    ContinentalSubtropical = 2,
+    MaritimeSubtropical = 3,
+    Desert = 4,
+    #[default]
+    ContinentalTemperate = 5,
+    MaritimeTemperateOverLand = 6,
+    MaritimeTemperateOverSea = 7,
+}
+
+impl RadioClimate {
+    /// Next climate in ITM's numbering (cycled with Shift+P)
+    pub fn next(self) -> Self {
+        match self {
+            Self::Equatorial => Self::ContinentalSubtropical,
+            Self::ContinentalSubtropical => Self::MaritimeSubtropical,
+            Self::MaritimeSubtropical => Self::Desert,
+            Self::Desert => Self::ContinentalTemperate,
+            Self::ContinentalTemperate => Self::MaritimeTemperateOverLand,
+            Self::MaritimeTemperateOverLand => Self::MaritimeTemperateOverSea,
+            Self::MaritimeTemperateOverSea => Self::Equatorial,
+        }
+    }
+}
+
+/// Polarization of the antennas
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Polarization {
+    #[default]
+    Horizontal,
+    Vertical,
+}
+
+/// ITM inputs other than the path
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ItmSettings {
+    pub climate: RadioClimate,
+    pub polarization: Polarization,
+    /// Relative permittivity of the ground (15 for average ground, 80 for sea water)
+    pub permittivity: f64,
+    /// Conductivity of the ground (S/m; 0.005 average ground, 5 sea water)
+    pub conductivity: f64,
+    /// Surface refractivity (N-units, 301 for a standard atmosphere)
+    pub surface_refractivity: f64,
+    /// Fraction of similar paths for which the loss is not exceeded (confidence)
+    pub confidence: f64,
+    /// Fraction of the time for which the loss is not exceeded (reliability)
+    pub reliability: f64,
+}
+
+impl ItmSettings {
+    /// Average ground, continental temperate climate, median loss
+    pub const AVERAGE_GROUND: Self = Self {
+        climate: RadioClimate::ContinentalTemperate,
+        polarization: Polarization::Horizontal,
+        permittivity: 15.0,
+        conductivity: 0.005,
+        surface_refractivity: 301.0,
+        confidence: 0.5,
+        reliability: 0.5,
+    };
+
+    /// Sea water, maritime temperate climate, median loss
+    pub const SEA_WATER: Self = Self {
+        climate: RadioClimate::MaritimeTemperateOverSea,
+        polarization: Polarization::Horizontal,
+        permittivity: 80.0,
+        conductivity: 5.0,
+        surface_refractivity: 301.0,
+        confidence: 0.5,
+        reliability: 0.5,
+    };
+}
+
+impl Default for ItmSettings {
+    fn default() -> Self {
+        Self::AVERAGE_GROUND
+    }
+}
+
+/// Model for the terrain loss of a station's paths
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Propagation {
+    /// Free space plus Deygout knife-edge diffraction over the profile
+    #[default]
+    KnifeEdge,
+    /// Longley-Rice irregular terrain model
+    Itm(ItmSettings),
+}
+
+impl Propagation {
+    /// Next model (cycled with the P key): knife edge, ITM over average ground, ITM over sea
+    pub fn next(self) -> Self {
+        match self {
+            Self::KnifeEdge => Self::Itm(ItmSettings::AVERAGE_GROUND),
+            Self::Itm(settings) if settings == ItmSettings::AVERAGE_GROUND => Self::Itm(ItmSettings::SEA_WATER),
+            Self::Itm(_) => Self::KnifeEdge,
+        }
+    }
+}
+
+impl std::fmt::Display for Propagation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::KnifeEdge => write!(f, "knife-edge diffraction"),
+            Self::Itm(settings) => write!(
+                f,
+                "ITM ({:?}, eps {}, sigma {} S/m, {:?})",
+                settings.climate, settings.permittivity, settings.conductivity, settings.polarization
+            ),
+        }
+    }
+}
+
+/// Propagation mode of a path
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PropagationMode {
+    LineOfSight,
+    /// Beyond one (single) or both (double) horizons, diffraction dominant
+    Diffraction { double_horizon: bool },
+    /// Beyond both horizons, troposcatter dominant
+    Troposcatter { double_horizon: bool },
+}
+
+/// Warning code of predictions with parameters out of range (`ItmResult::warnings`)
+pub const OUT_OF_RANGE: u8 = 4;
+
+/// Result of a point-to-point prediction
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ItmResult {
+    /// Basic transmission loss (dB)
+    pub loss_db: f64,
+    /// Free-space loss over the same distance (dB)
+    pub free_space_db: f64,
+    pub mode: PropagationMode,
+    /// ITM error code `kwx`: 0 valid, 1-3 parameters outside the validated range
+    /// (results questionable), 4 parameters out of range (results meaningless)
+    pub warnings: u8,
+}
+
+impl ItmResult {
+    /// Loss beyond free space (dB)
+    pub fn excess_loss_db(&self) -> f64 {
+        self.loss_db - self.free_space_db
+    }
+}
+
+/// Basic transmission loss between the ends of `profile` (ITM `point_to_point`)
+/// Antenna heights are above the ground at each end (`profile.start_terrain`/`end_terrain`);
+/// the profile's raw terrain is used, ITM applies the Earth's curvature itself.
+pub fn point_to_point(
+    profile: &TerrainProfile,
+    tx_height: f64,
+    rx_height: f64,
+    frequency_mhz: f64,
+    settings: &ItmSettings,
+) -> ItmResult {
+    // ITM profile: [intervals, spacing, elevations including both ends]
+    let elevations: Vec<f64> = std::iter::once(profile.start_terrain)
+        .chain(profile.points.iter().map(|point| point.terrain))
+        .chain(std::iter::once(profile.end_terrain))
+        .collect();
+    let intervals = elevations.len() - 1;
+    let mut pfl = Vec::with_capacity(elevations.len() + 2);
+    pfl.push(intervals as f64);
+    pfl.push(profile.length / intervals as f64);
+    pfl.extend(elevations);
+
+    let mut model = Model::default();
+    model.prop.hg = [tx_height, rx_height];
+    model.propv.klim = settings.climate as i32;
+    model.prop.kwx = 0;
+    model.propv.lvar = 5;
+    model.prop.mdp = -1;
+    let zc = qerfi(settings.confidence);
+    let zr = qerfi(settings.reliability);
+
+    // Mean elevation of the middle of the profile sets the surface refractivity
+    let np = intervals as i64;
+    let ja = (3.0 + 0.1 * pfl[0]) as i64;
+    let jb = np - ja + 6;
+    let mut zsys = 0.0;
+    for i in (ja - 1)..jb {
+        zsys += pfl[i as usize];
+    }
+    zsys /= (jb - ja + 1) as f64;
+
+    model.propv.mdvar = 12;
+    model.qlrps(frequency_mhz, zsys, settings.surface_refractivity, settings.polarization, settings.permittivity, settings.conductivity);
+    model.qlrpfl(&pfl, model.propv.klim, model.propv.mdvar);
+
+    let free_space_db = 32.45 + 20.0 * frequency_mhz.log10() + 20.0 * (model.prop.dist / 1000.0).log10();
+    let q = (model.prop.dist - model.propa.dla) as i64;
+    let mode = if q < 0 {
+        PropagationMode::LineOfSight
+    } else if model.prop.dist <= model.propa.dlsa || model.prop.dist <= model.propa.dx {
+        PropagationMode::Diffraction { double_horizon: q > 0 }
+    } else {
+        PropagationMode::Troposcatter { double_horizon: q > 0 }
+    };
+
+    let loss_db = model.avar(zr, 0.0, zc) + free_space_db;
+    ItmResult { loss_db, free_space_db, mode, warnings: model.prop.kwx as u8 }
+}
+
+/// Inverse of the complementary normal distribution: the z such that Q(z) = q
+/// (Abramowitz and Stegun 26.2.23, |error| < 4.5e-4)
+pub fn qerfi(q: f64) -> f64 {
+    const C0: f64 = 2.515516698;
+    const C1: f64 = 0.802853;
+    const C2: f64 = 0.010328;
+    const D1: f64 = 1.432788;
+    const D2: f64 = 0.189269;
+    const D3: f64 = 0.001308;
+
+    let x = 0.5 - q;
+    let t = (0.5 - x.abs()).max(0.000001);
+    let t = (-2.0 * t.ln()).sqrt();
+    let v = t - ((C2 * t + C1) * t + C0) / (((D3 * t + D2) * t + D1) * t + 1.0);
+    if x < 0.0 {
+        -v
+    } else {
+        v
+    }
+}
+
+const THIRD: f64 = 1.0 / 3.0;
+
+/// Minimal complex arithmetic for the ground impedance and reflection coefficient
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+struct Complex {
+    re: f64,
+    im: f64,
+}
+
+impl Complex {
+    fn new(re: f64, im: f64) -> Self {
+        Self { re, im }
+    }
+
+    fn norm_sqr(self) -> f64 {
+        self.re * self.re + self.im * self.im
+    }
+
+    fn abs(self) -> f64 {
+        self.norm_sqr().sqrt()
+    }
+
+    /// Principal square root
+    fn sqrt(self) -> Self {
+        let r = self.abs();
+        let re = (0.5 * (r + self.re)).max(0.0).sqrt();
+        let im = (0.5 * (r - self.re)).max(0.0).sqrt();
+        Self::new(re, if self.im < 0.0 { -im } else { im })
+    }
+
+    fn scale(self, k: f64) -> Self {
+        Self::new(self.re * k, self.im * k)
+    }
+}
+
+impl std::ops::Add for Complex {
+    type Output = Self;
+    fn add(self, other: Self) -> Self {
+        Self::new(self.re + other.re, self.im + other.im)
+    }
+}
+
+impl std::ops::Sub for Complex {
+    type Output = Self;
+    fn sub(self, other: Self) -> Self {
+        Self::new(self.re - other.re, self.im - other.im)
+    }
+}
+
+impl std::ops::Div for Complex {
+    type Output = Self;
+    fn div(self, other: Self) -> Self {
+        let d = other.norm_sqr();
+        Self::new(
+            (self.re * other.re + self.im * other.im) / d,
+            (self.im * other.re - self.re * other.im) / d,
+        )
+    }
+}
+
+/// Path parameters (`prop_type`)
+#[derive(Debug, Clone, Copy, Default)]
+struct Prop {
+    aref: f64,
+    dist: f64,
+    hg: [f64; 2],
+    wn: f64,
+    dh: f64,
+    ens: f64,
+    gme: f64,
+    zgnd: Complex,
+    he: [f64; 2],
+    dl: [f64; 2],
+    the: [f64; 2],
+    kwx: i32,
+    mdp: i32,
+}
+
+/// Variability parameters (`propv_type`)
+#[derive(Debug, Clone, Copy, Default)]
+struct PropV {
+    sgc: f64,
+    lvar: i32,
+    mdvar: i32,
+    klim: i32,
+}
+
+/// Derived reference attenuation parameters (`propa_type`)
+#[derive(Debug, Clone, Copy, Default)]
+struct PropA {
+    dlsa: f64,
+    dx: f64,
+    ael: f64,
+    ak1: f64,
+    ak2: f64,
+    aed: f64,
+    emd: f64,
+    aes: f64,
+    ems: f64,
+    dls: [f64; 2],
+    dla: f64,
+    tha: f64,
+}
+
+/// Static locals of `adiff`
+#[derive(Debug, Clone, Copy, Default)]
+struct DiffractionState {
+    wd1: f64,
+    xd1: f64,
+    afo: f64,
+    qk: f64,
+    aht: f64,
+    xht: f64,
+}
+
+/// Static locals of `ascat`
+#[derive(Debug, Clone, Copy, Default)]
+struct ScatterState {
+    ad: f64,
+    rr: f64,
+    etq: f64,
+    h0s: f64,
+}
+
+/// Static locals of `lrprop`
+#[derive(Debug, Clone, Copy, Default)]
+struct LrpropState {
+    wlos: bool,
+    wscat: bool,
+    dmin: f64,
+    xae: f64,
+}
+
+/// Static locals of `avar`
+#[derive(Debug, Clone, Copy, Default)]
+struct VariabilityState {
+    kdv: i32,
+    dexa: f64,
+    de: f64,
+    vmd: f64,
+    vs0: f64,
+    sgl: f64,
+    sgtm: f64,
+    sgtp: f64,
+    sgtd: f64,
+    tgtd: f64,
+    gm: f64,
+    gp: f64,
+    cv1: f64,
+    cv2: f64,
+    yv1: f64,
+    yv2: f64,
+    yv3: f64,
+    csm1: f64,
+    csm2: f64,
+    ysm1: f64,
+    ysm2: f64,
+    ysm3: f64,
+    csp1: f64,
+    csp2: f64,
+    ysp1: f64,
+    ysp2: f64,
+    ysp3: f64,
+    csd1: f64,
+    zd: f64,
+    cfm1: f64,
+    cfm2: f64,
+    cfm3: f64,
+    cfp1: f64,
+    cfp2: f64,
+    cfp3: f64,
+    ws: bool,
+    w1: bool,
+}
+
+/// One prediction's worth of ITM state
+#[derive(Debug, Clone, Copy, Default)]
+struct Model {
+    prop: Prop,
+    propv: PropV,
+    propa: PropA,
+    diffraction: DiffractionState,
+    scatter: ScatterState,
+    wls: f64,
+    lrprop: LrpropState,
+    variability: VariabilityState,
+}
+
+/// FORTRAN's positive difference
+fn dim(x: f64, y: f64) -> f64 {
+    if x > y {
+        x - y
+    } else {
+        0.0
+    }
+}
+
+/// Knife-edge attenuation for v^2
+fn aknfe(v2: f64) -> f64 {
+    if v2 < 5.76 {
+        6.02 + 9.11 * v2.sqrt() - 1.27 * v2
+    } else {
+        12.953 + 4.343 * v2.ln()
+    }
+}
+
+/// Height-gain over a smooth spherical Earth
+fn fht(x: f64, pk: f64) -> f64 {
+    if x < 200.0 {
+        let w = -pk.ln();
+        if pk < 1e-5 || x * w.powi(3) > 5495.0 {
+            let mut fhtv = -117.0;
+            if x > 1.0 {
+                fhtv += 17.372 * x.ln();
+            }
+            fhtv
+        } else {
+            2.5e-5 * x * x / pk - 8.686 * w - 15.0
+        }
+    } else {
+        let mut fhtv = 0.05751 * x - 4.343 * x.ln();
+        if x < 2000.0 {
+            let w = 0.0134 * x * (-0.005 * x).exp();
+            fhtv = (1.0 - w) * fhtv + w * (17.372 * x.ln() - 117.0);
+        }
+        fhtv
+    }
+}
+
+/// Scatter frequency gain function
+fn h0f(r: f64, et: f64) -> f64 {
+    const A: [f64; 5] = [25.0, 80.0, 177.0, 395.0, 705.0];
+    const B: [f64; 5] = [24.0, 45.0, 68.0, 80.0, 105.0];
+
+    let mut it = et as i32;
+    let q;
+    if it <= 0 {
+        it = 1;
+        q = 0.0;
+    } else if it >= 5 {
+        it = 5;
+        q = 0.0;
+    } else {
+        q = et - it as f64;
+    }
+    let x = (1.0 / r).powi(2);
+    let i = (it - 1) as usize;
+    let mut h0fv = 4.343 * ((A[i] * x + B[i]) * x + 1.0).ln();
+    if q != 0.0 {
+        h0fv = (1.0 - q) * h0fv + q * 4.343 * ((A[i + 1] * x + B[i + 1]) * x + 1.0).ln();
+    }
+    h0fv
+}
+
+/// Scatter attenuation function F(theta d)
+fn ahd(td: f64) -> f64 {
+    const A: [f64; 3] = [133.4, 104.6, 71.8];
+    const B: [f64; 3] = [0.332e-3, 0.212e-3, 0.157e-3];
+    const C: [f64; 3] = [-4.343, -1.086, 2.171];
+
+    let i = if td <= 10e3 {
+        0
+    } else if td <= 70e3 {
+        1
+    } else {
+        2
+    };
+    A[i] + B[i] * td + C[i] * td.ln()
+}
+
+/// Variability curve fit
+fn curve(c1: f64, c2: f64, x1: f64, x2: f64, x3: f64, de: f64) -> f64 {
+    (c1 + c2 / (1.0 + ((de - x2) / x3).powi(2))) * (de / x1).powi(2) / (1.0 + (de / x1).powi(2))
+}
+
+/// Least squares line through the profile between x1 and x2; its heights at both ends
+fn zlsq1(z: &[f64], x1: f64, x2: f64) -> (f64, f64) {
+    let xn = z[0];
+    let mut xa = dim(x1 / z[1], 0.0).trunc();
+    let mut xb = xn - dim(xn, x2 / z[1]).trunc();
+    if xb <= xa {
+        xa = dim(xa, 1.0);
+        xb = xn - dim(xn, xb + 1.0);
+    }
+    let mut ja = xa as usize;
+    let jb = xb as usize;
+    let n = jb - ja;
+    xa = xb - xa;
+    let mut x = -0.5 * xa;
+    xb += x;
+    let mut a = 0.5 * (z[ja + 2] + z[jb + 2]);
+    let mut b = 0.5 * (z[ja + 2] - z[jb + 2]) * x;
+    for _ in 2..=n {
+        ja += 1;
+        x += 1.0;
+        a += z[ja + 2];
+        b += z[ja + 2] * x;
+    }
+    a /= xa;
+    b = b * 12.0 / ((xa * xa + 2.0) * xa);
+    (a - b * xb, a + b * (xn - xb))
+}
+
+/// The `ir`-th largest of `a` (0-based, clamped)
+fn qtile(a: &[f64], ir: usize) -> f64 {
+    let mut sorted = a.to_vec();
+    sorted.sort_by(|x, y| y.total_cmp(x));
+    sorted[ir.min(sorted.len() - 1)]
+}
+
+/// Interdecile range of the terrain heights between x1 and x2, about a least squares line
+fn dlthx(pfl: &[f64], x1: f64, x2: f64) -> f64 {
+    let np = pfl[0] as usize;
+    let mut xa = x1 / pfl[1];
+    let mut xb = x2 / pfl[1];
+    if xb - xa < 2.0 {
+        return 0.0;
+    }
+    let ka = ((0.1 * (xb - xa + 8.0)) as usize).clamp(4, 25);
+    let n = 10 * ka - 5;
+    let kb = n - ka + 1;
+    let sn = (n - 1) as f64;
+
+    let mut s = vec![0.0; n + 2];
+    s[0] = sn;
+    s[1] = 1.0;
+    xb = (xb - xa) / sn;
+    let mut k = (xa + 1.0) as usize;
+    xa -= k as f64;
+    for j in 0..n {
+        while xa > 0.0 && k < np {
+            xa -= 1.0;
+            k += 1;
+        }
+        s[j + 2] = pfl[k + 2] + (pfl[k + 2] - pfl[k + 1]) * xa;
+        xa += xb;
+    }
+
+    let (z0, zn) = zlsq1(&s, 0.0, sn);
+    let step = (zn - z0) / sn;
+    let mut line = z0;
+    for value in s.iter_mut().skip(2) {
+        *value -= line;
+        line += step;
+    }
+    let dlthxv = qtile(&s[2..], ka - 1) - qtile(&s[2..], kb - 1);
+    dlthxv / (1.0 - 0.8 * (-(x2 - x1) / 50.0e3).exp())
+}
+
+impl Model {
+    /// Frequency, refractivity and ground constants
+    fn qlrps(&mut self, fmhz: f64, zsys: f64, en0: f64, polarization: Polarization, eps: f64, sgm: f64) {
+        const GMA: f64 = 157e-9;
+        let prop = &mut self.prop;
+        prop.wn = fmhz / 47.7;
+        prop.ens = en0;
+        if zsys != 0.0 {
+            prop.ens *= (-zsys / 9460.0).exp();
+        }
+        prop.gme = GMA * (1.0 - 0.04665 * (prop.ens / 179.3).exp());
+        let zq = Complex::new(eps, 376.62 * sgm / prop.wn);
+        let mut zgnd = (zq - Complex::new(1.0, 0.0)).sqrt();
+        if polarization == Polarization::Vertical {
+            zgnd = zgnd / zq;
+        }
+        prop.zgnd = zgnd;
+    }
+
+    /// Horizon distances and elevation angles from the profile
+    fn hzns(&mut self, pfl: &[f64]) {
+        let prop = &mut self.prop;
+        let np = pfl[0] as usize;
+        let xi = pfl[1];
+        let za = pfl[2] + prop.hg[0];
+        let zb = pfl[np + 2] + prop.hg[1];
+        let qc = 0.5 * prop.gme;
+        let mut q = qc * prop.dist;
+        prop.the[1] = (zb - za) / prop.dist;
+        prop.the[0] = prop.the[1] - q;
+        prop.the[1] = -prop.the[1] - q;
+        prop.dl = [prop.dist, prop.dist];
+        if np >= 2 {
+            let mut sa = 0.0;
+            let mut sb = prop.dist;
+            let mut wq = true;
+            for i in 1..np {
+                sa += xi;
+                sb -= xi;
+                q = pfl[i + 2] - (qc * sa + prop.the[0]) * sa - za;
+                if q > 0.0 {
+                    prop.the[0] += q / sa;
+                    prop.dl[0] = sa;
+                    wq = false;
+                }
+                if !wq {
+                    q = pfl[i + 2] - (qc * sb + prop.the[1]) * sb - zb;
+                    if q > 0.0 {
+                        prop.the[1] += q / sb;
+                        prop.dl[1] = sb;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Path geometry from the profile: horizons, terrain irregularity, effective heights
+    fn qlrpfl(&mut self, pfl: &[f64], klimx: i32, mdvarx: i32) {
+        self.prop.dist = pfl[0] * pfl[1];
+        let np = pfl[0] as usize;
+        self.hzns(pfl);
+
+        let prop = &mut self.prop;
+        let mut xl = [0, 1].map(|j| (15.0 * prop.hg[j]).min(0.1 * prop.dl[j]));
+        xl[1] = prop.dist - xl[1];
+        prop.dh = dlthx(pfl, xl[0], xl[1]);
+
+        if prop.dl[0] + prop.dl[1] > 1.5 * prop.dist {
+            // Line of sight: effective heights above a least squares fit of the terrain
+            let (za, zb) = zlsq1(pfl, xl[0], xl[1]);
+            prop.he[0] = prop.hg[0] + dim(pfl[2], za);
+            prop.he[1] = prop.hg[1] + dim(pfl[np + 2], zb);
+            for j in 0..2 {
+                prop.dl[j] = (2.0 * prop.he[j] / prop.gme).sqrt() * (-0.07 * (prop.dh / prop.he[j].max(5.0)).sqrt()).exp();
+            }
+            let q = prop.dl[0] + prop.dl[1];
+            if q <= prop.dist {
+                let q = (prop.dist / q).powi(2);
+                for j in 0..2 {
+                    prop.he[j] *= q;
+                    prop.dl[j] =
+                        (2.0 * prop.he[j] / prop.gme).sqrt() * (-0.07 * (prop.dh / prop.he[j].max(5.0)).sqrt()).exp();
+                }
+            }
+            for j in 0..2 {
+                let q = (2.0 * prop.he[j] / prop.gme).sqrt();
+                prop.the[j] = (0.65 * prop.dh * (q / prop.dl[j] - 1.0) - 2.0 * prop.he[j]) / q;
+            }
+        } else {
+            // Transhorizon: fits over the foreground of each terminal
+            let (za, _) = zlsq1(pfl, xl[0], 0.9 * prop.dl[0]);
+            let (_, zb) = zlsq1(pfl, prop.dist - 0.9 * prop.dl[1], xl[1]);
+            prop.he[0] = prop.hg[0] + dim(pfl[2], za);
+            prop.he[1] = prop.hg[1] + dim(pfl[np + 2], zb);
+        }
+
+        prop.mdp = -1;
+        self.propv.lvar = self.propv.lvar.max(3);
+        if mdvarx >= 0 {
+            self.propv.mdvar = mdvarx;
+            self.propv.lvar = self.propv.lvar.max(4);
+        }
+        if klimx > 0 {
+            self.propv.klim = klimx;
+            self.propv.lvar = 5;
+        }
+        self.lrprop(0.0);
+    }
+
+    /// Line-of-sight attenuation (two-ray with a rough-ground reflection)
+    #[allow(clippy::approx_constant)] // ITM's own rounded constants, kept for identical results
+    fn alos(&mut self, d: f64) -> f64 {
+        let prop = self.prop;
+        let propa = self.propa;
+        if d == 0.0 {
+            self.wls = 0.021 / (0.021 + prop.wn * prop.dh / propa.dlsa.max(10e3));
+            return 0.0;
+        }
+
+        let mut q = (1.0 - 0.8 * (-d / 50e3).exp()) * prop.dh;
+        let s = 0.78 * q * (-(q / 16.0).powf(0.25)).exp();
+        q = prop.he[0] + prop.he[1];
+        let sps = q / (d * d + q * q).sqrt();
+        let mut r = ((Complex::new(sps, 0.0) - prop.zgnd) / (Complex::new(sps, 0.0) + prop.zgnd))
+            .scale((-(prop.wn * s * sps).min(10.0)).exp());
+        q = r.norm_sqr();
+        if q < 0.25 || q < sps {
+            r = r.scale((sps / q).sqrt());
+        }
+        let alosv = propa.emd * d + propa.aed;
+        q = prop.wn * prop.he[0] * prop.he[1] * 2.0 / d;
+        if q > 1.57 {
+            q = 3.14 - 2.4649 / q;
+        }
+        (-4.343 * (Complex::new(q.cos(), -q.sin()) + r).norm_sqr().ln() - alosv) * self.wls + alosv
+    }
+
+    /// Diffraction attenuation (knife edges blended with a smooth rounded Earth)
+    fn adiff(&mut self, d: f64) -> f64 {
+        let prop = self.prop;
+        let propa = self.propa;
+        let st = &mut self.diffraction;
+
+        if d == 0.0 {
+            let mut q = prop.hg[0] * prop.hg[1];
+            st.qk = prop.he[0] * prop.he[1] - q;
+            if prop.mdp < 0 {
+                q += 10.0;
+            }
+            st.wd1 = (1.0 + st.qk / q).sqrt();
+            st.xd1 = propa.dla + propa.tha / prop.gme;
+            q = (1.0 - 0.8 * (-propa.dlsa / 50e3).exp()) * prop.dh;
+            q *= 0.78 * (-(q / 16.0).powf(0.25)).exp();
+            st.afo = (2.171 * (1.0 + 4.77e-4 * prop.hg[0] * prop.hg[1] * prop.wn * q).ln()).min(15.0);
+            st.qk = 1.0 / prop.zgnd.abs();
+            st.aht = 20.0;
+            st.xht = 0.0;
+            for j in 0..2 {
+                let a = 0.5 * prop.dl[j].powi(2) / prop.he[j];
+                let wa = (a * prop.wn).powf(THIRD);
+                let pk = st.qk / wa;
+                let q = (1.607 - pk) * 151.0 * wa * prop.dl[j] / a;
+                st.xht += q;
+                st.aht += fht(q, pk);
+            }
+            return 0.0;
+        }
+
+        let th = propa.tha + d * prop.gme;
+        let ds = d - propa.dla;
+        let mut q = 0.0795775 * prop.wn * ds * th.powi(2);
+        let knife = aknfe(q * prop.dl[0] / (ds + prop.dl[0])) + aknfe(q * prop.dl[1] / (ds + prop.dl[1]));
+        let a = ds / th;
+        let wa = (a * prop.wn).powf(THIRD);
+        let pk = st.qk / wa;
+        q = (1.607 - pk) * 151.0 * wa * th + st.xht;
+        let ar = 0.05751 * q - 4.343 * q.ln() - st.aht;
+        q = (st.wd1 + st.xd1 / d) * ((1.0 - 0.8 * (-d / 50e3).exp()) * prop.dh * prop.wn).min(6283.2);
+        let wd = 25.1 / (25.1 + q.sqrt());
+        ar * wd + (1.0 - wd) * knife + st.afo
+    }
+
+    /// Troposcatter attenuation (1001 when the scatter volume is too small to matter)
+    #[allow(clippy::approx_constant)] // ITM's own rounded constants, kept for identical results
+    fn ascat(&mut self, d: f64) -> f64 {
+        let prop = self.prop;
+        let propa = self.propa;
+        let st = &mut self.scatter;
+
+        if d == 0.0 {
+            st.ad = prop.dl[0] - prop.dl[1];
+            st.rr = prop.he[1] / prop.he[0];
+            if st.ad < 0.0 {
+                st.ad = -st.ad;
+                st.rr = 1.0 / st.rr;
+            }
+            st.etq = (5.67e-6 * prop.ens - 2.32e-3) * prop.ens + 0.031;
+            st.h0s = -15.0;
+            return 0.0;
+        }
+
+        let h0 = if st.h0s > 15.0 {
+            st.h0s
+        } else {
+            let th = prop.the[0] + prop.the[1] + d * prop.gme;
+            let mut r2 = 2.0 * prop.wn * th;
+            let r1 = r2 * prop.he[0];
+            r2 *= prop.he[1];
+            if r1 < 0.2 && r2 < 0.2 {
+                return 1001.0;
+            }
+            let mut ss = (d - st.ad) / (d + st.ad);
+            let mut q = st.rr / ss;
+            ss = ss.max(0.1);
+            q = q.clamp(0.1, 10.0);
+            let z0 = (d - st.ad) * (d + st.ad) * th * 0.25 / d;
+            let et = (st.etq * (-(z0 / 8.0e3).min(1.7).powi(6)).exp() + 1.0) * z0 / 1.7556e3;
+            let ett = et.max(1.0);
+            let mut h0 = (h0f(r1, ett) + h0f(r2, ett)) * 0.5;
+            h0 += h0.min((1.38 - ett.ln()) * ss.ln() * q.ln() * 0.49);
+            h0 = dim(h0, 0.0);
+            if et < 1.0 {
+                h0 = et * h0
+                    + (1.0 - et)
+                        * 4.343
+                        * (((1.0 + 1.4142 / r1) * (1.0 + 1.4142 / r2)).powi(2) * (r1 + r2) / (r1 + r2 + 2.8284)).ln();
+            }
+            if h0 > 15.0 && st.h0s >= 0.0 {
+                h0 = st.h0s;
+            }
+            h0
+        };
+        st.h0s = h0;
+        let th = propa.tha + d * prop.gme;
+        ahd(th * d) + 4.343 * (47.7 * prop.wn * th.powi(4)).ln() - 0.1 * (prop.ens - 301.0) * (-th * d / 40e3).exp() + h0
+    }
+
+    /// Reference attenuation at distance `d` (line of sight, diffraction or scatter)
+    fn lrprop(&mut self, d: f64) {
+        if self.prop.mdp != 0 {
+            let prop = &mut self.prop;
+            let propa = &mut self.propa;
+            for j in 0..2 {
+                propa.dls[j] = (2.0 * prop.he[j] / prop.gme).sqrt();
+            }
+            propa.dlsa = propa.dls[0] + propa.dls[1];
+            propa.dla = prop.dl[0] + prop.dl[1];
+            propa.tha = (prop.the[0] + prop.the[1]).max(-propa.dla * prop.gme);
+            self.lrprop.wlos = false;
+            self.lrprop.wscat = false;
+
+            // Validity of the parameters
+            if prop.wn < 0.838 || prop.wn > 210.0 {
+                prop.kwx = prop.kwx.max(1);
+            }
+            for j in 0..2 {
+                if prop.hg[j] < 1.0 || prop.hg[j] > 1000.0 {
+                    prop.kwx = prop.kwx.max(1);
+                }
+            }
+            for j in 0..2 {
+                if prop.the[j].abs() > 200e-3 || prop.dl[j] < 0.1 * propa.dls[j] || prop.dl[j] > 3.0 * propa.dls[j] {
+                    prop.kwx = prop.kwx.max(3);
+                }
+            }
+            if prop.ens < 250.0
+                || prop.ens > 400.0
+                || prop.gme < 75e-9
+                || prop.gme > 250e-9
+                || prop.zgnd.re <= prop.zgnd.im.abs()
+                || prop.wn < 0.419
+                || prop.wn > 420.0
+            {
+                prop.kwx = 4;
+            }
+            for j in 0..2 {
+                if prop.hg[j] < 0.5 || prop.hg[j] > 3000.0 {
+                    prop.kwx = 4;
+                }
+            }
+
+            self.lrprop.dmin = (prop.he[0] - prop.he[1]).abs() / 200e-3;
+            self.adiff(0.0);
+            let prop = &self.prop;
+            self.lrprop.xae = (prop.wn * prop.gme.powi(2)).powf(-THIRD);
+            let d3 = self.propa.dlsa.max(1.3787 * self.lrprop.xae + self.propa.dla);
+            let d4 = d3 + 2.7574 * self.lrprop.xae;
+            let a3 = self.adiff(d3);
+            let a4 = self.adiff(d4);
+            self.propa.emd = (a4 - a3) / (d4 - d3);
+            self.propa.aed = a3 - self.propa.emd * d3;
+        }
+
+        if self.prop.mdp >= 0 {
+            self.prop.mdp = 0;
+            self.prop.dist = d;
+        }
+
+        let prop = &mut self.prop;
+        if prop.dist > 0.0 {
+            if prop.dist > 1000e3 {
+                prop.kwx = prop.kwx.max(1);
+            }
+            if prop.dist < self.lrprop.dmin {
+                prop.kwx = prop.kwx.max(3);
+            }
+            if prop.dist < 1e3 || prop.dist > 2000e3 {
+                prop.kwx = 4;
+            }
+        }
+
+        if self.prop.dist < self.propa.dlsa {
+            if !self.lrprop.wlos {
+                // Fit A_los = ael + ak1 d + ak2 ln(d) through three line-of-sight points
+                self.alos(0.0);
+                let propa = self.propa;
+                let d2 = propa.dlsa;
+                let a2 = propa.aed + d2 * propa.emd;
+                let mut d0 = 1.908 * self.prop.wn * self.prop.he[0] * self.prop.he[1];
+                let d1;
+                if propa.aed >= 0.0 {
+                    d0 = d0.min(0.5 * propa.dla);
+                    d1 = d0 + 0.25 * (propa.dla - d0);
+                } else {
+                    d1 = (-propa.aed / propa.emd).max(0.25 * propa.dla);
+                }
+                let a1 = self.alos(d1);
+                let mut wq = false;
+                if d0 < d1 {
+                    let a0 = self.alos(d0);
+                    let q = (d2 / d0).ln();
+                    let propa = &mut self.propa;
+                    propa.ak2 = (((d2 - d0) * (a1 - a0) - (d1 - d0) * (a2 - a0)) / ((d2 - d0) * (d1 / d0).ln() - (d1 - d0) * q)).max(0.0);
+                    wq = propa.aed >= 0.0 || propa.ak2 > 0.0;
+                    if wq {
+                        propa.ak1 = (a2 - a0 - propa.ak2 * q) / (d2 - d0);
+                        if propa.ak1 < 0.0 {
+                            propa.ak1 = 0.0;
+                            propa.ak2 = dim(a2, a0) / q;
+                            if propa.ak2 == 0.0 {
+                                propa.ak1 = propa.emd;
+                            }
+                        }
+                    }
+                }
+                let propa = &mut self.propa;
+                if !wq {
+                    propa.ak1 = dim(a2, a1) / (d2 - d1);
+                    propa.ak2 = 0.0;
+                    if propa.ak1 == 0.0 {
+                        propa.ak1 = propa.emd;
+                    }
+                }
+                propa.ael = a2 - propa.ak1 * d2 - propa.ak2 * d2.ln();
+                self.lrprop.wlos = true;
+            }
+            if self.prop.dist > 0.0 {
+                let propa = &self.propa;
+                self.prop.aref = propa.ael + propa.ak1 * self.prop.dist + propa.ak2 * self.prop.dist.ln();
+            }
+        }
+
+        if self.prop.dist <= 0.0 || self.prop.dist >= self.propa.dlsa {
+            if !self.lrprop.wscat {
+                // Fit the scatter line and find where it takes over from diffraction
+                self.ascat(0.0);
+                let d5 = self.propa.dla + 200e3;
+                let d6 = d5 + 200e3;
+                let a6 = self.ascat(d6);
+                let a5 = self.ascat(d5);
+                let xae = self.lrprop.xae;
+                let wn = self.prop.wn;
+                let propa = &mut self.propa;
+                if a5 < 1000.0 {
+                    propa.ems = (a6 - a5) / 200e3;
+                    propa.dx = propa
+                        .dlsa
+                        .max(propa.dla + 0.3 * xae * (47.7 * wn).ln())
+                        .max((a5 - propa.aed - propa.ems * d5) / (propa.emd - propa.ems));
+                    propa.aes = (propa.emd - propa.ems) * propa.dx + propa.aed;
+                } else {
+                    propa.ems = propa.emd;
+                    propa.aes = propa.aed;
+                    propa.dx = 10.0e6;
+                }
+                self.lrprop.wscat = true;
+            }
+            let propa = &self.propa;
+            self.prop.aref = if self.prop.dist > propa.dx {
+                propa.aes + propa.ems * self.prop.dist
+            } else {
+                propa.aed + propa.emd * self.prop.dist
+            };
+        }
+        self.prop.aref = self.prop.aref.max(0.0);
+    }
+
+    /// Attenuation with time, location and situation variability at the given quantiles
+    fn avar(&mut self, zzt: f64, zzl: f64, zzc: f64) -> f64 {
+        const BV1: [f64; 7] = [-9.67, -0.62, 1.26, -9.21, -0.62, -0.39, 3.15];
+        const BV2: [f64; 7] = [12.7, 9.19, 15.5, 9.05, 9.19, 2.86, 857.9];
+        const XV1: [f64; 7] = [144.9e3, 228.9e3, 262.6e3, 84.1e3, 228.9e3, 141.7e3, 2222.0e3];
+        const XV2: [f64; 7] = [190.3e3, 205.2e3, 185.2e3, 101.1e3, 205.2e3, 315.9e3, 164.8e3];
+        const XV3: [f64; 7] = [133.8e3, 143.6e3, 99.8e3, 98.6e3, 143.6e3, 167.4e3, 116.3e3];
+        const BSM1: [f64; 7] = [2.13, 2.66, 6.11, 1.98, 2.68, 6.86, 8.51];
+        const BSM2: [f64; 7] = [159.5, 7.67, 6.65, 13.11, 7.16, 10.38, 169.8];
+        const XSM1: [f64; 7] = [762.2e3, 100.4e3, 138.2e3, 139.1e3, 93.7e3, 187.8e3, 609.8e3];
+        const XSM2: [f64; 7] = [123.6e3, 172.5e3, 242.2e3, 132.7e3, 186.8e3, 169.6e3, 119.9e3];
+        const XSM3: [f64; 7] = [94.5e3, 136.4e3, 178.6e3, 193.5e3, 133.5e3, 108.9e3, 106.6e3];
+        const BSP1: [f64; 7] = [2.11, 6.87, 10.08, 3.68, 4.75, 8.58, 8.43];
+        const BSP2: [f64; 7] = [102.3, 15.53, 9.60, 159.3, 8.12, 13.97, 8.19];
+        const XSP1: [f64; 7] = [636.9e3, 138.7e3, 165.3e3, 464.4e3, 93.2e3, 216.0e3, 136.2e3];
+        const XSP2: [f64; 7] = [134.8e3, 143.7e3, 225.7e3, 93.1e3, 135.9e3, 152.0e3, 188.5e3];
+        const XSP3: [f64; 7] = [95.6e3, 98.6e3, 129.7e3, 94.2e3, 113.4e3, 122.7e3, 122.9e3];
+        const BSD1: [f64; 7] = [1.224, 0.801, 1.380, 1.000, 1.224, 1.518, 1.518];
+        const BZD1: [f64; 7] = [1.282, 2.161, 1.282, 20.0, 1.282, 1.282, 1.282];
+        const BFM1: [f64; 7] = [1.0, 1.0, 1.0, 1.0, 0.92, 1.0, 1.0];
+        const BFM2: [f64; 7] = [0.0, 0.0, 0.0, 0.0, 0.25, 0.0, 0.0];
+        const BFM3: [f64; 7] = [0.0, 0.0, 0.0, 0.0, 1.77, 0.0, 0.0];
+        const BFP1: [f64; 7] = [1.0, 0.93, 1.0, 0.93, 0.93, 1.0, 1.0];
+        const BFP2: [f64; 7] = [0.0, 0.31, 0.0, 0.19, 0.31, 0.0, 0.0];
+        const BFP3: [f64; 7] = [0.0, 2.00, 0.0, 1.79, 2.00, 0.0, 0.0];
+        const RT: f64 = 7.8;
+        const RL: f64 = 24.0;
+
+        let prop = &mut self.prop;
+        let propv = &mut self.propv;
+        let st = &mut self.variability;
+
+        // The original falls through its switch from `lvar` down to 1
+        if propv.lvar > 0 {
+            if propv.lvar >= 5 {
+                if propv.klim <= 0 || propv.klim > 7 {
+                    propv.klim = 5;
+                    prop.kwx = prop.kwx.max(2);
+                }
+                let k = (propv.klim - 1) as usize;
+                st.cv1 = BV1[k];
+                st.cv2 = BV2[k];
+                st.yv1 = XV1[k];
+                st.yv2 = XV2[k];
+                st.yv3 = XV3[k];
+                st.csm1 = BSM1[k];
+                st.csm2 = BSM2[k];
+                st.ysm1 = XSM1[k];
+                st.ysm2 = XSM2[k];
+                st.ysm3 = XSM3[k];
+                st.csp1 = BSP1[k];
+                st.csp2 = BSP2[k];
+                st.ysp1 = XSP1[k];
+                st.ysp2 = XSP2[k];
+                st.ysp3 = XSP3[k];
+                st.csd1 = BSD1[k];
+                st.zd = BZD1[k];
+                st.cfm1 = BFM1[k];
+                st.cfm2 = BFM2[k];
+                st.cfm3 = BFM3[k];
+                st.cfp1 = BFP1[k];
+                st.cfp2 = BFP2[k];
+                st.cfp3 = BFP3[k];
+            }
+            if propv.lvar >= 4 {
+                st.kdv = propv.mdvar;
+                st.ws = st.kdv >= 20;
+                if st.ws {
+                    st.kdv -= 20;
+                }
+                st.w1 = st.kdv >= 10;
+                if st.w1 {
+                    st.kdv -= 10;
+                }
+                if st.kdv < 0 || st.kdv > 3 {
+                    st.kdv = 0;
+                    prop.kwx = prop.kwx.max(2);
+                }
+            }
+            if propv.lvar >= 3 {
+                let q = (0.133 * prop.wn).ln();
+                st.gm = st.cfm1 + st.cfm2 / ((st.cfm3 * q).powi(2) + 1.0);
+                st.gp = st.cfp1 + st.cfp2 / ((st.cfp3 * q).powi(2) + 1.0);
+            }
+            if propv.lvar >= 2 {
+                st.dexa = (18e6 * prop.he[0]).sqrt() + (18e6 * prop.he[1]).sqrt() + (575.7e12 / prop.wn).powf(THIRD);
+            }
+            st.de = if prop.dist < st.dexa {
+                130e3 * prop.dist / st.dexa
+            } else {
+                130e3 + prop.dist - st.dexa
+            };
+
+            st.vmd = curve(st.cv1, st.cv2, st.yv1, st.yv2, st.yv3, st.de);
+            st.sgtm = curve(st.csm1, st.csm2, st.ysm1, st.ysm2, st.ysm3, st.de) * st.gm;
+            st.sgtp = curve(st.csp1, st.csp2, st.ysp1, st.ysp2, st.ysp3, st.de) * st.gp;
+            st.sgtd = st.sgtp * st.csd1;
+            st.tgtd = (st.sgtp - st.sgtd) * st.zd;
+            st.sgl = if st.w1 {
+                0.0
+            } else {
+                let q = (1.0 - 0.8 * (-prop.dist / 50e3).exp()) * prop.dh * prop.wn;
+                10.0 * q / (q + 13.0)
+            };
+            st.vs0 = if st.ws { 0.0 } else { (5.0 + 3.0 * (-st.de / 100e3).exp()).powi(2) };
+            propv.lvar = 0;
+        }
+
+        let (mut zt, mut zl, zc) = (zzt, zzl, zzc);
+        match st.kdv {
+            0 => {
+                zt = zc;
+                zl = zc;
+            }
+            1 => zl = zc,
+            2 => zl = zt,
+            _ => {}
+        }
+        if zt.abs() > 3.1 || zl.abs() > 3.1 || zc.abs() > 3.1 {
+            prop.kwx = prop.kwx.max(1);
+        }
+        let sgt = if zt < 0.0 {
+            st.sgtm
+        } else if zt <= st.zd {
+            st.sgtp
+        } else {
+            st.sgtd + st.tgtd / zt
+        };
+        let vs = st.vs0 + (sgt * zt).powi(2) / (RT + zc * zc) + (st.sgl * zl).powi(2) / (RL + zc * zc);
+        let yr = match st.kdv {
+            0 => {
+                propv.sgc = (sgt * sgt + st.sgl * st.sgl + vs).sqrt();
+                0.0
+            }
+            1 => {
+                propv.sgc = (st.sgl * st.sgl + vs).sqrt();
+                sgt * zt
+            }
+            2 => {
+                propv.sgc = vs.sqrt();
+                (sgt * sgt + st.sgl * st.sgl).sqrt() * zt
+            }
+            _ => {
+                propv.sgc = vs.sqrt();
+                sgt * zt + st.sgl * zl
+            }
+        };
+        let mut avarv = prop.aref - st.vmd - yr - propv.sgc * zc;
+        if avarv < 0.0 {
+            avarv = avarv * (29.0 - avarv) / (29.0 - 10.0 * avarv);
+        }
+        avarv
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::profile::ProfilePoint;
+    use crate::radar::SPEED_OF_LIGHT;
+    use std::f64::consts::PI;
+
+    /// Profile of `n` intervals over `length` with the given terrain heights
+    fn profile(length: f64, heights: &[f64]) -> TerrainProfile {
+        let n = heights.len() - 1;
+        let points = (1..n)
+            .map(|i| ProfilePoint { distance: length * i as f64 / n as f64, terrain: heights[i], ground: heights[i] })
+            .collect();
+        TerrainProfile {
+            length,
+            start_height: heights[0],
+            end_height: heights[n],
+            start_terrain: heights[0],
+            end_terrain: heights[n],
+            points,
+        }
+    }
+
+    #[test]
+    fn test_normal_quantiles() {
+        // Standard normal table: Q(1.2816) = 0.1, Q(2.3263) = 0.01, Q(0) = 0.5
+        assert!((qerfi(0.1) - 1.2816).abs() < 5e-4);
+        assert!((qerfi(0.01) - 2.3263).abs() < 5e-4);
+        assert!((qerfi(0.9) + 1.2816).abs() < 5e-4);
+        assert!(qerfi(0.5).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_free_space_reference() {
+        // 32.45 + 20 log f(MHz) + 20 log d(km): 100 MHz over 10 km is 92.45 dB
+        let flat = profile(10_000.0, &[0.0; 101]);
+        let result = point_to_point(&flat, 30.0, 30.0, 100.0, &ItmSettings::default());
+        assert!((result.free_space_db - 92.45).abs() < 0.01);
+        assert_eq!(result.mode, PropagationMode::LineOfSight);
+        assert_eq!(result.warnings, 0);
+
+        // Over smooth ground the line-of-sight loss follows the two-ray model:
+        // excess = -20 log10(2 sin(2 pi h1 h2 / (lambda d))), 8.5 dB here (near a null)
+        let lambda = SPEED_OF_LIGHT / 100e6;
+        let two_ray = -20.0 * (2.0 * (2.0 * PI * 30.0 * 30.0 / (lambda * 10_000.0)).sin()).log10();
+        assert!((result.excess_loss_db() - two_ray).abs() < 1.5, "{:?} vs {:.1}", result, two_ray);
+
+        // Higher antennas move to a two-ray peak: up to 6 dB better than free space
+        let peak = point_to_point(&flat, 130.0, 130.0, 100.0, &ItmSettings::default());
+        assert!(peak.excess_loss_db() < 0.0 && peak.excess_loss_db() > -6.5, "{:?}", peak);
+    }
+
+    #[test]
+    fn test_modes_and_distance() {
+        let settings = ItmSettings::default();
+        // Smooth Earth, 10 m masts at 300 MHz: horizons ~13 km each
+        let losses: Vec<ItmResult> = [5_000.0, 40_000.0, 120_000.0, 400_000.0]
+            .iter()
+            .map(|length| point_to_point(&profile(*length, &[0.0; 201]), 10.0, 10.0, 300.0, &settings))
+            .collect();
+        assert_eq!(losses[0].mode, PropagationMode::LineOfSight);
+        assert!(matches!(losses[1].mode, PropagationMode::Diffraction { .. }));
+        assert!(matches!(losses[3].mode, PropagationMode::Troposcatter { .. }));
+        // Beyond the horizon the loss exceeds free space, and keeps growing with distance
+        assert!(losses.windows(2).all(|pair| pair[0].loss_db < pair[1].loss_db), "{:?}", losses);
+        assert!(losses[1].excess_loss_db() > 10.0 && losses[2].excess_loss_db() > losses[1].excess_loss_db());
+    }
+
+    #[test]
+    fn test_ridge_and_settings() {
+        // A 300 m ridge half-way along a 50 km path adds loss
+        let mut heights = [100.0; 101];
+        let flat = point_to_point(&profile(50_000.0, &heights), 20.0, 20.0, 1_000.0, &ItmSettings::default());
+        heights[50] = 400.0;
+        let ridge = point_to_point(&profile(50_000.0, &heights), 20.0, 20.0, 1_000.0, &ItmSettings::default());
+        assert!(ridge.loss_db > flat.loss_db + 10.0, "{:?} vs {:?}", ridge, flat);
+
+        // Losses exceeded only 10% of the time are lower than median ones
+        let optimistic = ItmSettings { reliability: 0.1, ..ItmSettings::default() };
+        let pessimistic = ItmSettings { reliability: 0.9, ..ItmSettings::default() };
+        let low = point_to_point(&profile(50_000.0, &heights), 20.0, 20.0, 1_000.0, &optimistic);
+        let high = point_to_point(&profile(50_000.0, &heights), 20.0, 20.0, 1_000.0, &pessimistic);
+        assert!(low.loss_db < ridge.loss_db && ridge.loss_db < high.loss_db);
+
+        // Aircraft heights are outside ITM's range: flagged, not refused
+        let aircraft = point_to_point(&profile(50_000.0, &heights), 20.0, 6_000.0, 1_000.0, &ItmSettings::default());
+        assert_eq!(aircraft.warnings, OUT_OF_RANGE);
+    }
+
+    /// Smooth-earth diffraction loss beyond free space between two antennas of height `h`
+    /// (meters) `d` meters apart at `f` MHz over an effective Earth of radius `ae` km:
+    /// ITU-R P.526 section 3.1.1, for horizontal polarization or UHF over land (beta = 1)
+    fn p526_smooth_earth(f: f64, h: f64, d: f64, ae: f64) -> f64 {
+        let x = 2.188 * f.powf(1.0 / 3.0) * ae.powf(-2.0 / 3.0) * d / 1000.0;
+        let y = 9.575e-3 * f.powf(2.0 / 3.0) * ae.powf(-1.0 / 3.0) * h;
+        let distance_term = if x >= 1.6 {
+            11.0 + 10.0 * x.log10() - 17.6 * x
+        } else {
+            -20.0 * x.log10() - 5.6488 * x.powf(1.425)
+        };
+        let height_gain = if y > 2.0 {
+            17.6 * (y - 1.1).sqrt() - 5.0 * (y - 1.1).log10() - 8.0
+        } else {
+            20.0 * (y + 0.1 * y.powi(3)).log10()
+        };
+        -(distance_term + 2.0 * height_gain)
+    }
+
+    #[test]
+    fn test_smooth_earth_reference() {
+        // Beyond the horizon of smooth ground ITM's diffraction follows the residue series
+        // of ITU-R P.526 to within 1.5 dB (median, continental temperate; the default
+        // surface refractivity of 301 N gives ITM an effective radius of ~8490 km)
+        let cases = [
+            (300.0, 10.0, 30_000.0),
+            (300.0, 10.0, 40_000.0),
+            (300.0, 10.0, 60_000.0),
+            (300.0, 30.0, 60_000.0),
+            (300.0, 30.0, 80_000.0),
+            (1_000.0, 10.0, 30_000.0),
+            (1_000.0, 10.0, 60_000.0),
+            (1_000.0, 50.0, 60_000.0),
+            (1_000.0, 50.0, 80_000.0),
+            (3_000.0, 20.0, 40_000.0),
+            (3_000.0, 20.0, 60_000.0),
+        ];
+        for (f, h, d) in cases {
+            let result = point_to_point(&profile(d, &[0.0; 201]), h, h, f, &ItmSettings::default());
+            let reference = p526_smooth_earth(f, h, d, 8_490.0);
+            assert!(matches!(result.mode, PropagationMode::Diffraction { .. }), "{:?}", result);
+            assert!(
+                (result.excess_loss_db() - reference).abs() < 1.5,
+                "{} MHz, {} m, {} km: {:.1} dB vs {:.1} dB",
+                f,
+                h,
+                d / 1000.0,
+                result.excess_loss_db(),
+                reference
+            );
+        }
+
+        // Farther out troposcatter takes over and the loss grows more slowly than diffraction
+        let scatter = point_to_point(&profile(100_000.0, &[0.0; 201]), 10.0, 10.0, 1_000.0, &ItmSettings::default());
+        assert!(matches!(scatter.mode, PropagationMode::Troposcatter { .. }));
+        assert!(scatter.excess_loss_db() < p526_smooth_earth(1_000.0, 10.0, 100_000.0, 8_490.0));
+    }
+
+    /// Published NTIA/ITS point-to-point cases, transcribed from the QKPFL example of the
+    /// ITM 1.2.2 distribution (Crystal Palace to Mursley: the example profile, its inputs and
+    /// the table of losses by confidence and reliability). One `key values...` line each:
+    ///     frequency <MHz>
+    ///     heights <tx m> <rx m>
+    ///     ground <permittivity> <conductivity S/m>
+    ///     refractivity <N-units>
+    ///     climate <1-7>
+    ///     profile <intervals> <spacing m> <elevations m, both ends included...>
+    ///     loss <confidence> <reliability> <dB>
+    /// Blank lines and lines starting with '#' are skipped.
+    const REFERENCE_CASES: &str = "testdata/itm_qkpfl.txt";
+
+    #[test]
+    #[ignore = "needs the QKPFL profile and loss table in testdata/itm_qkpfl.txt"]
+    fn test_ntia_reference_cases() {
+        let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join(REFERENCE_CASES);
+        let text = std::fs::read_to_string(&path).unwrap_or_else(|e| panic!("{:?}: {}", path, e));
+
+        let mut settings = ItmSettings::default();
+        let (mut frequency, mut heights, mut terrain) = (None, None, None);
+        let mut expected = Vec::new();
+        for line in text.lines().map(str::trim).filter(|line| !line.is_empty() && !line.starts_with('#')) {
+            let mut fields = line.split_whitespace();
+            let key = fields.next().unwrap();
+            let values: Vec<f64> = fields.map(|field| field.parse().unwrap_or_else(|_| panic!("bad number in {:?}", line))).collect();
+            match (key, values.as_slice()) {
+                ("frequency", [mhz]) => frequency = Some(*mhz),
+                ("heights", [tx, rx]) => heights = Some((*tx, *rx)),
+                ("ground", [permittivity, conductivity]) => {
+                    settings.permittivity = *permittivity;
+                    settings.conductivity = *conductivity;
+                }
+                ("refractivity", [n]) => settings.surface_refractivity = *n,
+                ("climate", [klim]) => {
+                    settings.climate = std::iter::successors(Some(RadioClimate::Equatorial), |c| Some(c.next()))
+                        .find(|climate| *climate as i32 as f64 == *klim)
+                        .unwrap();
+                }
+                ("profile", [intervals, spacing, elevations @ ..]) => {
+                    assert_eq!(elevations.len(), *intervals as usize + 1, "profile length");
+                    terrain = Some(profile(intervals * spacing, elevations));
+                }
+                ("loss", [confidence, reliability, db]) => expected.push((*confidence, *reliability, *db)),
+                _ => panic!("unexpected line {:?}", line),
+            }
+        }
+
+        let (frequency, (tx, rx), terrain) = (frequency.unwrap(), heights.unwrap(), terrain.unwrap());
+        assert!(!expected.is_empty());
+        for (confidence, reliability, db) in expected {
+            let case = ItmSettings { confidence, reliability, ..settings };
+            let result = point_to_point(&terrain, tx, rx, frequency, &case);
+            // The published table is printed to 0.1 dB
+            assert!(
+                (result.loss_db - db).abs() <= 0.1,
+                "confidence {}, reliability {}: {:.2} dB vs {:.1} dB",
+                confidence,
+                reliability,
+                result.loss_db,
+                db
+            );
+        }
+    }
+}
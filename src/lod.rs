@@ -1,5 +1,6 @@
 // Level of Detail management
 use bevy::prelude::*;
+use crate::world::WorldPosition;
 
 /// LOD manager resource
 #[derive(Resource)]
@@ -54,10 +55,10 @@ impl LodManager {
 /// System to update LOD based on camera
 pub fn update_lod_system(
     mut lod_manager: ResMut<LodManager>,
-    camera_query: Query<&Transform, With<Camera>>,
+    camera_query: Query<&WorldPosition, With<Camera>>,
 ) {
-    if let Ok(camera_transform) = camera_query.single() {
-        let camera_height = camera_transform.translation.y.abs();
+    if let Ok(camera_pos) = camera_query.single() {
+        let camera_height = camera_pos.0.y.abs() as f32;
         let new_level = lod_manager.calculate_lod(camera_height);
         
         // Only mutate if actually changed to avoid triggering change detection
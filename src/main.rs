@@ -1,17 +1,38 @@
+mod antenna;
+mod blockage;
 mod cache;
 mod camera;
 mod colormap;
+mod coverage;
+mod detection;
 mod downloader;
+mod geodesy;
+mod horizon;
+mod itm;
 mod lod;
+mod mda;
+mod mlat;
+mod placement;
+mod profile;
 mod mesh_builder;
 mod systems;
 mod tile;
+mod tile_manager;
 mod radar;
+mod refraction;
+mod scenario;
+mod station;
+#[cfg(test)]
+mod test_support;
 mod ui;
+mod world;
 
 use bevy::prelude::*;
+use bevy::transform::TransformSystems;
 
 fn main() {
+    let (radars, scenario_path) = scenario::radars_from_args();
+
     App::new()
         .add_plugins(DefaultPlugins.set(WindowPlugin {
             primary_window: Some(Window {
@@ -25,31 +46,67 @@ fn main() {
         // Resources
         .init_resource::<cache::TileCache>()
         .init_resource::<colormap::ColorMap>()
+        .init_resource::<coverage::CoverageCache>()
+        .init_resource::<coverage::CoverageSettings>()
         .init_resource::<downloader::TileDownloader>()
         .init_resource::<lod::LodManager>()
-        .init_resource::<radar::Radars>()
+        .init_resource::<placement::Placement>()
+        .init_resource::<tile_manager::TileManager>()
+        .insert_resource(radars)
+        .insert_resource(scenario_path)
+        .init_resource::<world::FloatingOrigin>()
+        .init_resource::<world::WorldMode>()
         // Startup systems
         .add_systems(Startup, (
             setup_scene,
             camera::setup_camera,
-            radar::setup_radar_marker,
+            radar::setup_radar_marker.after(camera::setup_camera),
             ui::setup_ui,
         ))
         // Update systems
-        // Update systems
         .add_systems(Update, (
+            world::toggle_world_mode_system,
+            radar::toggle_radar_system,
+            radar::toggle_antenna_system,
+            radar::toggle_propagation_system,
+            radar::cycle_refraction_system,
+            coverage::coverage_input_system,
+            detection::detection_input_system,
+            mlat::mlat_input_system,
+            mda::export_mda_system,
+            blockage::export_blockage_system,
+            scenario::save_scenario_system,
+            placement::placement_input_system,
+            placement::placement_commit_system,
+            world::follow_refraction_system,
             camera::camera_flight_system,
+            world::floating_origin_system,
+        ).chain())
+        .add_systems(Update, (
             lod::update_lod_system,
             systems::tile_loader_system,
-            systems::mesh_update_system,
-            systems::process_mesh_tasks,
-            radar::update_radar_position_system,
+            // Finished meshes are swapped in before new tasks replace pending ones,
+            // and eviction runs last so its despawns come after any insert
+            (
+                systems::process_mesh_tasks,
+                systems::process_recolor_tasks,
+                systems::radar_recolor_system,
+                systems::placement_preview_system,
+                systems::mesh_update_system,
+                systems::tile_eviction_system,
+            ).chain(),
+            radar::update_site_elevation_system,
+            radar::update_radar_position_system.after(radar::update_site_elevation_system),
+            placement::update_candidate_marker_system,
             ui::update_mouse_coordinates_system,
-        ))
+            ui::update_coverage_layer_text_system,
+        ).after(world::floating_origin_system))
         .add_systems(Update, (
             downloader::process_downloads,
             // mesh_update_system is already above
         ))
+        // Render-space transforms are derived from f64 world positions last
+        .add_systems(PostUpdate, world::sync_world_transforms_system.before(TransformSystems::Propagate))
         .run();
 }
 
@@ -74,4 +131,17 @@ fn setup_scene(mut commands: Commands) {
     info!("  Q/E or Shift/Space: Move up/down");
     info!("  Right-click + drag: Rotate camera");
     info!("  Mouse wheel: Zoom in/out");
+    info!("  G: Toggle flat map / globe");
+    info!("  1-9: Toggle radar stations");
+    info!("  PageUp/PageDown: Coverage flight level (Shift: height above ground, Ctrl: above sea level, Home: ground)");
+    info!("  V: Cycle coverage view (visibility / overlap / unique / single point of failure / Pd / margin / MLAT / MDA / refraction)");
+    info!("  K: Cycle refraction model, ending on each station's own (Shift: comparison model)");
+    info!("  B: Cycle antenna patterns (isotropic / en-route / each station's own)");
+    info!("  P: Cycle the propagation model (knife edge / ITM average ground / ITM sea water / each station's own; Shift+P: ITM climate)");
+    info!("  T: Cycle detection target class (Shift: Swerling model)");
+    info!("  M: Cycle receivers required for multilateration (4-6)");
+    info!("  X: Export minimum detectable altitude rasters");
+    info!("  Shift+X: Export beam blockage (polar CSV and PPI image per radar)");
+    info!("  N: Placement mode (left click/drag: place a candidate station, Enter: commit, Esc: discard)");
+    info!("  F5: Save the stations to the scenario file (--radars, or scenarios/radars.csv)");
 }
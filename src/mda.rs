@@ -0,0 +1,306 @@
+// Minimum detectable altitude (MDA) maps
+use crate::coverage::{CoverageCache, CoverageSettings};
+use crate::geodesy;
+use crate::horizon::HorizonProfile;
+use crate::radar::{Radar, Radars};
+use crate::tile::{TileCoord, TileData};
+use bevy::prelude::*;
+use bevy::tasks::AsyncComputeTaskPool;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Feet to meters
+pub const FEET_TO_METERS: f32 = 0.3048;
+
+/// Altitudes above this are reported as "not detected" (FL450, meters)
+pub const MDA_CEILING: f32 = 45_000.0 * FEET_TO_METERS;
+
+/// Value written for undetected cells in exported rasters
+const NODATA: i32 = -9999;
+
+/// Spacing of the altitudes tried when the antenna pattern cuts the range (meters, ~100 ft)
+const BEAM_STEP: f32 = 30.0;
+
+/// Lowest altitude (meters AMSL) at which `radar` sees a target of `rcs_m2` over (lat, lon)
+/// Closed form from the horizon profile: the radio horizon, the terrain horizon and the
+/// bottom of the beam each give a lower bound on it, and the cone of silence an upper one.
+/// None when the point is out of range, blanked or only visible above `MDA_CEILING`.
+pub fn radar_mda(radar: &Radar, horizon: &HorizonProfile, lat: f64, lon: f64, ground: f32, rcs_m2: f64) -> Option<f32> {
+    if !radar.enabled {
+        return None;
+    }
+
+    // 1. Physics range at boresight (the pattern only lowers it) and blanked sectors
+    let inverse = geodesy::inverse(radar.position.x, radar.position.y, lat, lon);
+    let dist = inverse.distance;
+    let azimuth = inverse.azimuth.to_degrees().rem_euclid(360.0);
+    if dist > radar.calculate_max_range(rcs_m2) || radar.antenna.is_blanked(azimuth) {
+        return None;
+    }
+
+    // 2. Radio horizon: dist <= sqrt(2 h_r R) + sqrt(2 h_t R)
+    let d_radar = radar.refraction.radio_horizon(radar.position.z.max(0.0));
+    let radio_horizon = if dist > d_radar {
+        (dist - d_radar).powi(2) / (2.0 * radar.refraction.effective_radius())
+    } else {
+        0.0
+    };
+
+    // 3. Terrain horizon: the clearance grows one for one with the target altitude
+    let terrain_horizon = ground as f64 - horizon.clearance(lat, lon, ground);
+
+    // 4. Elevation coverage of the antenna
+    let beam_bottom = radar.altitude_at_elevation(dist, radar.antenna.min_elevation_deg);
+    let ceiling = radar.altitude_at_elevation(dist, radar.antenna.max_elevation_deg).min(MDA_CEILING as f64) as f32;
+
+    // ALGORITHM: Pattern-Limited Range
+    // The range equation now depends on the elevation gain, so it is no longer the same
+    // for every altitude; climb from the geometric bound until the echo is strong enough.
+    let mut mda = (ground as f64).max(radio_horizon).max(terrain_horizon).max(beam_bottom) as f32;
+    while !radar.in_beam(dist, azimuth, mda, rcs_m2) {
+        mda += BEAM_STEP;
+        if mda > ceiling {
+            return None;
+        }
+    }
+    (mda <= ceiling).then_some(mda)
+}
+
+/// Lowest altitude at which `radar` sees a target, by binary search on the raycast
+/// Slower but exact reference for a single point (e.g. under the cursor). Assumes
+/// visibility is monotonic in altitude below the cone of silence, which holds for the
+/// effective Earth ray model as long as the pattern doesn't cut the range on the way up.
+pub fn radar_mda_raycast(
+    radar: &Radar,
+    lat: f64,
+    lon: f64,
+    ground: f32,
+    rcs_m2: f64,
+    cache_snapshot: &HashMap<TileCoord, Arc<TileData>>,
+) -> Option<f32> {
+    let visible = |alt: f32| radar.is_visible_raycast(lat, lon, alt, rcs_m2, cache_snapshot);
+
+    // Highest altitude below the cone of silence
+    let dist = geodesy::distance(radar.position.x, radar.position.y, lat, lon);
+    let ceiling = radar.altitude_at_elevation(dist, radar.antenna.max_elevation_deg).min(MDA_CEILING as f64) as f32;
+
+    if visible(ground) {
+        return Some(ground);
+    }
+    if ceiling <= ground || !visible(ceiling) {
+        return None;
+    }
+
+    // Invariant: hidden at lo, visible at hi
+    let (mut lo, mut hi) = (ground, ceiling);
+    while hi - lo > 1.0 {
+        let mid = 0.5 * (lo + hi);
+        if visible(mid) {
+            hi = mid;
+        } else {
+            lo = mid;
+        }
+    }
+    Some(hi)
+}
+
+/// Lowest altitude at which any enabled radar sees a target (raycast reference)
+pub fn network_mda_raycast(
+    radars: &Radars,
+    lat: f64,
+    lon: f64,
+    ground: f32,
+    rcs_m2: f64,
+    cache_snapshot: &HashMap<TileCoord, Arc<TileData>>,
+) -> Option<f32> {
+    radars
+        .stations
+        .iter()
+        .filter(|radar| radar.enabled)
+        .filter_map(|radar| radar_mda_raycast(radar, lat, lon, ground, rcs_m2, cache_snapshot))
+        .min_by(|a, b| a.total_cmp(b))
+}
+
+/// Minimum detectable altitude over a tile, for one radar or a set of radars
+/// Laid out like `CoverageGrid` (row 0 = north edge, column 0 = west edge).
+#[derive(Debug, Clone)]
+pub struct MdaGrid {
+    pub coord: TileCoord,
+    pub resolution: usize,
+    /// Meters AMSL, None where no radar detects below the ceiling
+    pub altitudes: Vec<Option<f32>>,
+}
+
+impl MdaGrid {
+    /// Compute the lowest altitude at which any of the given radars sees a target of `rcs_m2`
+    /// over each sample
+    pub fn compute(radars: &[(&Radar, &HorizonProfile)], tile: &TileData, resolution: usize, rcs_m2: f64) -> Self {
+        use rayon::prelude::*;
+
+        let coord = tile.coord;
+        let max_coord = tile.size - 1;
+        let step = 1.0 / (resolution - 1) as f64;
+        let altitudes = (0..resolution * resolution)
+            .into_par_iter()
+            .map(|i| {
+                let (x, y) = (i % resolution, i / resolution);
+                let lat = coord.lat as f64 + 1.0 - y as f64 * step;
+                let lon = coord.lon as f64 + x as f64 * step;
+                let tx = (x * max_coord + (resolution - 1) / 2) / (resolution - 1);
+                let ty = (y * max_coord + (resolution - 1) / 2) / (resolution - 1);
+                let ground = tile.get_height(tx, ty).unwrap_or(0) as f32;
+
+                radars
+                    .iter()
+                    .filter_map(|(radar, horizon)| radar_mda(radar, horizon, lat, lon, ground, rcs_m2))
+                    .min_by(|a, b| a.total_cmp(b))
+            })
+            .collect();
+
+        Self {
+            coord,
+            resolution,
+            altitudes,
+        }
+    }
+
+    /// Nearest sample to a normalized tile position (0..1 from the NW corner)
+    pub fn sample(&self, nx: f32, ny: f32) -> Option<f32> {
+        let max = (self.resolution - 1) as f32;
+        let x = (nx.clamp(0.0, 1.0) * max).round() as usize;
+        let y = (ny.clamp(0.0, 1.0) * max).round() as usize;
+        self.altitudes[y * self.resolution + x]
+    }
+
+    /// ESRI ASCII grid in feet AMSL (cell centers on the samples, north row first)
+    pub fn to_esri_ascii(&self) -> String {
+        let cellsize = 1.0 / (self.resolution - 1) as f64;
+        let mut out = format!(
+            "ncols {}\nnrows {}\nxllcenter {}\nyllcenter {}\ncellsize {}\nNODATA_value {}\n",
+            self.resolution, self.resolution, self.coord.lon, self.coord.lat, cellsize, NODATA
+        );
+        for row in self.altitudes.chunks(self.resolution) {
+            let values: Vec<String> = row
+                .iter()
+                .map(|alt| match alt {
+                    Some(meters) => format!("{:.0}", meters / FEET_TO_METERS),
+                    None => NODATA.to_string(),
+                })
+                .collect();
+            out.push_str(&values.join(" "));
+            out.push('\n');
+        }
+        out
+    }
+}
+
+/// System to export the MDA of every loaded tile as ESRI ASCII grids (X key)
+/// Files go to `exports/mda_<tile>.asc`, one per tile, values in feet AMSL.
+pub fn export_mda_system(
+    keys: Res<ButtonInput<KeyCode>>,
+    cache: Res<crate::cache::TileCache>,
+    radars: Res<Radars>,
+    coverage_cache: Res<CoverageCache>,
+    settings: Res<CoverageSettings>,
+) {
+    // Shift+X exports the beam blockage instead
+    let shift_pressed = keys.pressed(KeyCode::ShiftLeft) || keys.pressed(KeyCode::ShiftRight);
+    if !keys.just_pressed(KeyCode::KeyX) || shift_pressed {
+        return;
+    }
+
+    let snapshot = cache.get_snapshot();
+    let radars = radars.clone();
+    let coverage_cache = coverage_cache.clone();
+    let resolution = settings.resolution;
+    let rcs_m2 = settings.detection.target.rcs_m2();
+
+    // Runs in the background; the result is only reported in the log
+    AsyncComputeTaskPool::get()
+        .spawn(async move {
+            let dir = std::env::current_dir()
+                .expect("Could not determine current directory")
+                .join("exports");
+            if let Err(e) = std::fs::create_dir_all(&dir) {
+                error!("Failed to create export directory ({:?}): {}", dir, e);
+                return;
+            }
+
+            let horizons: Vec<(&Radar, Arc<HorizonProfile>)> = radars
+                .stations
+                .iter()
+                .filter(|radar| radar.enabled)
+                .map(|radar| (radar, coverage_cache.horizon(radar, &snapshot)))
+                .collect();
+            let profiles: Vec<(&Radar, &HorizonProfile)> =
+                horizons.iter().map(|(radar, horizon)| (*radar, horizon.as_ref())).collect();
+
+            let mut written = 0;
+            for tile in snapshot.values() {
+                let grid = MdaGrid::compute(&profiles, tile, resolution, rcs_m2);
+                let name = tile.coord.filename().replace(".hgt", ".asc");
+                let path = dir.join(format!("mda_{}", name));
+                match std::fs::write(&path, grid.to_esri_ascii()) {
+                    Ok(()) => written += 1,
+                    Err(e) => error!("Failed to write {:?}: {}", path, e),
+                }
+            }
+            info!("Exported minimum detectable altitude for {} tiles to {:?}", written, dir);
+        })
+        .detach();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{radar_at, ridge_tile, snapshot, TARGET_RCS};
+
+    #[test]
+    fn test_matches_raycast() {
+        let tile = ridge_tile();
+        let snapshot = snapshot(&tile);
+        let radar = radar_at(43.5, 7.3, 400.0);
+        let horizon = HorizonProfile::compute(&radar, &snapshot);
+
+        let mut behind_ridge = 0;
+        for y in (0..tile.size).step_by(30) {
+            for x in (0..tile.size).step_by(30) {
+                let lat = 44.0 - y as f64 / 360.0;
+                let lon = 7.0 + x as f64 / 360.0;
+                let ground = tile.get_height(x, y).unwrap() as f32;
+
+                let closed = radar_mda(&radar, &horizon, lat, lon, ground, TARGET_RCS).unwrap();
+                let reference = radar_mda_raycast(&radar, lat, lon, ground, TARGET_RCS, &snapshot).unwrap();
+                assert!(closed >= ground);
+                // Same tolerance as the horizon profile vs the raycast
+                assert!((closed - reference).abs() < 50.0, "{} vs {} at {},{}", closed, reference, lat, lon);
+                if x > 240 {
+                    assert!(closed > ground + 10.0);
+                    behind_ridge += 1;
+                }
+            }
+        }
+        assert!(behind_ridge > 0);
+    }
+
+    #[test]
+    fn test_network_takes_lowest() {
+        let tile = ridge_tile();
+        let snapshot = snapshot(&tile);
+        let west = radar_at(43.5, 7.3, 400.0);
+        let east = radar_at(43.5, 7.9, 400.0);
+        let west_horizon = HorizonProfile::compute(&west, &snapshot);
+        let east_horizon = HorizonProfile::compute(&east, &snapshot);
+
+        // Only the western radar: the area behind the ridge needs altitude
+        let west_only = MdaGrid::compute(&[(&west, &west_horizon)], &tile, 11, TARGET_RCS);
+        let both = MdaGrid::compute(&[(&west, &west_horizon), (&east, &east_horizon)], &tile, 11, TARGET_RCS);
+        let behind = west_only.sample(0.8, 0.5).unwrap();
+        assert!(behind > 150.0);
+        assert!(both.sample(0.8, 0.5).unwrap() < behind);
+
+        let ascii = both.to_esri_ascii();
+        assert!(ascii.starts_with("ncols 11\nnrows 11\nxllcenter 7\nyllcenter 43\n"));
+        assert_eq!(ascii.lines().count(), 6 + 11);
+    }
+}
@@ -1,18 +1,42 @@
 // Triangle mesh generation for terrain
 use crate::colormap::ColorMap;
+use crate::coverage::{CoverageView, TileCoverage};
+use crate::detection::PD_CONTOURS;
+use crate::mlat::HDOP_BANDS;
+use crate::profile::MARGIN_BANDS;
+use crate::mda::FEET_TO_METERS;
+use crate::radar::{Radar, Radars};
 use crate::tile::TileData;
 use bevy::prelude::*;
 use bevy::mesh::Indices;
 use bevy::render::render_resource::PrimitiveTopology;
-use std::collections::HashMap;
-use std::sync::Arc;
 use crate::tile::TileCoord;
+use crate::world::WorldMode;
+
+/// Color of the sea-level surface drawn for tiles that don't exist (ocean)
+const WATER_COLOR: [f32; 4] = [0.1, 0.35, 0.75, 0.6];
+
+/// Color of the hatching drawn for tiles that failed to load
+const ERROR_COLOR: [f32; 4] = [1.0, 0.5, 0.0, 1.0];
+
+/// Color of terrain hidden from every radar (faint red)
+const HIDDEN_COLOR: [f32; 4] = [1.0, 0.0, 0.0, 0.1];
+
+/// Kind of placeholder drawn where there is no elevation data
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlaceholderKind {
+    /// Tile is not part of SRTM (ocean): sea-level water surface
+    Water,
+    /// Tile failed to load: hatched wireframe, so data problems aren't mistaken for coastline
+    Error,
+}
 
 /// Build a terrain mesh from tile data
 pub struct TerrainMeshBuilder {
     pub lod_level: usize,  // Level of detail (1 = full res, 2 = half res, etc.)
     pub scale: f32,        // Horizontal scale factor
     pub height_scale: f32, // Vertical exaggeration
+    pub mode: WorldMode,   // Flat map or globe vertex placement
 }
 
 impl Default for TerrainMeshBuilder {
@@ -21,6 +45,7 @@ impl Default for TerrainMeshBuilder {
             lod_level: 1,
             scale: 1.0, 
             height_scale: 1.0,
+            mode: WorldMode::Flat,
         }
     }
 }
@@ -32,11 +57,18 @@ impl TerrainMeshBuilder {
             lod_level,
             scale: 1.0, 
             height_scale: 1.0,
+            mode: WorldMode::Flat,
         }
     }
 
+    /// Set how vertices are placed in world space
+    pub fn with_mode(mut self, mode: WorldMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
     /// Build a mesh for a given tile
-    pub fn build_mesh(&self, tile: &TileData, colormap: &ColorMap, radars: Option<&crate::radar::Radars>, cache_snapshot: Option<&HashMap<TileCoord, Arc<TileData>>>) -> Mesh {
+    pub fn build_mesh(&self, tile: &TileData, colormap: &ColorMap, radars: Option<&Radars>, coverage: Option<&TileCoverage>) -> Mesh {
         let step = self.lod_level;
         let size = tile.size;
         
@@ -47,15 +79,10 @@ impl TerrainMeshBuilder {
         // We need to generate vertices up to max_coord inclusive
         let vertices_per_row = max_coord / step + 1;
         
-        let mut positions = Vec::new();
-        let mut colors = Vec::new();
         let mut indices = Vec::new();
         
-        // Tile origin in World Coordinates (lat/lon)
-        // Tile N43E007 origin is 43N, 7E.
-        // x index 0..3600 maps to 0..1 deg.
-        let tile_lat_base = tile.coord.lat as f64;
-        let tile_lon_base = tile.coord.lon as f64;
+        // Vertices are relative to the tile anchor (see WorldMode::tile_anchor)
+        let anchor = self.mode.tile_anchor(tile.coord);
         
         // Generate vertices in parallel using Rayon
         // Generate vertices in parallel using Rayon (Outer loop only to reduce overhead)
@@ -70,69 +97,19 @@ impl TerrainMeshBuilder {
         
         use rayon::prelude::*;
         
-        let vertices: Vec<( [f32; 3], [f32; 4] )> = (0..total_vertices)
+        let positions: Vec<[f32; 3]> = (0..total_vertices)
             .into_par_iter()
             .map(|i| {
-                let yi = i / vertices_per_row;
-                let xi = i % vertices_per_row;
-                
-                let y = yi * step;
-                let x = xi * step;
+                let y = (i / vertices_per_row) * step;
+                let x = (i % vertices_per_row) * step;
                 
                 let height = tile.get_height(x, y).unwrap_or(0) as f32;
-                
-                // Position
-                let px = (x as f32 / max_coord as f32) * (size as f32) * self.scale;
-                let py = height * self.height_scale;
-                let pz = (y as f32 / max_coord as f32) * (size as f32) * self.scale;
-                
-                let position = [px, py, pz];
-                
-                // Determine color
-                let mut final_color_rgba = [1.0, 1.0, 1.0, 1.0];
-                
-                if let Some(rds) = radars {
-                    if let Some(snap) = cache_snapshot {
-                        // Re-calculate lat/lon per vertex
-                        let v_lat = (tile_lat_base + 1.0) - (y as f64 / max_coord as f64);
-                        let v_lon = tile_lon_base + (x as f64 / max_coord as f64);
-                        
-                        let (visible, color) = rds.check_visibility(v_lat, v_lon, height as f32, snap);
-
-                        if visible {
-                            // Use radar color if visible, with user-defined transparency
-                             if let Some(c) = color {
-                                let srgba = c.to_srgba();
-                                final_color_rgba = [srgba.red, srgba.green, srgba.blue, 0.3]; // Use standard transparency
-                             } else {
-                                final_color_rgba = [0.0, 1.0, 0.0, 0.3]; // Fallback Green
-                             }
-                        } else {
-                            // Red for hidden (keep previous transparency edit if desired, or standardize)
-                            final_color_rgba = [1.0, 0.0, 0.0, 0.1]; // User recently set this to 0.1
-                        }
-                    } else {
-                         // Fallback without snapshot? Or just skip
-                         // If no snapshot, we can't do accurate visibility.
-                         // Maybe simple LOS check? But check_visibility requires snapshot for raycast.
-                         let c = colormap.get_color(height).to_srgba();
-                         final_color_rgba = [c.red, c.green, c.blue, c.alpha];
-                    }
-                } else {
-                     // Fallback to colormap if no radar
-                    let c = colormap.get_color(height).to_srgba();
-                    final_color_rgba = [c.red, c.green, c.blue, c.alpha];
-                }
-                
-                (position, final_color_rgba)
+                self.vertex_position(tile.coord, anchor, x, y, max_coord, size, height)
             })
             .collect();
 
-        // Populate the buffers
-        for (pos, col) in vertices {
-            positions.push(pos);
-            colors.push(col);
-        }
+        // Colors are computed separately so they can be refreshed without touching geometry
+        let colors = self.build_colors(tile, colormap, radars, coverage);
         
         // Generate wireframe indices (optimized: min lines)
         // Grid size is number of cells
@@ -184,58 +161,166 @@ impl TerrainMeshBuilder {
         mesh
     }
 
-    /// Build a placeholder mesh for missing tiles (red at height 0)
-    pub fn build_missing_mesh(&self) -> Mesh {
-        let size = 100; // Simple low-res grid for missing tiles
-        let step = self.lod_level.max(10);
-        let grid_size = size / step + 1;
+    /// Compute the vertex colors of a terrain mesh (same vertex order as `build_mesh`)
+    /// Used on its own to recolor an existing mesh when the radars change.
+    pub fn build_colors(&self, tile: &TileData, colormap: &ColorMap, radars: Option<&Radars>, coverage: Option<&TileCoverage>) -> Vec<[f32; 4]> {
+        let step = self.lod_level;
+        let max_coord = tile.size - 1;
+        let vertices_per_row = max_coord / step + 1;
+        let total_vertices = vertices_per_row * vertices_per_row;
+        let mda_colormap = ColorMap::minimum_altitude();
+        let pd_colormap = ColorMap::probability_of_detection();
+        let dop_colormap = ColorMap::dilution_of_precision();
+        let margin_colormap = ColorMap::signal_margin();
+        
+        use rayon::prelude::*;
+        
+        (0..total_vertices)
+            .into_par_iter()
+            .map(|i| {
+                let y = (i / vertices_per_row) * step;
+                let x = (i % vertices_per_row) * step;
+                
+                let height = tile.get_height(x, y).unwrap_or(0) as f32;
+                
+                // Position of this vertex in the tile (0..1 from the NW corner)
+                let nx = x as f32 / max_coord as f32;
+                let ny = y as f32 / max_coord as f32;
+                
+                // Determine color
+                let mut final_color_rgba = [1.0, 1.0, 1.0, 1.0];
+                
+                if let (Some(_), Some(Some(mda))) = (radars, coverage.map(|cov| &cov.minimum_altitude)) {
+                    // Minimum detectable altitude in feet; undetected areas stay dim red
+                    final_color_rgba = match mda.sample(nx, ny) {
+                        Some(meters) => {
+                            let c = mda_colormap.get_color(meters / FEET_TO_METERS).to_srgba();
+                            [c.red, c.green, c.blue, 0.5]
+                        }
+                        None => HIDDEN_COLOR,
+                    };
+                } else if let (Some(_), Some(Some(pd_grid))) = (radars, coverage.map(|cov| &cov.detection)) {
+                    // Network Pd in bands between the contour levels; below the lowest, dim red
+                    let pd = pd_grid.sample(nx, ny);
+                    final_color_rgba = match PD_CONTOURS.iter().rev().find(|level| pd >= **level) {
+                        Some(level) => {
+                            let c = pd_colormap.get_color(*level).to_srgba();
+                            [c.red, c.green, c.blue, 0.5]
+                        }
+                        None => HIDDEN_COLOR,
+                    };
+                } else if let (Some(_), Some(Some(margin))) = (radars, coverage.map(|cov| &cov.margin)) {
+                    // Margin in bands above the sensitivity; below it, dim red
+                    let db = margin.sample(nx, ny);
+                    final_color_rgba = match MARGIN_BANDS.iter().rev().find(|band| db >= **band) {
+                        Some(band) => {
+                            let c = margin_colormap.get_color(*band).to_srgba();
+                            [c.red, c.green, c.blue, 0.5]
+                        }
+                        None => HIDDEN_COLOR,
+                    };
+                } else if let (Some(_), Some(Some(mlat))) = (radars, coverage.map(|cov| &cov.mlat)) {
+                    // HDOP in bands, worst beyond the last one; dim red without enough receivers
+                    let (_, hdop) = mlat.sample(nx, ny);
+                    final_color_rgba = if hdop.is_finite() {
+                        let band = HDOP_BANDS.iter().find(|band| hdop <= **band).copied().unwrap_or(f32::MAX);
+                        let c = dop_colormap.get_color(band).to_srgba();
+                        [c.red, c.green, c.blue, 0.5]
+                    } else {
+                        HIDDEN_COLOR
+                    };
+                } else if let (Some(_), Some(Some(compared))) = (radars, coverage.map(|cov| &cov.comparison)) {
+                    // Scenario refraction vs comparison refraction at the selected layer
+                    let scenario = coverage.is_some_and(|cov| cov.merged.sample(nx, ny).visible);
+                    final_color_rgba = match (scenario, compared.sample(nx, ny).visible) {
+                        (true, true) => [0.0, 1.0, 0.0, 0.3],
+                        (true, false) => [0.2, 0.4, 1.0, 0.6], // Only with the scenario refraction
+                        (false, true) => [1.0, 0.6, 0.0, 0.6], // Only with the comparison refraction
+                        (false, false) => HIDDEN_COLOR,
+                    };
+                } else if let Some(rds) = radars {
+                    if let Some(cov) = coverage {
+                        // Sample the coverage raster (independent of the LOD stride)
+                        final_color_rgba = coverage_color(rds, cov, nx, ny);
+                    } else {
+                         // No coverage computed for this tile: show elevation instead
+                         let c = colormap.get_color(height).to_srgba();
+                         final_color_rgba = [c.red, c.green, c.blue, c.alpha];
+                    }
+                } else {
+                     // Fallback to colormap if no radar
+                    let c = colormap.get_color(height).to_srgba();
+                    final_color_rgba = [c.red, c.green, c.blue, c.alpha];
+                }
+                
+                final_color_rgba
+            })
+            .collect()
+    }
+
+    /// Build a placeholder mesh for tiles without elevation data
+    /// Placeholders cover the full SRTM1 tile so they line up with real tiles.
+    pub fn build_placeholder_mesh(&self, coord: TileCoord, kind: PlaceholderKind) -> Mesh {
+        const SIZE: usize = 3601;
+        const STEP: usize = 100; // Divisor of 3600 => 36 x 36 cells (enough to follow the globe)
+        let max_coord = SIZE - 1;
+        let vertices_per_row = max_coord / STEP + 1;
+        let anchor = self.mode.tile_anchor(coord);
         
         let mut positions = Vec::new();
-        let mut colors = Vec::new();
         let mut indices = Vec::new();
         
-        // Generate flat red grid
-        for y in (0..=size).step_by(step) {
-            for x in (0..=size).step_by(step) {
-                // Use absolute coordinates to match terrain tiles
-                let px = x as f32 * self.scale;
-                let py = 0.0; // Height 0
-                let pz = y as f32 * self.scale;
-                
-                positions.push([px, py, pz]);
-                colors.push([1.0, 0.0, 0.0, 1.0]); // Red
+        // Flat grid at sea level
+        for yi in 0..vertices_per_row {
+            for xi in 0..vertices_per_row {
+                positions.push(self.vertex_position(coord, anchor, xi * STEP, yi * STEP, max_coord, SIZE, 0.0));
             }
         }
         
-        // Generate wireframe indices for missing tile
-        let grid_w = size / step; // Number of cells
-        
-        for y in 0..grid_w {
-            for x in 0..grid_w {
-                let i0 = y * (grid_w + 1) + x;
-                let i1 = i0 + 1;
-                let i2 = i0 + (grid_w + 1);
-                
-                // Wireframe lines
-                indices.push(i0 as u32); indices.push(i1 as u32); // Top
-                indices.push(i0 as u32); indices.push(i2 as u32); // Left
-                indices.push(i1 as u32); indices.push(i2 as u32); // Diagonal
-                
-                // Right and Bottom edges
-                if x == grid_w - 1 {
-                    let i3 = i2 + 1;
-                    indices.push(i1 as u32); indices.push(i3 as u32);
+        let cells = vertices_per_row - 1;
+        let (topology, color) = match kind {
+            PlaceholderKind::Water => {
+                // Filled surface: two triangles per cell, wound so the normal points up
+                for y in 0..cells {
+                    for x in 0..cells {
+                        let i0 = (y * vertices_per_row + x) as u32;
+                        let i1 = i0 + 1;
+                        let i2 = i0 + vertices_per_row as u32;
+                        let i3 = i2 + 1;
+                        indices.extend_from_slice(&[i0, i2, i1, i1, i2, i3]);
+                    }
                 }
-                if y == grid_w - 1 {
-                    let i3 = i2 + 1;
-                    indices.push(i2 as u32); indices.push(i3 as u32);
+                (PrimitiveTopology::TriangleList, WATER_COLOR)
+            }
+            PlaceholderKind::Error => {
+                // Hatched wireframe: cell edges plus both diagonals ("X" in every cell),
+                // clearly different from both terrain and water
+                for y in 0..cells {
+                    for x in 0..cells {
+                        let i0 = (y * vertices_per_row + x) as u32;
+                        let i1 = i0 + 1;
+                        let i2 = i0 + vertices_per_row as u32;
+                        let i3 = i2 + 1;
+                        indices.extend_from_slice(&[i0, i1, i0, i2, i0, i3, i1, i2]);
+                        if x == cells - 1 {
+                            indices.extend_from_slice(&[i1, i3]);
+                        }
+                        if y == cells - 1 {
+                            indices.extend_from_slice(&[i2, i3]);
+                        }
+                    }
                 }
+                (PrimitiveTopology::LineList, ERROR_COLOR)
             }
-        }
+        };
         
-        let normals = vec![[0.0, 1.0, 0.0]; positions.len()];
+        let normals = match topology {
+            PrimitiveTopology::TriangleList => self.calculate_normals(&positions, &indices),
+            _ => vec![[0.0, 1.0, 0.0]; positions.len()],
+        };
+        let colors = vec![color; positions.len()];
         
-        let mut mesh = Mesh::new(PrimitiveTopology::LineList, Default::default());
+        let mut mesh = Mesh::new(topology, Default::default());
         mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
         mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, normals);
         mesh.insert_attribute(Mesh::ATTRIBUTE_COLOR, colors);
@@ -244,6 +329,27 @@ impl TerrainMeshBuilder {
         mesh
     }
 
+    /// Position of grid sample (x, y) of a tile, relative to the tile anchor
+    #[allow(clippy::too_many_arguments)]
+    fn vertex_position(&self, coord: TileCoord, anchor: bevy::math::DVec3, x: usize, y: usize, max_coord: usize, size: usize, height: f32) -> [f32; 3] {
+        match self.mode {
+            WorldMode::Flat => {
+                let px = (x as f32 / max_coord as f32) * (size as f32) * self.scale;
+                let py = height * self.height_scale;
+                let pz = (y as f32 / max_coord as f32) * (size as f32) * self.scale;
+                [px, py, pz]
+            }
+            WorldMode::Globe { .. } => {
+                // Place on the ellipsoid in f64, then make it anchor-relative for f32
+                let v_lat = (coord.lat as f64 + 1.0) - (y as f64 / max_coord as f64);
+                let v_lon = coord.lon as f64 + (x as f64 / max_coord as f64);
+                let h = (height * self.height_scale) as f64;
+                let p = self.mode.geo_to_world(v_lat, v_lon, h) - anchor;
+                [p.x as f32, p.y as f32, p.z as f32]
+            }
+        }
+    }
+
     /// Calculate normals for the mesh
     fn calculate_normals(&self, positions: &[[f32; 3]], indices: &[u32]) -> Vec<[f32; 3]> {
         let mut normals = vec![[0.0f32, 0.0, 0.0]; positions.len()];
@@ -291,3 +397,91 @@ impl TerrainMeshBuilder {
         normals
     }
 }
+
+/// Vertex color for the visibility and overlap views, from the coverage rasters
+fn coverage_color(radars: &Radars, coverage: &TileCoverage, nx: f32, ny: f32) -> [f32; 4] {
+    // Stations that see the point, whatever their order in the list
+    let seen_by: Vec<&Radar> = coverage
+        .per_radar
+        .iter()
+        .filter(|(_, grid)| grid.sample(nx, ny).visible)
+        .filter_map(|(index, _)| radars.stations.get(*index))
+        .collect();
+    let radar_color = |radar: &Radar, alpha: f32| {
+        let c = radar.color.to_srgba();
+        [c.red, c.green, c.blue, alpha]
+    };
+
+    match (coverage.view, seen_by.len()) {
+        (_, 0) => HIDDEN_COLOR,
+        // Number of radars: 1 orange, 2 yellow, 3+ green
+        (CoverageView::Overlap, 1) => [1.0, 0.5, 0.0, 0.4],
+        (CoverageView::Overlap, 2) => [1.0, 1.0, 0.0, 0.4],
+        (CoverageView::Overlap, _) => [0.0, 1.0, 0.0, 0.4],
+        // Only seen by one station: its color; shared areas in grey
+        (CoverageView::UniqueCoverage, 1) => radar_color(seen_by[0], 0.5),
+        (CoverageView::UniqueCoverage, _) => [0.5, 0.5, 0.5, 0.2],
+        // Losing the only radar that sees these areas loses them
+        (CoverageView::SinglePointOfFailure, 1) => [1.0, 0.3, 0.0, 0.6],
+        (CoverageView::SinglePointOfFailure, _) => [0.0, 1.0, 0.0, 0.15],
+        // Blend of the colors of the radars that see the point
+        (_, count) => {
+            let mut color = [0.0, 0.0, 0.0, 0.3];
+            for radar in &seen_by {
+                let c = radar_color(radar, 0.3);
+                for channel in 0..3 {
+                    color[channel] += c[channel] / count as f32;
+                }
+            }
+            color
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::refraction::Refraction;
+    use bevy::mesh::VertexAttributeValues;
+
+    fn positions(mesh: &Mesh) -> Vec<[f32; 3]> {
+        match mesh.attribute(Mesh::ATTRIBUTE_POSITION) {
+            Some(VertexAttributeValues::Float32x3(p)) => p.clone(),
+            _ => panic!("mesh has no positions"),
+        }
+    }
+
+    #[test]
+    fn test_placeholders_match_tile_extent() {
+        let coord = TileCoord::new(43, 7);
+        let mut tile = TileData::new(coord, 3601);
+        tile.heights.fill(500);
+        for mode in [WorldMode::Flat, WorldMode::globe(Refraction::STANDARD)] {
+            let builder = TerrainMeshBuilder::new(100).with_mode(mode);
+            let terrain = positions(&builder.build_mesh(&tile, &ColorMap::default(), None, None));
+            let corner = |p: &[[f32; 3]], row: usize, col: usize| {
+                let side = (p.len() as f64).sqrt() as usize;
+                Vec3::from(p[row * side + col])
+            };
+            for kind in [PlaceholderKind::Water, PlaceholderKind::Error] {
+                let placeholder = positions(&builder.build_placeholder_mesh(coord, kind));
+                let last = (placeholder.len() as f64).sqrt() as usize - 1;
+                let terrain_last = (terrain.len() as f64).sqrt() as usize - 1;
+                // Same corners once the terrain's 500 m is taken off
+                for (row, col) in [(0, 0), (0, 1), (1, 0), (1, 1)] {
+                    let p = corner(&placeholder, row * last, col * last);
+                    let t = corner(&terrain, row * terrain_last, col * terrain_last);
+                    let up = mode.up(mode.tile_anchor(coord) + t.as_dvec3()).as_vec3();
+                    let offset = (t - p).dot(up);
+                    assert!((t - p - up * offset).length() < 1.0, "{mode:?} {kind:?} corner ({row}, {col})");
+                }
+            }
+            // Water is drawn at sea level
+            let anchor = mode.tile_anchor(coord);
+            for p in positions(&builder.build_placeholder_mesh(coord, PlaceholderKind::Water)) {
+                let geo = mode.world_to_geo(anchor + Vec3::from(p).as_dvec3());
+                assert!(geo.z.abs() < 1.0, "{mode:?} water at {} m", geo.z);
+            }
+        }
+    }
+}
@@ -0,0 +1,211 @@
+// Multilateration (MLAT / WAM) geometry from a receiver network
+use crate::coverage::{CoverageAltitude, CoverageGrid, CoverageSettings};
+use crate::geodesy;
+use crate::radar::Radar;
+use crate::tile::TileData;
+use bevy::math::{DMat4, DVec3, DVec4};
+use bevy::prelude::*;
+
+/// Fewest receivers that fix a 3D position from time differences of arrival
+pub const MIN_RECEIVERS: usize = 4;
+
+/// Largest required receiver count offered by the M key
+const MAX_REQUIRED_RECEIVERS: usize = 6;
+
+/// HDOP levels drawn as bands in the MLAT view (best first)
+pub const HDOP_BANDS: [f32; 4] = [1.0, 2.0, 5.0, 10.0];
+
+/// Dilution of precision of a multilateration fix
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Dop {
+    /// Horizontal position error per unit of range (timing) error
+    pub hdop: f64,
+    /// Vertical position error per unit of range error
+    pub vdop: f64,
+}
+
+/// Multilateration requirements
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MlatSettings {
+    /// Receivers that must see the target for a position (at least `MIN_RECEIVERS`)
+    pub min_receivers: usize,
+}
+
+impl MlatSettings {
+    /// Next required receiver count (cycled with the M key)
+    pub fn next(self) -> Self {
+        let min_receivers = if self.min_receivers >= MAX_REQUIRED_RECEIVERS {
+            MIN_RECEIVERS
+        } else {
+            self.min_receivers + 1
+        };
+        Self { min_receivers }
+    }
+}
+
+impl Default for MlatSettings {
+    fn default() -> Self {
+        Self { min_receivers: MIN_RECEIVERS }
+    }
+}
+
+impl std::fmt::Display for MlatSettings {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, ">= {} receivers", self.min_receivers)
+    }
+}
+
+/// Dilution of precision of a TDOA fix at `target` from `receivers`
+/// Positions are (lat, lon, alt) like `Radar::position`. None with fewer than
+/// `MIN_RECEIVERS` or when the geometry is degenerate (e.g. receivers in a line).
+///
+/// ALGORITHM: TDOA Dilution of Precision
+/// Time differences against a reference receiver are equivalent to times of arrival with
+/// an unknown emission time. Linearizing the ranges around the target, each receiver gives
+/// a row [u_e, u_n, u_u, 1] with u the unit vector towards it in the target's local
+/// east/north/up frame, the last column absorbing the emission time. With equal timing
+/// errors on every receiver, the covariance of (e, n, u, t) is proportional to (H^T H)^-1,
+/// and HDOP = sqrt(Q_ee + Q_nn), VDOP = sqrt(Q_uu). The emission time column removes the
+/// common part of the geometry, so receivers clustered in one direction give a poor fix.
+pub fn tdoa_dop(receivers: &[DVec3], target: DVec3) -> Option<Dop> {
+    if receivers.len() < MIN_RECEIVERS {
+        return None;
+    }
+
+    let target_ecef = geodesy::to_ecef(target.x, target.y, target.z);
+    let (east, north, up) = geodesy::enu_basis(target.x, target.y);
+
+    let normal = receivers.iter().fold(DMat4::ZERO, |normal, receiver| {
+        let los = (geodesy::to_ecef(receiver.x, receiver.y, receiver.z) - target_ecef).normalize_or_zero();
+        let row = DVec4::new(los.dot(east), los.dot(north), los.dot(up), 1.0);
+        normal + DMat4::from_cols(row * row.x, row * row.y, row * row.z, row * row.w)
+    });
+
+    // Relative to the scale of the entries (at most the receiver count)
+    if normal.determinant().abs() < 1e-9 {
+        return None;
+    }
+    let covariance = normal.inverse();
+    let (q_ee, q_nn, q_uu) = (covariance.x_axis.x, covariance.y_axis.y, covariance.z_axis.z);
+    if !(q_ee >= 0.0 && q_nn >= 0.0 && q_uu >= 0.0) {
+        return None;
+    }
+    Some(Dop { hdop: (q_ee + q_nn).sqrt(), vdop: q_uu.sqrt() })
+}
+
+/// Receiver count and HDOP over a tile at the selected coverage layer
+pub struct MlatGrid {
+    pub resolution: usize,
+    /// Receivers in view of each sample
+    pub receivers: Vec<u8>,
+    /// HDOP of each sample (infinite without enough receivers or geometry)
+    pub hdop: Vec<f32>,
+}
+
+impl MlatGrid {
+    /// Receivers in view and HDOP at each sample of the layer, from the stations whose
+    /// coverage grid sees the sample
+    pub fn compute(
+        receivers: &[(&Radar, &CoverageGrid)],
+        tile: &TileData,
+        resolution: usize,
+        altitude: CoverageAltitude,
+        settings: &MlatSettings,
+    ) -> Self {
+        use rayon::prelude::*;
+
+        let max_coord = tile.size - 1;
+        let samples: Vec<(u8, f32)> = (0..resolution * resolution)
+            .into_par_iter()
+            .map(|i| {
+                let (x, y) = (i % resolution, i / resolution);
+                let in_view: Vec<DVec3> = receivers
+                    .iter()
+                    .filter(|(_, grid)| grid.cells[i].visible)
+                    .map(|(radar, _)| radar.position)
+                    .collect();
+                let count = in_view.len().min(u8::MAX as usize) as u8;
+                if in_view.len() < settings.min_receivers.max(MIN_RECEIVERS) {
+                    return (count, f32::INFINITY);
+                }
+
+                let (lat, lon) = CoverageGrid::sample_geo(tile.coord, resolution, x, y);
+                let tx = (x * max_coord + (resolution - 1) / 2) / (resolution - 1);
+                let ty = (y * max_coord + (resolution - 1) / 2) / (resolution - 1);
+                let ground = tile.get_height(tx, ty).unwrap_or(0) as f32;
+                let height = altitude.target_altitude(ground) as f64;
+
+                let hdop = tdoa_dop(&in_view, DVec3::new(lat, lon, height)).map_or(f32::INFINITY, |dop| dop.hdop as f32);
+                (count, hdop)
+            })
+            .collect();
+
+        let (receivers, hdop) = samples.into_iter().unzip();
+        Self { resolution, receivers, hdop }
+    }
+
+    /// Nearest sample to a normalized tile position (0..1 from the NW corner)
+    pub fn sample(&self, nx: f32, ny: f32) -> (u8, f32) {
+        let max = (self.resolution - 1) as f32;
+        let x = (nx.clamp(0.0, 1.0) * max).round() as usize;
+        let y = (ny.clamp(0.0, 1.0) * max).round() as usize;
+        let i = y * self.resolution + x;
+        (self.receivers[i], self.hdop[i])
+    }
+}
+
+/// System to change the number of receivers required for a position (M key)
+pub fn mlat_input_system(keys: Res<ButtonInput<KeyCode>>, mut settings: ResMut<CoverageSettings>) {
+    if !keys.just_pressed(KeyCode::KeyM) {
+        return;
+    }
+
+    settings.mlat = settings.mlat.next();
+    info!("Multilateration: {}", settings.mlat);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dop_geometry() {
+        let target = DVec3::new(43.5, 7.0, 6_000.0);
+        // Square of receivers around the target, ~40 km out, on sites of different heights
+        // (at equal elevation angles, height and emission time can't be told apart)
+        let square = [
+            DVec3::new(43.86, 7.0, 300.0),
+            DVec3::new(43.5, 7.5, 1_800.0),
+            DVec3::new(43.14, 7.0, 900.0),
+            DVec3::new(43.5, 6.5, 2_500.0),
+        ];
+        let inside = tdoa_dop(&square, target).unwrap();
+        // Ideal horizontal geometry: HDOP ~ 1 (Q_ee = Q_nn = 1/2 for four receivers 90 degrees apart)
+        assert!((0.9..1.3).contains(&inside.hdop), "{:?}", inside);
+        // All receivers below the target: height is weakly observed
+        assert!(inside.vdop > inside.hdop);
+
+        // The same receivers seen from far outside the square: same directions, poor fix
+        let outside = tdoa_dop(&square, DVec3::new(43.5, 9.0, 6_000.0)).unwrap();
+        assert!(outside.hdop > 5.0 * inside.hdop, "{:?}", outside);
+
+        // A fifth receiver helps
+        let mut five = square.to_vec();
+        five.push(DVec3::new(43.75, 7.3, 800.0));
+        assert!(tdoa_dop(&five, target).unwrap().hdop < inside.hdop);
+
+        // Too few receivers
+        assert_eq!(tdoa_dop(&square[..3], target), None);
+    }
+
+    #[test]
+    fn test_settings_cycle() {
+        let mut settings = MlatSettings::default();
+        let mut seen = vec![settings.min_receivers];
+        for _ in 0..3 {
+            settings = settings.next();
+            seen.push(settings.min_receivers);
+        }
+        assert_eq!(seen, [4, 5, 6, 4]);
+    }
+}
@@ -0,0 +1,364 @@
+// Interactive placement of a candidate radar station
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+use std::collections::HashSet;
+use crate::cache::TileCache;
+use crate::coverage::CoverageCache;
+use crate::geodesy;
+use crate::radar::{self, Radar, RadarMarker, Radars};
+use crate::tile::TileCoord;
+use crate::ui::pick_terrain;
+use crate::world::{FloatingOrigin, WorldMode, WorldPosition};
+
+/// Mast height of a new candidate (meters above the picked ground)
+pub const DEFAULT_MAST_HEIGHT: f64 = 20.0;
+
+/// While dragging, the preview restarts once the candidate rests this long (seconds)
+pub const PREVIEW_DELAY_SECS: f64 = 0.3;
+
+/// Marker color of the candidate (white: not one of the station colors)
+const CANDIDATE_COLOR: Color = Color::WHITE;
+
+/// Candidate station being placed, and the progress of its coverage preview
+/// The candidate is not part of `Radars` until committed: the terrain is colored for
+/// `Placement::radars`, the stations plus the candidate.
+#[derive(Resource, Default)]
+pub struct Placement {
+    /// Placement mode (N): left clicks place the candidate, dragging moves it
+    pub active: bool,
+    /// Station being placed
+    pub candidate: Option<Radar>,
+    /// Left button held since the candidate was placed
+    pub dragging: bool,
+    /// Time of the last change the preview does not show yet (seconds since startup)
+    pending_since: Option<f64>,
+    /// Candidate shown by the current preview (None: the terrain shows `Radars` alone)
+    previewed: Option<Radar>,
+    /// Tiles still to recolor, nearest to the site last
+    queue: Vec<TileCoord>,
+}
+
+impl Placement {
+    /// Stations to color the terrain with: `Radars` plus the candidate
+    pub fn radars(&self, radars: &Radars) -> Radars {
+        let mut preview = radars.clone();
+        preview.stations.extend(self.candidate.iter().cloned());
+        preview
+    }
+
+    /// Index of the candidate in `Placement::radars` (after the stations of `radars`)
+    pub fn candidate_index(&self, radars: &Radars) -> Option<usize> {
+        self.candidate.as_ref().map(|_| radars.stations.len())
+    }
+
+    /// Place the candidate at a picked terrain point, or move it there
+    /// A new candidate copies `template` (e.g. the first station) on a default mast.
+    pub fn move_candidate(&mut self, template: &Radar, name: &str, lat: f64, lon: f64, ground: f64, now: f64) {
+        let candidate = self.candidate.get_or_insert_with(|| Radar {
+            name: name.to_string(),
+            antenna_height_agl: Some(DEFAULT_MAST_HEIGHT),
+            site_elevation: None,
+            enabled: true,
+            color: CANDIDATE_COLOR,
+            ..template.clone()
+        });
+        if candidate.site_elevation.is_some() && candidate.position.x == lat && candidate.position.y == lon {
+            return;
+        }
+        candidate.position.x = lat;
+        candidate.position.y = lon;
+        candidate.set_site_elevation(ground);
+        self.pending_since = Some(now);
+    }
+
+    /// Add the candidate to the stations; returns its index in `Radars`
+    /// The terrain is recolored for the new `Radars`, so the preview stops here.
+    pub fn commit(&mut self, radars: &mut Radars) -> Option<usize> {
+        let candidate = self.candidate.take()?;
+        radars.stations.push(candidate);
+        self.pending_since = None;
+        self.previewed = None;
+        self.queue.clear();
+        Some(radars.stations.len() - 1)
+    }
+
+    /// Drop the candidate; the tiles it covered are recolored without it
+    pub fn discard(&mut self, now: f64) -> Option<Radar> {
+        let candidate = self.candidate.take()?;
+        self.dragging = false;
+        self.pending_since = Some(now);
+        Some(candidate)
+    }
+
+    /// Whether the preview should restart: the candidate changed and is not being dragged,
+    /// or has rested for `PREVIEW_DELAY_SECS`
+    pub fn preview_due(&self, now: f64) -> bool {
+        self.pending_since.is_some_and(|since| !self.dragging || now - since >= PREVIEW_DELAY_SECS)
+    }
+
+    /// Restart the preview over `tiles` (the meshed tiles): those in range of the candidate
+    /// for a target of `rcs_m2`, of the previous one whose coverage is on screen, or left
+    /// over from an interrupted preview, nearest to the candidate first
+    pub fn start_preview(&mut self, tiles: impl IntoIterator<Item = TileCoord>, rcs_m2: f64) {
+        self.pending_since = None;
+        let leftover: HashSet<TileCoord> = self.queue.drain(..).collect();
+        let sites: Vec<&Radar> = self.candidate.iter().chain(&self.previewed).collect();
+        let Some(center) = sites.first().map(|site| site.position) else {
+            return;
+        };
+        let mut queue: Vec<(f64, TileCoord)> = tiles
+            .into_iter()
+            .filter(|coord| {
+                leftover.contains(coord)
+                    || sites.iter().any(|site| {
+                        tile_distance(*coord, site.position.x, site.position.y) <= site.calculate_max_range(rcs_m2)
+                    })
+            })
+            .map(|coord| (tile_distance(coord, center.x, center.y), coord))
+            .collect();
+        // Farthest first, so popping yields the nearest tile
+        queue.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+        self.queue = queue.into_iter().map(|(_, coord)| coord).collect();
+        self.previewed = self.candidate.clone();
+    }
+
+    /// Next tiles of the preview, nearest to the site first
+    pub fn next_tiles(&mut self, count: usize) -> Vec<TileCoord> {
+        let start = self.queue.len().saturating_sub(count);
+        self.queue.drain(start..).rev().collect()
+    }
+
+    /// Tiles the preview still has to recolor
+    pub fn remaining(&self) -> usize {
+        self.queue.len()
+    }
+
+    /// One-line state for the UI (None outside placement mode without a candidate)
+    pub fn status(&self) -> Option<String> {
+        match (&self.candidate, self.active) {
+            (Some(candidate), _) => Some(format!(
+                "Candidate '{}': {:.5}, {:.5}, ground {:.0} m, mast {:.0} m{}\nEnter: commit, Esc: discard",
+                candidate.name,
+                candidate.position.x,
+                candidate.position.y,
+                candidate.site_elevation.unwrap_or(0.0),
+                candidate.height_above_ground().unwrap_or(0.0),
+                if self.queue.is_empty() { String::new() } else { format!(" (preview: {} tiles left)", self.queue.len()) }
+            )),
+            (None, true) => Some("Placement: click the terrain to place a station".to_string()),
+            (None, false) => None,
+        }
+    }
+}
+
+/// Distance from a site to the nearest point of a tile (meters, 0 inside it)
+pub fn tile_distance(coord: TileCoord, lat: f64, lon: f64) -> f64 {
+    let nearest_lat = lat.clamp(coord.lat as f64, coord.lat as f64 + 1.0);
+    let nearest_lon = lon.clamp(coord.lon as f64, coord.lon as f64 + 1.0);
+    geodesy::distance(lat, lon, nearest_lat, nearest_lon)
+}
+
+/// Marker of the candidate station
+#[derive(Component)]
+pub struct CandidateMarker;
+
+/// System to place and drag the candidate station (N: placement mode, left click/drag)
+#[allow(clippy::too_many_arguments)]
+pub fn placement_input_system(
+    keys: Res<ButtonInput<KeyCode>>,
+    mouse_button: Res<ButtonInput<MouseButton>>,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    camera_query: Query<(&Camera, &GlobalTransform)>,
+    cache: Res<TileCache>,
+    origin: Res<FloatingOrigin>,
+    mode: Res<WorldMode>,
+    radars: Res<Radars>,
+    mut placement: ResMut<Placement>,
+    time: Res<Time>,
+) {
+    if keys.just_pressed(KeyCode::KeyN) {
+        placement.active = !placement.active;
+        placement.dragging = false;
+        info!("Placement mode {}", if placement.active { "on: click the terrain to place a station" } else { "off" });
+    }
+    if !placement.active {
+        return;
+    }
+
+    if mouse_button.just_released(MouseButton::Left) && placement.dragging {
+        placement.dragging = false;
+        if let Some(candidate) = &placement.candidate {
+            info!(
+                "Candidate '{}' at {:.5}, {:.5}: ground {:.0} m, antenna at {:.0} m AMSL",
+                candidate.name,
+                candidate.position.x,
+                candidate.position.y,
+                candidate.site_elevation.unwrap_or(0.0),
+                candidate.position.z
+            );
+        }
+    }
+    if !mouse_button.just_pressed(MouseButton::Left) && !placement.dragging {
+        return;
+    }
+
+    let Ok((camera, camera_transform)) = camera_query.single() else {
+        return;
+    };
+    let Some(cursor_position) = window_query.single().ok().and_then(|window| window.cursor_position()) else {
+        return;
+    };
+    // Off the terrain (sky, unloaded tiles): the candidate stays where it was
+    let Some(hit) = pick_terrain(camera, camera_transform, cursor_position, &origin, *mode, &cache) else {
+        return;
+    };
+
+    let template = radars.stations.first().cloned().unwrap_or_else(|| Radars::default().stations[0].clone());
+    let name = format!("Candidate {}", radars.stations.len() + 1);
+    let now = time.elapsed_secs_f64();
+    placement.move_candidate(&template, &name, hit.x, hit.y, hit.z, now);
+    placement.dragging = true;
+}
+
+/// System to commit the candidate into `Radars` (Enter) or discard it (Escape/Delete)
+#[allow(clippy::too_many_arguments)]
+pub fn placement_commit_system(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    keys: Res<ButtonInput<KeyCode>>,
+    mut radars: ResMut<Radars>,
+    mut placement: ResMut<Placement>,
+    coverage_cache: Res<CoverageCache>,
+    origin: Res<FloatingOrigin>,
+    mode: Res<WorldMode>,
+    time: Res<Time>,
+) {
+    if placement.candidate.is_none() || placement.dragging {
+        return;
+    }
+
+    if keys.just_pressed(KeyCode::Enter) {
+        let Some(index) = placement.commit(&mut radars) else {
+            return;
+        };
+        let radar = &radars.stations[index];
+        coverage_cache.commit_candidate(radar);
+        let world_pos = mode.geo_to_world(radar.position.x, radar.position.y, radar.position.z);
+        radar::spawn_marker(&mut commands, &mut meshes, &mut materials, radar, world_pos, &origin, RadarMarker { index });
+        info!(
+            "Radar '{}' added at {:.5}, {:.5} (antenna {:.0} m AMSL); F5 saves the scenario",
+            radar.name, radar.position.x, radar.position.y, radar.position.z
+        );
+    } else if keys.just_pressed(KeyCode::Escape) || keys.just_pressed(KeyCode::Delete) {
+        if let Some(candidate) = placement.discard(time.elapsed_secs_f64()) {
+            coverage_cache.evict_candidates(None);
+            info!("Candidate '{}' discarded", candidate.name);
+        }
+    }
+}
+
+/// System to show the candidate: spawned, moved and despawned with it
+pub fn update_candidate_marker_system(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    placement: Res<Placement>,
+    origin: Res<FloatingOrigin>,
+    mode: Res<WorldMode>,
+    mut query: Query<(Entity, &mut WorldPosition), With<CandidateMarker>>,
+) {
+    if !placement.is_changed() && !mode.is_changed() {
+        return;
+    }
+
+    let marker = query.single_mut().ok();
+    match (&placement.candidate, marker) {
+        (Some(candidate), Some((_, mut world_pos))) => {
+            let position = mode.geo_to_world(candidate.position.x, candidate.position.y, candidate.position.z);
+            // Only touch the marker when it moves (the placement changes every preview frame)
+            if world_pos.0 != position {
+                world_pos.0 = position;
+            }
+        }
+        (Some(candidate), None) => {
+            let position = mode.geo_to_world(candidate.position.x, candidate.position.y, candidate.position.z);
+            radar::spawn_marker(&mut commands, &mut meshes, &mut materials, candidate, position, &origin, CandidateMarker);
+        }
+        (None, Some((entity, _))) => commands.entity(entity).despawn(),
+        (None, None) => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{default_radar, TARGET_RCS};
+
+    #[test]
+    fn test_candidate_lifecycle() {
+        let mut placement = Placement::default();
+        placement.move_candidate(&default_radar(), "Candidate 7", 43.5, 7.3, 600.0, 1.0);
+
+        let candidate = placement.candidate.as_ref().unwrap();
+        assert_eq!(candidate.position.z, 600.0 + DEFAULT_MAST_HEIGHT);
+        assert_eq!(candidate.height_above_ground(), Some(DEFAULT_MAST_HEIGHT));
+        let base = Radars::default();
+        assert_eq!(placement.radars(&base).stations.len(), base.stations.len() + 1);
+
+        // Dragged: the preview waits for the candidate to rest, or for the release
+        placement.dragging = true;
+        placement.move_candidate(&default_radar(), "ignored", 43.6, 7.3, 200.0, 2.0);
+        assert_eq!(placement.candidate.as_ref().unwrap().name, "Candidate 7");
+        assert_eq!(placement.candidate.as_ref().unwrap().position.z, 200.0 + DEFAULT_MAST_HEIGHT);
+        assert!(!placement.preview_due(2.1));
+        assert!(placement.preview_due(2.5));
+        placement.dragging = false;
+        assert!(placement.preview_due(2.1));
+
+        let mut radars = Radars::default();
+        let index = placement.commit(&mut radars).unwrap();
+        assert_eq!(index, base.stations.len());
+        assert_eq!(radars.stations[index].name, "Candidate 7");
+        assert!(placement.candidate.is_none() && !placement.preview_due(3.0));
+        assert!(placement.commit(&mut radars).is_none());
+
+        // Discarding recolors the tiles the candidate covered
+        placement.move_candidate(&default_radar(), "Candidate 8", 43.5, 7.3, 600.0, 4.0);
+        placement.start_preview([TileCoord::new(43, 7)], TARGET_RCS);
+        assert!(placement.discard(5.0).is_some());
+        assert!(placement.preview_due(5.0));
+        placement.start_preview([TileCoord::new(43, 7)], TARGET_RCS);
+        assert_eq!(placement.next_tiles(4), vec![TileCoord::new(43, 7)]);
+    }
+
+    #[test]
+    fn test_preview_order() {
+        let mut placement = Placement::default();
+        placement.move_candidate(&default_radar(), "Candidate", 43.5, 7.5, 100.0, 0.0);
+        let range = placement.candidate.as_ref().unwrap().calculate_max_range(TARGET_RCS);
+
+        assert_eq!(tile_distance(TileCoord::new(43, 7), 43.5, 7.5), 0.0);
+        let far = TileCoord::new(43, 7 + (range / 80_000.0).ceil() as i32 + 1);
+        assert!(tile_distance(far, 43.5, 7.5) > range);
+
+        let tiles = [TileCoord::new(43, 9), far, TileCoord::new(43, 7), TileCoord::new(44, 8), TileCoord::new(43, 8)];
+        placement.start_preview(tiles, TARGET_RCS);
+        assert_eq!(placement.remaining(), 4);
+        assert_eq!(placement.next_tiles(2), vec![TileCoord::new(43, 7), TileCoord::new(43, 8)]);
+        assert_eq!(placement.next_tiles(5), vec![TileCoord::new(44, 8), TileCoord::new(43, 9)]);
+        assert_eq!(placement.remaining(), 0);
+
+        // Moved east: the tiles of the old position are recolored too
+        placement.move_candidate(&default_radar(), "Candidate", 43.5, far.lon as f64 + 0.5, 100.0, 1.0);
+        placement.start_preview(tiles, TARGET_RCS);
+        assert_eq!(placement.remaining(), 5);
+        assert_eq!(placement.next_tiles(1), vec![far]);
+
+        // Moved again before the preview finished: its remaining tiles are kept
+        placement.move_candidate(&default_radar(), "Candidate", 43.5, far.lon as f64 + 5.5, 100.0, 2.0);
+        placement.start_preview(tiles, TARGET_RCS);
+        assert_eq!(placement.remaining(), 5);
+    }
+}
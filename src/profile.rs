@@ -0,0 +1,330 @@
+// Terrain profiles along radar paths: Fresnel clearance, diffraction loss and signal margin
+use crate::coverage::{CoverageAltitude, CoverageGrid};
+use crate::detection::DetectionSettings;
+use crate::geodesy::GeodesicLine;
+use crate::radar::Radar;
+use crate::tile::{TileCoord, TileData};
+use bevy::math::DVec3;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Finest spacing of profile samples (meters, about the SRTM1 resolution)
+const MIN_STEP: f64 = 100.0;
+
+/// Most samples in a profile; longer paths are sampled more coarsely
+const MAX_POINTS: usize = 1000;
+
+/// Levels of sub-edges below the main edge in the Deygout construction
+/// (1: the main edge and one sub-edge on each side, as ITU-R P.526 recommends)
+const DEYGOUT_DEPTH: usize = 1;
+
+/// Below this diffraction parameter an edge adds no loss (ITU-R P.526 validity limit)
+const KNIFE_EDGE_MIN_V: f64 = -0.78;
+
+/// Signal margin levels drawn as bands in the margin view (dB, lowest first)
+pub const MARGIN_BANDS: [f32; 4] = [0.0, 3.0, 10.0, 20.0];
+
+/// Terrain sample along a path
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ProfilePoint {
+    /// Distance from the start of the path (meters)
+    pub distance: f64,
+    /// Terrain height (meters AMSL, 0 where no tile is loaded)
+    pub terrain: f64,
+    /// Terrain raised by the effective Earth bulge d1 d2 / (2 R_eff), so that the ray is a
+    /// straight line between the end heights
+    pub ground: f64,
+}
+
+/// Terrain between two points, flattened over the effective Earth
+#[derive(Debug, Clone, PartialEq)]
+pub struct TerrainProfile {
+    /// Geodesic length of the path (meters)
+    pub length: f64,
+    /// Height of the start of the path (meters AMSL)
+    pub start_height: f64,
+    /// Height of the end of the path (meters AMSL)
+    pub end_height: f64,
+    /// Terrain under the start of the path (meters AMSL, 0 where no tile is loaded)
+    pub start_terrain: f64,
+    /// Terrain under the end of the path
+    pub end_terrain: f64,
+    /// Samples strictly between the ends, in order
+    pub points: Vec<ProfilePoint>,
+}
+
+/// Where the terrain loss of a `PathMargin` comes from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LossModel {
+    KnifeEdge,
+    /// ITM, with its warning code (1-3: outside the validated range, results questionable)
+    Itm { warnings: u8 },
+    /// Knife edge in place of an ITM prediction with parameters out of range (warning 4,
+    /// usually an airborne receiver more than 3000 m above the ground)
+    ItmOutOfRange,
+}
+
+impl std::fmt::Display for LossModel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LossModel::KnifeEdge => write!(f, "knife edge"),
+            LossModel::Itm { warnings: 0 } => write!(f, "ITM"),
+            LossModel::Itm { warnings } => write!(f, "ITM, warning {}", warnings),
+            LossModel::ItmOutOfRange => write!(f, "knife edge, out of ITM's range"),
+        }
+    }
+}
+
+/// Diffraction and clearance along a path, and the resulting link margin
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PathMargin {
+    /// SNR above the detection threshold of the target class (dB, negative when lost)
+    pub margin_db: f64,
+    /// One-way loss of the terrain beyond free space (dB; diffraction, or the ITM excess loss)
+    pub terrain_loss_db: f64,
+    /// Model that gave `terrain_loss_db`
+    pub loss_model: LossModel,
+    /// Smallest clearance of the ray over the terrain in first Fresnel zone radii
+    /// (1 or more: free space; 0: grazing; negative: obstructed)
+    pub fresnel_clearance: f64,
+}
+
+impl TerrainProfile {
+    /// Profile along the geodesic from `start` to `end` ((lat, lon, alt) like `Radar::position`)
+    /// over an Earth of effective radius `r_eff`
+    pub fn extract(start: DVec3, end: DVec3, r_eff: f64, cache_snapshot: &HashMap<TileCoord, Arc<TileData>>) -> Self {
+        let (path, length) = GeodesicLine::between(start.x, start.y, end.x, end.y);
+        let step = (length / MAX_POINTS as f64).max(MIN_STEP);
+        let num_steps = (length / step).ceil() as usize;
+
+        let mut current: Option<(TileCoord, Option<&TileData>)> = None;
+        let mut terrain_at = |lat: f64, lon: f64| {
+            // Same tile lookup as the raycast: keep the current tile between samples
+            let coord = TileCoord::from_world_coords(lat, lon);
+            if current.is_none_or(|(cached, _)| cached != coord) {
+                current = Some((coord, cache_snapshot.get(&coord).map(|tile| tile.as_ref())));
+            }
+            current.and_then(|(_, tile)| tile).map_or(0.0, |tile| {
+                let nx = (lon - coord.lon as f64) as f32;
+                let ny = (1.0 - (lat - coord.lat as f64)) as f32; // Inverted Y for SRTM
+                tile.get_height_normalized(nx, ny) as f64
+            })
+        };
+
+        let start_terrain = terrain_at(start.x, start.y);
+        let points = (1..num_steps)
+            .map(|i| {
+                let distance = length * i as f64 / num_steps as f64;
+                let (lat, lon) = path.position(distance);
+                let terrain = terrain_at(lat, lon);
+
+                let bulge = distance * (length - distance) / (2.0 * r_eff);
+                ProfilePoint { distance, terrain, ground: terrain + bulge }
+            })
+            .collect();
+        let end_terrain = terrain_at(end.x, end.y);
+
+        Self { length, start_height: start.z, end_height: end.z, start_terrain, end_terrain, points }
+    }
+
+    /// Height of the straight ray at `distance` from the start
+    pub fn ray_height(&self, distance: f64) -> f64 {
+        self.start_height + (self.end_height - self.start_height) * distance / self.length
+    }
+
+    /// Smallest clearance of the ray in first Fresnel zone radii (infinite without samples)
+    pub fn fresnel_clearance(&self, wavelength: f64) -> f64 {
+        self.points
+            .iter()
+            .map(|point| {
+                let radius = fresnel_radius(point.distance, self.length - point.distance, wavelength);
+                (self.ray_height(point.distance) - point.ground) / radius
+            })
+            .fold(f64::INFINITY, f64::min)
+    }
+
+    /// Diffraction loss over the terrain (dB, one way)
+    ///
+    /// ALGORITHM: Deygout Multiple Knife Edges (ITU-R P.526)
+    /// Every sample is a candidate knife edge with parameter
+    ///     v = h sqrt(2 (d1 + d2) / (lambda d1 d2))
+    /// where h is its height above the ray between the path ends. The edge with the largest
+    /// v is the main edge and contributes J(v); the two sub-paths on either side of it are
+    /// then treated the same way, against the ray from their ends to the main edge's summit.
+    pub fn diffraction_loss_db(&self, wavelength: f64) -> f64 {
+        deygout(&self.points, (0.0, self.start_height), (self.length, self.end_height), wavelength, DEYGOUT_DEPTH)
+    }
+}
+
+/// Radius of the first Fresnel zone at distances `d1` and `d2` from the path ends (meters)
+pub fn fresnel_radius(d1: f64, d2: f64, wavelength: f64) -> f64 {
+    (wavelength * d1 * d2 / (d1 + d2)).sqrt()
+}
+
+/// Loss of a single knife edge with diffraction parameter `v` (dB, ITU-R P.526 eq. 31)
+/// About 6 dB at grazing incidence (v = 0), 0 once the edge clears the Fresnel zone.
+pub fn knife_edge_loss_db(v: f64) -> f64 {
+    if v <= KNIFE_EDGE_MIN_V {
+        return 0.0;
+    }
+    6.9 + 20.0 * (((v - 0.1).powi(2) + 1.0).sqrt() + v - 0.1).log10()
+}
+
+/// Deygout loss of the samples between `start` and `end` ((distance, height) of the ray ends)
+fn deygout(points: &[ProfilePoint], start: (f64, f64), end: (f64, f64), wavelength: f64, depth: usize) -> f64 {
+    let span = end.0 - start.0;
+    let main = points
+        .iter()
+        .enumerate()
+        .filter(|(_, point)| point.distance > start.0 && point.distance < end.0)
+        .map(|(i, point)| {
+            let (d1, d2) = (point.distance - start.0, end.0 - point.distance);
+            let ray = start.1 + (end.1 - start.1) * d1 / span;
+            let v = (point.ground - ray) * (2.0 * span / (wavelength * d1 * d2)).sqrt();
+            (i, v)
+        })
+        .max_by(|a, b| a.1.total_cmp(&b.1));
+
+    let Some((index, v)) = main else {
+        return 0.0;
+    };
+    if v <= KNIFE_EDGE_MIN_V {
+        return 0.0;
+    }
+
+    let mut loss = knife_edge_loss_db(v);
+    if depth > 0 {
+        let summit = (points[index].distance, points[index].ground);
+        loss += deygout(&points[..index], start, summit, wavelength, depth - 1);
+        loss += deygout(&points[index + 1..], summit, end, wavelength, depth - 1);
+    }
+    loss
+}
+
+/// Best signal margin over the network at each sample of a tile layer
+pub struct MarginGrid {
+    pub resolution: usize,
+    /// Margin of the best station (dB, negative infinity where no station is in range)
+    pub values: Vec<f32>,
+}
+
+impl MarginGrid {
+    /// Signal margin at each sample of the layer: the best over `radars` of the free-space
+    /// margin for the target of `detection` minus the terrain's diffraction loss
+    pub fn compute(
+        radars: &[&Radar],
+        tile: &TileData,
+        resolution: usize,
+        altitude: CoverageAltitude,
+        detection: &DetectionSettings,
+        cache_snapshot: &HashMap<TileCoord, Arc<TileData>>,
+    ) -> Self {
+        use rayon::prelude::*;
+
+        let max_coord = tile.size - 1;
+        let values = (0..resolution * resolution)
+            .into_par_iter()
+            .map(|i| {
+                let (x, y) = (i % resolution, i / resolution);
+                let (lat, lon) = CoverageGrid::sample_geo(tile.coord, resolution, x, y);
+                let tx = (x * max_coord + (resolution - 1) / 2) / (resolution - 1);
+                let ty = (y * max_coord + (resolution - 1) / 2) / (resolution - 1);
+                let ground = tile.get_height(tx, ty).unwrap_or(0) as f32;
+                let height = altitude.target_altitude(ground);
+
+                radars
+                    .iter()
+                    .filter_map(|radar| radar.path_margin(lat, lon, height, detection, cache_snapshot))
+                    .map(|path| path.margin_db as f32)
+                    .fold(f32::NEG_INFINITY, f32::max)
+            })
+            .collect();
+
+        Self { resolution, values }
+    }
+
+    /// Nearest sample to a normalized tile position (0..1 from the NW corner)
+    pub fn sample(&self, nx: f32, ny: f32) -> f32 {
+        let max = (self.resolution - 1) as f32;
+        let x = (nx.clamp(0.0, 1.0) * max).round() as usize;
+        let y = (ny.clamp(0.0, 1.0) * max).round() as usize;
+        self.values[y * self.resolution + x]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Flat profile at sea level with one obstacle of `height` half-way (no Earth bulge)
+    fn single_edge(height: f64) -> TerrainProfile {
+        let points = (1..100)
+            .map(|i| {
+                let distance = i as f64 * 100.0;
+                let terrain = if i == 50 { height } else { 0.0 };
+                ProfilePoint { distance, terrain, ground: terrain }
+            })
+            .collect();
+        TerrainProfile { length: 10_000.0, start_height: 100.0, end_height: 100.0, start_terrain: 0.0, end_terrain: 0.0, points }
+    }
+
+    #[test]
+    fn test_knife_edge() {
+        // Grazing incidence: 6 dB; well clear: nothing; deep shadow: grows with v
+        assert!((knife_edge_loss_db(0.0) - 6.0).abs() < 0.1);
+        assert_eq!(knife_edge_loss_db(-1.0), 0.0);
+        assert!((knife_edge_loss_db(1.0) - 13.9).abs() < 0.2);
+        assert!(knife_edge_loss_db(2.4) > knife_edge_loss_db(1.0) + 5.0);
+    }
+
+    #[test]
+    fn test_single_edge_profile() {
+        let wavelength = 0.23; // L band
+        let f1 = fresnel_radius(5_000.0, 5_000.0, wavelength);
+        assert!((f1 - 23.98).abs() < 0.01);
+
+        // An edge grazing the ray: 6 dB, zero clearance
+        let grazing = single_edge(100.0);
+        assert!((grazing.diffraction_loss_db(wavelength) - 6.0).abs() < 0.1);
+        assert!(grazing.fresnel_clearance(wavelength).abs() < 1e-9);
+
+        // Clear of the first Fresnel zone: no loss
+        let clear = single_edge(100.0 - f1);
+        assert_eq!(clear.diffraction_loss_db(wavelength), 0.0);
+        assert!((clear.fresnel_clearance(wavelength) - 1.0).abs() < 1e-9);
+
+        // A metre above or below the ray changes the loss by about a dB, not all or nothing
+        let above = single_edge(101.0).diffraction_loss_db(wavelength);
+        let below = single_edge(99.0).diffraction_loss_db(wavelength);
+        assert!(below < 6.0 && above > 6.0 && above - below < 1.5);
+    }
+
+    #[test]
+    fn test_deygout_sub_edges() {
+        let wavelength = 0.23;
+        let mut profile = single_edge(130.0);
+        let main_only = profile.diffraction_loss_db(wavelength);
+        // A second ridge between the radar and the main edge adds its own loss
+        profile.points[24].ground = 125.0;
+        let two_edges = profile.diffraction_loss_db(wavelength);
+        assert!(two_edges > main_only + 3.0, "{} vs {}", two_edges, main_only);
+    }
+
+    #[test]
+    fn test_extract_over_sea() {
+        // No tiles: sea level, only the Earth bulge, which peaks half-way
+        let r_eff = crate::refraction::Refraction::STANDARD.effective_radius();
+        let profile = TerrainProfile::extract(
+            DVec3::new(43.0, 7.0, 10.0),
+            DVec3::new(43.0, 8.0, 10.0),
+            r_eff,
+            &HashMap::new(),
+        );
+        let peak = profile.points.iter().map(|point| point.ground).fold(0.0, f64::max);
+        let expected = profile.length * profile.length / (8.0 * r_eff);
+        assert!((peak - expected).abs() < 1.0, "{} vs {}", peak, expected);
+        // Two 10 m masts 81 km apart are far beyond each other's horizon
+        assert!(profile.fresnel_clearance(0.23) < 0.0);
+        assert!(profile.diffraction_loss_db(0.23) > 20.0);
+    }
+}
@@ -1,19 +1,169 @@
 use bevy::prelude::*;
 use bevy::math::DVec3;
+use std::collections::HashSet;
+use crate::antenna::Antenna;
+use crate::detection::{required_snr, DetectionSettings, PD_CONTOURS};
+use crate::geodesy::{self, GeodesicLine};
+use crate::itm::{self, Propagation};
+use crate::profile::{LossModel, PathMargin, TerrainProfile};
+use crate::refraction::Refraction;
+use crate::station::StationType;
+use crate::world::{FloatingOrigin, WorldMode, WorldPosition};
+
+/// Mean Earth Radius in Meters
+pub const EARTH_RADIUS: f64 = 6_371_000.0;
+/// Effective Earth radius factor for standard atmospheric refraction (4/3 Earth)
+pub const EFFECTIVE_EARTH_FACTOR: f64 = 4.0 / 3.0;
+
+pub const SPEED_OF_LIGHT: f64 = 299_792_458.0;
+const BOLTZMANN: f64 = 1.380649e-23;
+/// Reference noise temperature (K)
+const REF_TEMP: f64 = 290.0;
+/// Free-space margin below which `Radar::path_margin` gives up (dB): the terrain can't lift
+/// the target back to the detection threshold (ITM's two-ray gain is at most 6 dB)
+const MARGIN_FLOOR_DB: f64 = -10.0;
 
 /// Individual Radar Station
 #[derive(Clone, Debug)]
 pub struct Radar {
     pub name: String,
-    pub position: DVec3, // Lat (deg), Lon (deg), Alt (meters)
+    pub position: DVec3, // Lat (deg), Lon (deg), Alt (meters AMSL of the antenna)
+    pub antenna_height_agl: Option<f64>, // Mast height above the site ground; None: Alt is configured AMSL
+    pub site_elevation: Option<f64>, // Ground elevation of the site from the DEM (None until its tile loads)
     pub enabled: bool,
     pub color: Color,
+    pub station_type: StationType, // Primary radar or cooperative (one-way) station
     
     // Physics Parameters
     pub frequency: f64,       // Hz (e.g. 1.3e9 for 1.3 GHz)
     pub transmit_power_dbm: f64, // dBm (e.g. 60.0 for 1kW)
     pub gain_dbi: f64,        // dBi (e.g. 30.0)
     pub sensitivity_dbm: f64, // dBm (e.g. -100.0)
+    pub noise_figure_db: f64, // dB (receiver noise above kTB)
+    pub bandwidth_hz: f64,    // Hz (receiver bandwidth, e.g. 1e6)
+    pub processing_gain_db: f64, // dB (pulse compression and integration)
+
+    // Antenna
+    pub antenna: Antenna, // Gain pattern, elevation coverage and blanked sectors
+
+    // Propagation
+    pub refraction: Refraction, // Atmosphere between the radar and its targets
+    pub propagation: Propagation, // Terrain loss model of the signal margin
+}
+
+/// Terrain sample along a radar ray
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TerrainSample {
+    pub lat: f64,
+    pub lon: f64,
+    /// Terrain height (meters AMSL)
+    pub height: f32,
+    /// Distance from the radar along the ground (meters)
+    pub range_m: f64,
+    /// Elevation angle of the sample seen from the radar, curvature-corrected (degrees)
+    pub elevation_deg: f64,
+}
+
+/// Line of sight from a radar to a target (see `Radar::line_of_sight`)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LosResult {
+    /// The ray clears the terrain everywhere
+    pub visible: bool,
+    /// Smallest height of the ray above the terrain (meters): the margin when visible,
+    /// minus the depth of the deepest obstruction when occluded. Infinite without terrain.
+    pub clearance_m: f64,
+    /// Terrain sample where that clearance occurs (None when no terrain was loaded)
+    pub critical: Option<TerrainSample>,
+}
+
+/// Line of sight from every enabled radar to one target
+#[derive(Clone, Debug, Default)]
+pub struct VisibilityReport {
+    /// (station index in `Radars`, result), None when out of range or beyond the radio horizon
+    pub per_radar: Vec<(usize, Option<LosResult>)>,
+}
+
+impl VisibilityReport {
+    /// Number of radars that see the target
+    pub fn visible_count(&self) -> usize {
+        self.per_radar.iter().filter(|(_, los)| los.is_some_and(|los| los.visible)).count()
+    }
+
+    /// Radar with the largest clearance: the best view when visible, otherwise the
+    /// radar for which the target is least occluded
+    pub fn best(&self) -> Option<(usize, LosResult)> {
+        self.per_radar
+            .iter()
+            .filter_map(|(index, los)| los.map(|los| (*index, los)))
+            .max_by(|(_, a), (_, b)| a.clearance_m.total_cmp(&b.clearance_m))
+    }
+}
+
+/// Setting cycled over every station from the keyboard, on top of the stations' own settings
+/// The stations' settings are kept (by name) when a preset first replaces them, and the step
+/// that would come back to the first preset hands them back instead, so the per-station
+/// values of a scenario are never lost.
+pub struct PresetCycle<T> {
+    /// First and current preset, while one is applied
+    applied: Option<(T, T)>,
+    originals: Vec<(String, T)>,
+}
+
+impl<T> Default for PresetCycle<T> {
+    fn default() -> Self {
+        Self { applied: None, originals: Vec::new() }
+    }
+}
+
+impl<T: Clone + PartialEq> PresetCycle<T> {
+    /// Preset applied to every station, None while they use their own settings
+    pub fn current(&self) -> Option<&T> {
+        self.applied.as_ref().map(|(_, current)| current)
+    }
+
+    /// Apply the preset after the current one (after the first station's own setting to
+    /// start), or restore the stations' own settings at the end of the cycle
+    /// Returns the preset applied, None when the own settings were restored.
+    pub fn step(&mut self, radars: &mut Radars, field: fn(&mut Radar) -> &mut T, next: impl Fn(&T) -> T) -> Option<T> {
+        let preset = match &self.applied {
+            Some((first, current)) => Some(next(current)).filter(|preset| preset != first),
+            None => radars.stations.first_mut().map(|radar| next(field(radar))),
+        };
+        match preset {
+            Some(preset) => {
+                self.apply(radars, field, preset.clone());
+                Some(preset)
+            }
+            None => {
+                self.restore(radars, field);
+                None
+            }
+        }
+    }
+
+    /// Use `preset` for every station, keeping the settings it replaces
+    pub fn apply(&mut self, radars: &mut Radars, field: fn(&mut Radar) -> &mut T, preset: T) {
+        for radar in &mut radars.stations {
+            // Stations committed since the cycle started keep theirs too
+            if !self.originals.iter().any(|(name, _)| *name == radar.name) {
+                self.originals.push((radar.name.clone(), field(radar).clone()));
+            }
+            *field(radar) = preset.clone();
+        }
+        let first = self.applied.take().map_or_else(|| preset.clone(), |(first, _)| first);
+        self.applied = Some((first, preset));
+    }
+
+    /// Give every station its own setting back
+    pub fn restore(&mut self, radars: &mut Radars, field: fn(&mut Radar) -> &mut T) {
+        for radar in &mut radars.stations {
+            if let Some((_, own)) = self.originals.iter().find(|(name, _)| *name == radar.name) {
+                *field(radar) = own.clone();
+            }
+        }
+        self.originals.clear();
+        self.applied = None;
+    }
 }
 
 /// Resource holding all radar stations
@@ -29,32 +179,117 @@ impl Default for Radars {
                 Radar {
                     name: "Mont Agel".to_string(),
                     position: DVec3::new(43.77528, 7.42639, 1248.0), 
+                    antenna_height_agl: None,
+                    site_elevation: None,
                     enabled: true,
                     color: Color::srgb(0.0, 1.0, 1.0), // Cyan
+                    station_type: StationType::Primary,
                     frequency: 1.3e9, // 1.3 GHz (L-Band)
                     transmit_power_dbm: 80.0, // 100 kW (Typical En-Route Peak)
                     gain_dbi: 35.0, // High gain antenna
                     sensitivity_dbm: -113.0, // High sensitivity
+                    noise_figure_db: 3.0,
+                    bandwidth_hz: 1.0e6,
+                    processing_gain_db: 13.0, // ~20 pulses integrated per dwell
+                    antenna: Antenna::isotropic(),
+                    refraction: Refraction::STANDARD,
+                    propagation: Propagation::KnifeEdge,
                 },
                 Radar {
                     name: "Sainte-Baume".to_string(),
                     position: DVec3::new(43.3337, 5.7866, 1148.0),
+                    antenna_height_agl: None,
+                    site_elevation: None,
                     enabled: true,
                     color: Color::srgb(1.0, 0.0, 1.0), // Magenta
+                    station_type: StationType::Primary,
                     frequency: 1.3e9,
                     transmit_power_dbm: 80.0,
                     gain_dbi: 35.0,
                     sensitivity_dbm: -113.0,
+                    noise_figure_db: 3.0,
+                    bandwidth_hz: 1.0e6,
+                    processing_gain_db: 13.0,
+                    antenna: Antenna::isotropic(),
+                    refraction: Refraction::STANDARD,
+                    propagation: Propagation::KnifeEdge,
                 },
                 Radar {
                     name: "Lyon (Mont Verdun)".to_string(),
                     position: DVec3::new(45.8498, 4.7795, 626.0),
+                    antenna_height_agl: None,
+                    site_elevation: None,
                     enabled: true,
                     color: Color::srgb(1.0, 1.0, 0.0), // Yellow
+                    station_type: StationType::Primary,
                     frequency: 1.3e9,
                     transmit_power_dbm: 80.0,
                     gain_dbi: 35.0,
                     sensitivity_dbm: -113.0,
+                    noise_figure_db: 3.0,
+                    bandwidth_hz: 1.0e6,
+                    processing_gain_db: 13.0,
+                    antenna: Antenna::isotropic(),
+                    refraction: Refraction::STANDARD,
+                    propagation: Propagation::KnifeEdge,
+                },
+                // Cooperative stations, off by default (toggle with 4-6)
+                Radar {
+                    name: "Mont Agel SSR".to_string(),
+                    position: DVec3::new(43.77528, 7.42639, 1248.0),
+                    antenna_height_agl: None,
+                    site_elevation: None,
+                    enabled: false,
+                    color: Color::srgb(0.0, 0.5, 1.0), // Azure
+                    station_type: StationType::ssr(),
+                    frequency: 1.03e9, // 1030 MHz interrogation
+                    transmit_power_dbm: 63.0, // 2 kW
+                    gain_dbi: 27.0,
+                    sensitivity_dbm: -80.0,
+                    noise_figure_db: 3.0,
+                    bandwidth_hz: 8.0e6,
+                    processing_gain_db: 0.0,
+                    antenna: Antenna::isotropic(),
+                    refraction: Refraction::STANDARD,
+                    propagation: Propagation::KnifeEdge,
+                },
+                Radar {
+                    name: "Nice ADS-B".to_string(),
+                    position: DVec3::new(43.6584, 7.2159, 30.0), // Until the site's ground is known
+                    antenna_height_agl: Some(25.0), // Mast on the airport
+                    site_elevation: None,
+                    enabled: false,
+                    color: Color::srgb(0.0, 1.0, 0.3), // Green
+                    station_type: StationType::adsb(),
+                    frequency: 1.09e9,
+                    transmit_power_dbm: 0.0, // Receive only
+                    gain_dbi: 8.0,
+                    sensitivity_dbm: -90.0,
+                    noise_figure_db: 3.0,
+                    bandwidth_hz: 8.0e6,
+                    processing_gain_db: 0.0,
+                    antenna: Antenna::isotropic(),
+                    refraction: Refraction::STANDARD,
+                    propagation: Propagation::KnifeEdge,
+                },
+                Radar {
+                    name: "Mont Ventoux VHF".to_string(),
+                    position: DVec3::new(44.1740, 5.2790, 1910.0),
+                    antenna_height_agl: None,
+                    site_elevation: None,
+                    enabled: false,
+                    color: Color::srgb(1.0, 0.5, 0.0), // Orange
+                    station_type: StationType::vhf_radio(),
+                    frequency: 1.25e8, // 125 MHz (airband)
+                    transmit_power_dbm: 47.0, // 50 W
+                    gain_dbi: 3.0,
+                    sensitivity_dbm: -101.0,
+                    noise_figure_db: 6.0,
+                    bandwidth_hz: 25.0e3,
+                    processing_gain_db: 0.0,
+                    antenna: Antenna::isotropic(),
+                    refraction: Refraction::STANDARD,
+                    propagation: Propagation::KnifeEdge,
                 },
             ],
         }
@@ -62,33 +297,84 @@ impl Default for Radars {
 }
 
 impl Radars {
-    /// Check if a point is visible by ANY enabled radar station.
-    /// Returns (is_visible, color_of_station)
+    /// Use the same refraction model for every station (the scenario's atmosphere)
+    pub fn set_refraction(&mut self, refraction: Refraction) {
+        for radar in &mut self.stations {
+            radar.refraction = refraction;
+        }
+    }
+
+    /// Refraction model of the scenario (that of the first station)
+    pub fn refraction(&self) -> Refraction {
+        self.stations.first().map_or(Refraction::STANDARD, |radar| radar.refraction)
+    }
+
+    /// Copy of the stations under another refraction model, for comparisons
+    pub fn with_refraction(&self, refraction: Refraction) -> Self {
+        let mut radars = self.clone();
+        radars.set_refraction(refraction);
+        radars
+    }
+
+    /// Line of sight from each enabled radar to a target of `rcs_m2` (raycast)
     pub fn check_visibility(
         &self,
         target_lat: f64,
         target_lon: f64,
         target_alt: f32,
+        rcs_m2: f64,
         cache_snapshot: &std::collections::HashMap<crate::tile::TileCoord, std::sync::Arc<crate::tile::TileData>>,
-    ) -> (bool, Option<Color>) {
-        for radar in &self.stations {
-            if !radar.enabled { continue; }
-            if radar.is_visible_raycast(target_lat, target_lon, target_alt, cache_snapshot) {
-                return (true, Some(radar.color));
-            }
-        }
-        (false, None)
+    ) -> VisibilityReport {
+        let per_radar = self
+            .stations
+            .iter()
+            .enumerate()
+            .filter(|(_, radar)| radar.enabled)
+            .map(|(index, radar)| (index, radar.line_of_sight(target_lat, target_lon, target_alt, rcs_m2, cache_snapshot)))
+            .collect();
+        VisibilityReport { per_radar }
+    }
+
+    /// Tiles crossed by the rays from enabled radars to any point of `coord`
+    /// (excluding the tile itself). Coverage of `coord` depends on their terrain.
+    pub fn tiles_between(&self, coord: crate::tile::TileCoord, rcs_m2: f64) -> HashSet<crate::tile::TileCoord> {
+        self.stations
+            .iter()
+            .filter(|radar| radar.enabled)
+            .flat_map(|radar| radar.tiles_between(coord, rcs_m2))
+            .collect()
     }
 }
 
 impl Radar {
-    /// Calculate Maximum Detection Range using the Radar Range Equation
+    /// Record the ground elevation of the site (from the DEM)
+    /// A station with a mast height gets its antenna at ground + mast; one configured in
+    /// AMSL keeps its altitude. `position.z` is the effective antenna altitude everything
+    /// else uses (range checks, raycasts, horizons and the marker).
+    pub fn set_site_elevation(&mut self, ground: f64) {
+        self.site_elevation = Some(ground);
+        if let Some(agl) = self.antenna_height_agl {
+            self.position.z = ground + agl;
+        }
+    }
+
+    /// Height of the antenna above the site ground, when the ground is known
+    pub fn height_above_ground(&self) -> Option<f64> {
+        self.site_elevation.map(|ground| self.position.z - ground)
+    }
+
+    /// Whether a configured AMSL altitude puts the antenna below the terrain of its site
+    pub fn is_below_terrain(&self) -> bool {
+        self.antenna_height_agl.is_none() && self.height_above_ground().is_some_and(|height| height < 0.0)
+    }
+
+    /// Calculate Maximum Detection Range of a target of `rcs_m2` using the Radar Range
+    /// Equation, or the shortest one-way link of a cooperative station (which ignores the RCS)
     /// Returns range in meters
-    pub fn calculate_max_range(&self) -> f64 {
-        const SPEED_OF_LIGHT: f64 = 299_792_458.0;
-        const BOLTZMANN: f64 = 1.380649e-23;
-        const REF_TEMP: f64 = 290.0;
-        const DEFAULT_RCS: f64 = 5.0; // 5 m^2 (Typical fighter/small aircraft)
+    pub fn calculate_max_range(&self, rcs_m2: f64) -> f64 {
+        if self.station_type != StationType::Primary {
+            return self.station_type.links(self).iter().map(|link| link.max_range()).fold(f64::INFINITY, f64::min);
+        }
 
         // Convert decibels to linear units
         let p_t = 10.0_f64.powf((self.transmit_power_dbm - 30.0) / 10.0); // Watts
@@ -100,7 +386,7 @@ impl Radar {
         // Radar Range Equation:
         // R_max = [ (P_t * G^2 * lambda^2 * sigma) / ((4*pi)^3 * P_min) ] ^ (1/4)
         
-        let numerator = p_t * g * g * lambda * lambda * DEFAULT_RCS;
+        let numerator = p_t * g * g * lambda * lambda * rcs_m2;
         let denominator = (4.0 * std::f64::consts::PI).powi(3) * p_min;
         
         if denominator == 0.0 {
@@ -110,86 +396,305 @@ impl Radar {
         (numerator / denominator).powf(0.25)
     }
 
-    /// Calculate if a target point is within Radio Line of Sight (LOS)
-    /// Uses 4/3 Earth Radius approximation AND Physics-based Range Check
-    pub fn is_visible(&self, target_lat: f64, target_lon: f64, target_alt: f32) -> bool {
+    /// Signal-to-noise ratio (linear) of a target of `rcs_m2` at `range_m` (slant range)
+    /// after processing, from the radar equation and the thermal noise k T0 B F.
+    /// Cooperative stations ignore the RCS: the SNR is that of the downlink, and zero when
+    /// any link falls below its receiver's sensitivity (no interrogation or no decode).
+    pub fn snr(&self, range_m: f64, rcs_m2: f64) -> f64 {
+        let noise = BOLTZMANN * REF_TEMP * self.bandwidth_hz * 10.0_f64.powf(self.noise_figure_db / 10.0);
+        let processing_gain = 10.0_f64.powf(self.processing_gain_db / 10.0);
+
+        if self.station_type != StationType::Primary {
+            let links = self.station_type.links(self);
+            if links.iter().any(|link| link.received_dbm(range_m) < link.sensitivity_dbm) {
+                return 0.0;
+            }
+            let Some(downlink) = links.iter().find(|link| link.to_ground) else {
+                return 0.0;
+            };
+            let p_r = 10.0_f64.powf((downlink.received_dbm(range_m) - 30.0) / 10.0); // Watts
+            return p_r / noise * processing_gain;
+        }
+
+        let p_t = 10.0_f64.powf((self.transmit_power_dbm - 30.0) / 10.0); // Watts
+        let g = 10.0_f64.powf(self.gain_dbi / 10.0);
+        let lambda = SPEED_OF_LIGHT / self.frequency;
+
+        // Received power: P_t * G^2 * lambda^2 * sigma / ((4*pi)^3 * R^4)
+        let p_r = p_t * g * g * lambda * lambda * rcs_m2
+            / ((4.0 * std::f64::consts::PI).powi(3) * range_m.powi(4));
+
+        p_r / noise * processing_gain
+    }
+
+    /// Signal-to-noise ratio (linear) of a target of `rcs_m2` at a position, with the
+    /// antenna gain in its direction instead of the boresight gain.
+    pub fn snr_at(&self, target_lat: f64, target_lon: f64, target_alt: f32, rcs_m2: f64) -> f64 {
+        let (ground_range, azimuth, elevation) = self.look_angles(target_lat, target_lon, target_alt);
+        let slant_range = ground_range.hypot(target_alt as f64 - self.position.z).max(1.0);
+        // The pattern applies to every pass through the antenna, which amounts to scaling the
+        // range by 10^(-gain/20) in both models (echo power ~ G^2/R^4, one-way power ~ G/R^2)
+        let scale = 10.0_f64.powf(-self.antenna.relative_gain_db(azimuth, elevation) / 20.0);
+        self.snr(slant_range * scale, rcs_m2)
+    }
+
+    /// Received power of the weakest link of a cooperative station above its receiver's
+    /// sensitivity (dB) at slant range `range_m`
+    fn link_margin_db(&self, range_m: f64) -> f64 {
+        self.station_type
+            .links(self)
+            .iter()
+            .map(|link| link.received_dbm(range_m) - link.sensitivity_dbm)
+            .fold(f64::INFINITY, f64::min)
+    }
+
+    /// Signal margin to a target including the antenna pattern and the terrain loss of the
+    /// station's propagation model, with the Fresnel clearance of the path
+    /// The margin is the SNR of the target class of `detection` over the SNR that detects it
+    /// with the lowest Pd contour. None when the antenna doesn't cover the target, or when
+    /// even free space leaves it more than `MARGIN_FLOOR_DB` below the threshold.
+    pub fn path_margin(
+        &self,
+        target_lat: f64,
+        target_lon: f64,
+        target_alt: f32,
+        detection: &DetectionSettings,
+        cache_snapshot: &std::collections::HashMap<crate::tile::TileCoord, std::sync::Arc<crate::tile::TileData>>,
+    ) -> Option<PathMargin> {
         if !self.enabled {
-            return false;
+            return None;
+        }
+        let (dist, azimuth, elevation) = self.look_angles(target_lat, target_lon, target_alt);
+        if !self.antenna.covers(azimuth, elevation) {
+            return None;
+        }
+
+        // The pattern and the terrain loss scale the range like in `snr_at`: by 10^(dB/20),
+        // on the echo power (G^2 L^2 / R^4) and on the one-way power (G L / R^2) alike
+        let gain_db = self.antenna.relative_gain_db(azimuth, elevation);
+        let slant_range = dist.hypot(target_alt as f64 - self.position.z).max(1.0);
+        let threshold = required_snr(detection, PD_CONTOURS[0] as f64);
+        let margin_db = |loss_db: f64| {
+            let range = slant_range * 10.0_f64.powf((loss_db - gain_db) / 20.0);
+            match self.snr(range, detection.target.rcs_m2()) {
+                snr if snr > 0.0 => 10.0 * (snr / threshold).log10(),
+                // A cooperative link below its receiver's sensitivity: by how much
+                _ => self.link_margin_db(range).min(0.0),
+            }
+        };
+        if margin_db(0.0) < MARGIN_FLOOR_DB {
+            return None;
+        }
+
+        let profile = TerrainProfile::extract(
+            self.position,
+            DVec3::new(target_lat, target_lon, target_alt as f64),
+            self.refraction.effective_radius(),
+            cache_snapshot,
+        );
+        let wavelength = SPEED_OF_LIGHT / self.frequency;
+        let (terrain_loss_db, loss_model) = match &self.propagation {
+            Propagation::KnifeEdge => (profile.diffraction_loss_db(wavelength), LossModel::KnifeEdge),
+            Propagation::Itm(settings) => {
+                // Antenna heights above the ground under each end (ITM refuses buried antennas)
+                let tx_height = (self.position.z - profile.start_terrain).max(1.0);
+                let rx_height = (target_alt as f64 - profile.end_terrain).max(1.0);
+                let result = itm::point_to_point(&profile, tx_height, rx_height, self.frequency / 1e6, settings);
+                // Out of range (aircraft above 3000 m): ITM's numbers are meaningless
+                if result.warnings >= itm::OUT_OF_RANGE {
+                    (profile.diffraction_loss_db(wavelength), LossModel::ItmOutOfRange)
+                } else {
+                    (result.excess_loss_db(), LossModel::Itm { warnings: result.warnings })
+                }
+            }
+        };
+
+        Some(PathMargin {
+            margin_db: margin_db(terrain_loss_db),
+            terrain_loss_db,
+            loss_model,
+            fresnel_clearance: profile.fresnel_clearance(wavelength),
+        })
+    }
+
+    /// Geodesic distance (meters), azimuth and elevation (degrees) of a target seen from the radar
+    pub fn look_angles(&self, target_lat: f64, target_lon: f64, target_alt: f32) -> (f64, f64, f64) {
+        let inverse = geodesy::inverse(self.position.x, self.position.y, target_lat, target_lon);
+        let azimuth = inverse.azimuth.to_degrees().rem_euclid(360.0);
+        (inverse.distance, azimuth, self.elevation_angle(inverse.distance, target_alt))
+    }
+
+    /// Elevation (degrees) of a target at geodesic distance `dist` and altitude `target_alt`
+    ///
+    /// ALGORITHM: Curvature-Corrected Elevation
+    /// Over the effective Earth, a target at distance d and height h_t appears at
+    ///     tan(el) = (h_t - h_r - d^2 / (2 R_eff)) / d
+    /// the same slope as the horizon profile uses, so that refraction lifts low targets
+    /// into the beam like it lifts them over ridges.
+    pub fn elevation_angle(&self, dist: f64, target_alt: f32) -> f64 {
+        let rise = target_alt as f64 - self.position.z - dist * dist / (2.0 * self.refraction.effective_radius());
+        if dist > 0.0 {
+            rise.atan2(dist).to_degrees()
+        } else if rise >= 0.0 {
+            90.0
+        } else {
+            -90.0
         }
+    }
 
-        // Check against Physics Calculated Max Range first
-        let max_physics_range = self.calculate_max_range();
+    /// Altitude at which a target at geodesic distance `dist` is seen at `elevation_deg`
+    /// (inverse of `elevation_angle`)
+    pub fn altitude_at_elevation(&self, dist: f64, elevation_deg: f64) -> f64 {
+        self.position.z + dist * elevation_deg.to_radians().tan() + dist * dist / (2.0 * self.refraction.effective_radius())
+    }
 
-        // Earth constants
-        // 4/3 Earth Radius Model
-        const R_EARTH: f64 = 6_371_000.0; // Mean Earth Radius in Meters
-        const R_EFF: f64 = R_EARTH * (4.0/3.0); // Effective radius
+    /// Whether the antenna covers a target and the echo is strong enough, terrain and
+    /// horizon aside
+    /// The range scales with the square root of the pattern gain: two-way for a primary
+    /// radar (R^4 ~ G^2), one-way for each link of a cooperative station (R^2 ~ G).
+    pub fn in_beam(&self, dist: f64, azimuth_deg: f64, target_alt: f32, rcs_m2: f64) -> bool {
+        let elevation = self.elevation_angle(dist, target_alt);
+        if !self.antenna.covers(azimuth_deg, elevation) {
+            return false;
+        }
+        let pattern_db = self.antenna.relative_gain_db(azimuth_deg, elevation);
+        dist <= self.calculate_max_range(rcs_m2) * 10.0_f64.powf(pattern_db / 20.0)
+    }
 
-        // Calculate Great Circle Distance
-        let d_lat = (target_lat - self.position.x).to_radians();
-        let d_lon = (target_lon - self.position.y).to_radians();
-        let lat1 = self.position.x.to_radians();
-        let lat2 = target_lat.to_radians();
+    /// Calculate if a target point is within Radio Line of Sight (LOS)
+    /// Uses the effective Earth radius of the radar's refraction model, the antenna's
+    /// coverage AND Physics-based Range Check
+    pub fn is_visible(&self, target_lat: f64, target_lon: f64, target_alt: f32, rcs_m2: f64) -> bool {
+        if !self.enabled {
+            return false;
+        }
 
-        let a = (d_lat / 2.0).sin().powi(2)
-            + lat1.cos() * lat2.cos() * (d_lon / 2.0).sin().powi(2);
-        let c = 2.0 * a.sqrt().asin();
-        let dist = R_EARTH * c; // Surface distance
+        // Geodesic distance on the WGS84 ellipsoid, and direction from the radar
+        let inverse = geodesy::inverse(self.position.x, self.position.y, target_lat, target_lon);
+        let dist = inverse.distance;
 
-        // 1. Physics Range Check
-        if dist > max_physics_range {
-            return false; 
+        // 1. Antenna and Physics Range Check: blanked sectors, below the beam, cone of
+        // silence, and the range equation with the pattern gain
+        if !self.in_beam(dist, inverse.azimuth.to_degrees().rem_euclid(360.0), target_alt, rcs_m2) {
+            return false;
         }
 
         // 2. Radio Horizon Check (Geometric)
+        self.within_radio_horizon(dist, target_alt)
+    }
+
+    /// Whether the antenna covers a target within the radio horizon, however weak its echo
+    /// (the detection view grades the range with the Pd instead)
+    pub fn in_view(&self, target_lat: f64, target_lon: f64, target_alt: f32) -> bool {
+        if !self.enabled {
+            return false;
+        }
+        let inverse = geodesy::inverse(self.position.x, self.position.y, target_lat, target_lon);
+        let elevation = self.elevation_angle(inverse.distance, target_alt);
+        self.antenna.covers(inverse.azimuth.to_degrees().rem_euclid(360.0), elevation)
+            && self.within_radio_horizon(inverse.distance, target_alt)
+    }
+
+    /// Whether a target at geodesic distance `dist` is within the radio horizon
+    fn within_radio_horizon(&self, dist: f64, target_alt: f32) -> bool {
         let h_radar = self.position.z.max(0.0);
         let h_target = target_alt.max(0.0) as f64;
 
-        let d_radar = (2.0 * h_radar * R_EFF).sqrt();
-        let d_target = (2.0 * h_target * R_EFF).sqrt();
+        let d_radar = self.refraction.radio_horizon(h_radar);
+        let d_target = self.refraction.radio_horizon(h_target);
 
         dist <= (d_radar + d_target)
     }
 
+    /// Tiles crossed by the rays from this radar to any point of `coord`
+    /// (excluding the tile itself). Coverage of `coord` depends on their terrain.
+    /// `rcs_m2` is the target's, which sets the range of a primary radar.
+    pub fn tiles_between(&self, coord: crate::tile::TileCoord, rcs_m2: f64) -> HashSet<crate::tile::TileCoord> {
+        use crate::tile::TileCoord;
+
+        // ALGORITHM: Sampled Ray Footprint
+        // The rays from a radar to the points of a tile sweep a wedge. We trace rays to a
+        // 5x5 grid of points across the tile, stepping along each geodesic like the raycast
+        // does, finely enough not to skip the corner of a tile.
+        const SAMPLES: usize = 5;
+        const STEP: f64 = 5_000.0;
+        // Half-diagonal of a tile (~1 degree) in meters, so radars that only reach a corner count
+        const TILE_HALF_DIAGONAL: f64 = 80_000.0;
+
+        let mut tiles = HashSet::new();
+        let center_lat = coord.lat as f64 + 0.5;
+        let center_lon = coord.lon as f64 + 0.5;
+
+        let start_lat = self.position.x;
+        let start_lon = self.position.y;
+        let dist = geodesy::distance(start_lat, start_lon, center_lat, center_lon);
+        if dist - TILE_HALF_DIAGONAL > self.calculate_max_range(rcs_m2) {
+            return tiles;
+        }
+
+        for sy in 0..SAMPLES {
+            for sx in 0..SAMPLES {
+                let target_lat = coord.lat as f64 + sy as f64 / (SAMPLES - 1) as f64;
+                let target_lon = coord.lon as f64 + sx as f64 / (SAMPLES - 1) as f64;
+                let (line, length) = GeodesicLine::between(start_lat, start_lon, target_lat, target_lon);
+                let num_steps = (length / STEP).ceil().max(1.0) as usize;
+
+                for i in 0..num_steps {
+                    let (lat, lon) = line.position(length * i as f64 / num_steps as f64);
+                    let tile = TileCoord::from_world_coords(lat, lon);
+                    if tile != coord {
+                        tiles.insert(tile);
+                    }
+                }
+            }
+        }
+
+        tiles
+    }
+
     /// Calculate visibility with terrain occlusion (Raycasting)
+    pub fn is_visible_raycast(&self, target_lat: f64, target_lon: f64, target_alt: f32, rcs_m2: f64, cache_snapshot: &std::collections::HashMap<crate::tile::TileCoord, std::sync::Arc<crate::tile::TileData>>) -> bool {
+        self.line_of_sight(target_lat, target_lon, target_alt, rcs_m2, cache_snapshot)
+            .is_some_and(|los| los.visible)
+    }
+
+    /// Line of sight to a target by raycasting, with the terrain sample where the ray
+    /// comes closest to the ground (the blocking ridge when occluded).
+    /// None when the target is beyond the physics range or the radio horizon.
     /// Optimized for performance: Cached TileData access to avoid hash lookups per step.
-    pub fn is_visible_raycast(&self, target_lat: f64, target_lon: f64, target_alt: f32, cache_snapshot: &std::collections::HashMap<crate::tile::TileCoord, std::sync::Arc<crate::tile::TileData>>) -> bool {
+    pub fn line_of_sight(&self, target_lat: f64, target_lon: f64, target_alt: f32, rcs_m2: f64, cache_snapshot: &std::collections::HashMap<crate::tile::TileCoord, std::sync::Arc<crate::tile::TileData>>) -> Option<LosResult> {
         if !self.enabled {
-            return false;
+            return None;
         }
 
         // 1. Fast Horizon Check
-        if !self.is_visible(target_lat, target_lon, target_alt) {
-            return false;
+        if !self.is_visible(target_lat, target_lon, target_alt, rcs_m2) {
+            return None;
         }
 
         // 2. Perform Raymarching
-        // Earth Constants
-        const R_EARTH: f64 = 6_371_000.0;
-        const R_EFF: f64 = R_EARTH * (4.0/3.0);
+        // Effective Earth radius (negative when ducting)
+        let r_eff = self.refraction.effective_radius();
         
         let start_lat = self.position.x;
         let start_lon = self.position.y;
         let start_alt = self.position.z; 
 
-        // Calculate total distance
-        let d_lat = (target_lat - start_lat).to_radians();
-        let d_lon = (target_lon - start_lon).to_radians();
-        
-        // Haversine calc
-        let lat1 = start_lat.to_radians();
-        let lat2 = target_lat.to_radians();
-        let a = (d_lat / 2.0).sin().powi(2)
-            + lat1.cos() * lat2.cos() * (d_lon / 2.0).sin().powi(2);
-        let c = 2.0 * a.sqrt().asin();
-        let total_dist = R_EARTH * c;
+        // Geodesic from the radar to the target on the WGS84 ellipsoid, and its length
+        let (path, total_dist) = GeodesicLine::between(start_lat, start_lon, target_lat, target_lon);
         
+        // Nothing in between: unobstructed
+        let mut result = LosResult {
+            visible: true,
+            clearance_m: f64::INFINITY,
+            critical: None,
+        };
         if total_dist < 100.0 {
-            return true;
+            return Some(result);
         }
         
         // Raymarch parameters
-        // We march along the Great Circle path from source to target.
+        // We march along the geodesic from source to target.
         // At each step, we check the height of the ray against the terrain height.
         let step_size = 500.0; // Meters. Smaller steps = higher precision but slower.
         let num_steps = (total_dist / step_size).ceil() as usize;
@@ -204,8 +709,7 @@ impl Radar {
         for i in 1..num_steps {
             let t = i as f64 / num_steps as f64;
             
-            let cur_lat = start_lat + (target_lat - start_lat) * t;
-            let cur_lon = start_lon + (target_lon - start_lon) * t;
+            let (cur_lat, cur_lon) = path.position(total_dist * t);
             
             // Height of Ray Calculation
             // We interpolate linearly between Source Altitude and Target Altitude.
@@ -214,7 +718,7 @@ impl Radar {
             // Drop Formula: h = d^2 / (2 * R_eff)
             let dist_from_start = total_dist * t;
             let linear_h = start_alt + (target_alt as f64 - start_alt) * t;
-            let earth_curvature_drop = (dist_from_start * (total_dist - dist_from_start)) / (2.0 * R_EFF);
+            let earth_curvature_drop = (dist_from_start * (total_dist - dist_from_start)) / (2.0 * r_eff);
             let ray_h = linear_h - earth_curvature_drop;
             
             if ray_h > 5000.0 {
@@ -249,13 +753,26 @@ impl Radar {
                 
                 let terrain_h = data.get_height_normalized(nx, ny);
                 
-                if (terrain_h as f64) > ray_h {
-                    return false; // Occluded
+                // Occluded wherever this goes negative
+                let clearance = ray_h - terrain_h as f64;
+                if clearance < result.clearance_m {
+                    // Elevation angle of the terrain sample, seen from the radar
+                    let drop = dist_from_start * dist_from_start / (2.0 * r_eff);
+                    let elevation = ((terrain_h as f64 - start_alt - drop) / dist_from_start).atan();
+                    result.clearance_m = clearance;
+                    result.critical = Some(TerrainSample {
+                        lat: cur_lat,
+                        lon: cur_lon,
+                        height: terrain_h,
+                        range_m: dist_from_start,
+                        elevation_deg: elevation.to_degrees(),
+                    });
                 }
             }
         }
         
-        true
+        result.visible = result.clearance_m >= 0.0;
+        Some(result)
     }
 }
 
@@ -265,35 +782,48 @@ pub fn setup_radar_marker(
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<StandardMaterial>>,
     radars: Res<Radars>,
+    settings: Res<crate::coverage::CoverageSettings>,
+    origin: Res<FloatingOrigin>,
+    mode: Res<WorldMode>,
 ) {
-    let tile_size = 3601.0;
-    let height_scale = 1.0; 
 
+    let target = settings.detection.target;
     for (index, radar) in radars.stations.iter().enumerate() {
-        if !radar.enabled {
-            continue;
-        }
-
-        let max_range_km = radar.calculate_max_range() / 1000.0;
-        info!("Radar '{}' Physics Range: {:.1} km (Power: {:.1} dBm, Gain: {:.1} dBi)", 
-              radar.name, max_range_km, radar.transmit_power_dbm, radar.gain_dbi);
+        let max_range_km = radar.calculate_max_range(target.rcs_m2()) / 1000.0;
+        info!("Radar '{}' ({}) Physics Range: {:.1} km ({}, Power: {:.1} dBm, Gain: {:.1} dBi)", 
+              radar.name, radar.station_type, max_range_km, target, radar.transmit_power_dbm, radar.gain_dbi);
 
-        let x = radar.position.y as f32 * tile_size;
-        let z = -(radar.position.x as f32) * tile_size; 
-        let y = radar.position.z as f32 * height_scale;
+        let world_pos = mode.geo_to_world(radar.position.x, radar.position.y, radar.position.z);
+        spawn_marker(&mut commands, &mut meshes, &mut materials, radar, world_pos, &origin, RadarMarker { index });
+    }
+}
 
-        commands.spawn((
-            Mesh3d(meshes.add(Sphere::new(100.0))), 
+/// Spawn the marker sphere of a station at `world_pos`, tagged with `marker`
+pub fn spawn_marker(
+    commands: &mut Commands,
+    meshes: &mut Assets<Mesh>,
+    materials: &mut Assets<StandardMaterial>,
+    radar: &Radar,
+    world_pos: DVec3,
+    origin: &FloatingOrigin,
+    marker: impl Bundle,
+) -> Entity {
+    commands
+        .spawn((
+            Mesh3d(meshes.add(Sphere::new(100.0))),
             MeshMaterial3d(materials.add(StandardMaterial {
-                base_color: radar.color, 
-                emissive: LinearRgba::from(radar.color) * 5.0, 
+                base_color: radar.color,
+                emissive: LinearRgba::from(radar.color) * 5.0,
                 unlit: true,
                 ..default()
             })),
-            Transform::from_xyz(x, y + 100.0, z), 
-            RadarMarker { index },
-        ));
-    }
+            Transform::from_translation(origin.to_local(world_pos)),
+            // Disabled stations keep a hidden marker so they can be toggled back on
+            if radar.enabled { Visibility::Inherited } else { Visibility::Hidden },
+            WorldPosition(world_pos),
+            marker,
+        ))
+        .id()
 }
 
 #[derive(Component)]
@@ -301,48 +831,373 @@ pub struct RadarMarker {
     pub index: usize,
 }
 
-/// System to continuously snap the radar marker to the ground surface
-pub fn update_radar_position_system(
-    radars: Res<Radars>,
-    cache: Res<crate::cache::TileCache>,
-    mut query: Query<(&mut Transform, &RadarMarker)>,
+/// Keys toggling radar stations on and off (station N = digit N)
+const RADAR_TOGGLE_KEYS: [KeyCode; 9] = [
+    KeyCode::Digit1, KeyCode::Digit2, KeyCode::Digit3,
+    KeyCode::Digit4, KeyCode::Digit5, KeyCode::Digit6,
+    KeyCode::Digit7, KeyCode::Digit8, KeyCode::Digit9,
+];
+
+/// System to enable/disable radar stations from the keyboard
+/// Only mutates `Radars` on a key press so change detection stays meaningful.
+pub fn toggle_radar_system(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut radars: ResMut<Radars>,
+    mut markers: Query<(&mut Visibility, &RadarMarker)>,
 ) {
-    for (mut transform, marker) in query.iter_mut() {
-        if marker.index >= radars.stations.len() {
+    for (index, key) in RADAR_TOGGLE_KEYS.iter().enumerate() {
+        if !keys.just_pressed(*key) || index >= radars.stations.len() {
             continue;
         }
-        let radar = &radars.stations[marker.index];
-        if !radar.enabled { continue; }
+        let radar = &mut radars.stations[index];
+        radar.enabled = !radar.enabled;
+        info!("Radar '{}' {}", radar.name, if radar.enabled { "enabled" } else { "disabled" });
 
-        let lat = radar.position.x;
-        let lon = radar.position.y;
-        
-        // Check if we have data for this location
-        let coord = crate::tile::TileCoord::from_world_coords(lat, lon);
-        
-        if let Some(crate::tile::TileState::Loaded(data)) = cache.tiles.get(&coord) {
-             // Sample height
-             let lat_base = coord.lat as f64;
-             let lon_base = coord.lon as f64;
-             
-             let d_lat = lat - lat_base;
-             let d_lon = lon - lon_base;
-             
-             // Y = (1.0 - d_lat) * 3600.0
-             let y_pct = 1.0 - d_lat;
-             let x_pct = d_lon;
-             
-             let pixel_x = (x_pct * 3600.0) as f32;
-             let pixel_y = (y_pct * 3600.0) as f32;
-             
-             if let Some(h) = data.get_height(pixel_x as usize, pixel_y as usize) {
-                 let terrain_height = h as f32; // Scale 1.0
-                 
-                 // Only update if significantly different
-                 if (transform.translation.y - terrain_height).abs() > 10.0 {
-                      transform.translation.y = terrain_height + 50.0; // Place on top
-                 }
-             }
+        for (mut visibility, marker) in markers.iter_mut() {
+            if marker.index == index {
+                *visibility = if radar.enabled { Visibility::Inherited } else { Visibility::Hidden };
+            }
+        }
+    }
+}
+
+/// System to cycle the refraction model of all stations (K key)
+/// The cycle ends on each station's own model. Shift+K changes the comparison model instead
+/// (see `coverage_input_system`).
+pub fn cycle_refraction_system(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut radars: ResMut<Radars>,
+    mut cycle: Local<PresetCycle<Refraction>>,
+) {
+    let shift_pressed = keys.pressed(KeyCode::ShiftLeft) || keys.pressed(KeyCode::ShiftRight);
+    if !keys.just_pressed(KeyCode::KeyK) || shift_pressed {
+        return;
+    }
+
+    match cycle.step(&mut radars, |radar: &mut Radar| &mut radar.refraction, |refraction| refraction.next_preset()) {
+        Some(refraction) => info!("Refraction: {}", refraction),
+        None => info!("Refraction: each station's own model"),
+    }
+}
+
+/// System to switch every station between an isotropic antenna and the en-route preset (B key)
+/// The cycle ends on each station's own antenna.
+pub fn toggle_antenna_system(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut radars: ResMut<Radars>,
+    mut cycle: Local<PresetCycle<Antenna>>,
+) {
+    if !keys.just_pressed(KeyCode::KeyB) {
+        return;
+    }
+
+    let next = |antenna: &Antenna| {
+        if *antenna == Antenna::isotropic() { Antenna::en_route() } else { Antenna::isotropic() }
+    };
+    match cycle.step(&mut radars, |radar: &mut Radar| &mut radar.antenna, next) {
+        Some(antenna) if antenna == Antenna::isotropic() => info!("Antenna: isotropic"),
+        Some(_) => info!("Antenna: en-route (cosecant-squared, -5 to 40 deg)"),
+        None => info!("Antenna: each station's own"),
+    }
+}
+
+/// System to cycle the terrain loss model of every station (P key; Shift+P: the ITM climate)
+/// The cycle ends on each station's own model.
+pub fn toggle_propagation_system(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut radars: ResMut<Radars>,
+    mut cycle: Local<PresetCycle<Propagation>>,
+) {
+    if !keys.just_pressed(KeyCode::KeyP) {
+        return;
+    }
+
+    let field: fn(&mut Radar) -> &mut Propagation = |radar| &mut radar.propagation;
+    let shift_pressed = keys.pressed(KeyCode::ShiftLeft) || keys.pressed(KeyCode::ShiftRight);
+    let propagation = if shift_pressed {
+        let current = cycle
+            .current()
+            .copied()
+            .or_else(|| radars.stations.first().map(|radar| radar.propagation))
+            .unwrap_or_default();
+        let propagation = match current {
+            Propagation::Itm(settings) => Propagation::Itm(itm::ItmSettings { climate: settings.climate.next(), ..settings }),
+            Propagation::KnifeEdge => Propagation::Itm(itm::ItmSettings::default()),
+        };
+        cycle.apply(&mut radars, field, propagation);
+        Some(propagation)
+    } else {
+        cycle.step(&mut radars, field, |propagation| propagation.next())
+    };
+    match propagation {
+        Some(propagation) => info!("Propagation: {}", propagation),
+        None => info!("Propagation: each station's own model"),
+    }
+}
+
+/// System to look up the ground elevation of each site once its tile is loaded
+/// Stations with a mast height move to ground + mast; a configured AMSL altitude below the
+/// terrain is reported.
+pub fn update_site_elevation_system(mut radars: ResMut<Radars>, cache: Res<crate::cache::TileCache>) {
+    // Only touch `Radars` (and trigger recomputations) when a site is resolved
+    let resolved: Vec<(usize, f64)> = radars
+        .stations
+        .iter()
+        .enumerate()
+        .filter(|(_, radar)| radar.site_elevation.is_none())
+        .filter_map(|(index, radar)| {
+            let (lat, lon) = (radar.position.x, radar.position.y);
+            let coord = crate::tile::TileCoord::from_world_coords(lat, lon);
+            match cache.tiles.get(&coord) {
+                Some(crate::tile::TileState::Loaded(data)) => {
+                    let nx = (lon - coord.lon as f64) as f32;
+                    let ny = (1.0 - (lat - coord.lat as f64)) as f32; // Inverted Y for SRTM
+                    Some((index, data.get_height_normalized(nx, ny) as f64))
+                }
+                _ => None,
+            }
+        })
+        .collect();
+
+    // Recording the ground alone changes nothing downstream; moving an antenna does
+    let mut moved = false;
+    for (index, ground) in resolved {
+        let radar = &mut radars.bypass_change_detection().stations[index];
+        let before = radar.position.z;
+        radar.set_site_elevation(ground);
+        moved |= radar.position.z != before;
+        if radar.is_below_terrain() {
+            warn!(
+                "Radar '{}': configured altitude {:.0} m AMSL is {:.0} m below the terrain of its site ({:.0} m); set an antenna height above ground instead",
+                radar.name,
+                radar.position.z,
+                ground - radar.position.z,
+                ground
+            );
+        } else {
+            info!(
+                "Radar '{}': site ground {:.0} m, antenna at {:.0} m AMSL ({:.0} m above ground)",
+                radar.name,
+                ground,
+                radar.position.z,
+                radar.position.z - ground
+            );
         }
     }
+    if moved {
+        radars.set_changed();
+    }
+}
+
+/// System to keep the radar markers on the effective antenna position
+pub fn update_radar_position_system(
+    radars: Res<Radars>,
+    mode: Res<WorldMode>,
+    mut query: Query<(&mut WorldPosition, &RadarMarker)>,
+) {
+    if !radars.is_changed() && !mode.is_changed() {
+        return;
+    }
+
+    for (mut world_pos, marker) in query.iter_mut() {
+        if let Some(radar) = radars.stations.get(marker.index) {
+            world_pos.0 = mode.geo_to_world(radar.position.x, radar.position.y, radar.position.z);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{radar_at, ridge_snapshot, TARGET_RCS};
+
+    #[test]
+    fn test_line_of_sight_reports_ridge() {
+        let snapshot = ridge_snapshot();
+        let mut radars = Radars::default();
+        radars.stations[0].position = DVec3::new(43.5, 7.3, 400.0);
+        let radar = &radars.stations[0];
+
+        // Behind the ridge: hidden by it, deeper when lower
+        let low = radar.line_of_sight(43.5, 7.9, 100.0, TARGET_RCS, &snapshot).unwrap();
+        let high = radar.line_of_sight(43.5, 7.9, 600.0, TARGET_RCS, &snapshot).unwrap();
+        assert!(!low.visible && !high.visible);
+        assert!(low.clearance_m < high.clearance_m && high.clearance_m < 0.0);
+
+        let ridge = low.critical.unwrap();
+        assert_eq!(ridge.height, 800.0);
+        assert!((7.63..7.66).contains(&ridge.lon), "{}", ridge.lon);
+        let ridge_range = geodesy::distance(43.5, 7.3, ridge.lat, ridge.lon);
+        assert!((ridge.range_m - ridge_range).abs() < 1.0);
+        assert!(ridge.elevation_deg > 0.0);
+
+        // In front of the ridge: visible, with a positive margin
+        let front = radar.line_of_sight(43.5, 7.5, 100.0, TARGET_RCS, &snapshot).unwrap();
+        assert!(front.visible && front.clearance_m > 0.0);
+
+        // Aggregated per radar: Sainte-Baume grazes the flat terrain and misses by a few
+        // meters, Lyon is beyond the radio horizon
+        let report = radars.check_visibility(43.5, 7.5, 100.0, TARGET_RCS, &snapshot);
+        assert_eq!(report.per_radar.len(), 3);
+        let grazing = report.per_radar[1].1.unwrap();
+        assert!(!grazing.visible && grazing.clearance_m > -10.0);
+        assert!(report.per_radar[2].1.is_none());
+        assert_eq!(report.visible_count(), 1);
+        assert_eq!(report.best().unwrap().0, 0);
+    }
+
+    #[test]
+    fn test_path_margin_is_graded() {
+        let snapshot = ridge_snapshot();
+        let mut radars = Radars::default();
+        radars.stations[0].position = DVec3::new(43.5, 7.3, 400.0);
+        let radar = &radars.stations[0];
+        let detection = DetectionSettings::default();
+
+        // In front of the ridge, well clear of the ground: free space
+        let front = radar.path_margin(43.5, 7.5, 400.0, &detection, &snapshot).unwrap();
+        assert_eq!(front.terrain_loss_db, 0.0);
+        assert!(front.fresnel_clearance > 1.0 && front.margin_db > 0.0);
+
+        // Behind the ridge the loss shrinks steadily as the target climbs, instead of
+        // switching from blocked to clear
+        let losses: Vec<f64> = [300.0, 600.0, 900.0, 1_200.0]
+            .iter()
+            .map(|alt| radar.path_margin(43.5, 7.9, *alt, &detection, &snapshot).unwrap().terrain_loss_db)
+            .collect();
+        assert!(losses.windows(2).all(|pair| pair[0] > pair[1]), "{:?}", losses);
+        assert!(losses[0] > 10.0 && losses[3] < 6.0, "{:?}", losses);
+
+        // Longley-Rice agrees on the shadow of the ridge
+        let mut itm_radar = radar.clone();
+        itm_radar.propagation = Propagation::Itm(itm::ItmSettings::default());
+        let shadowed = itm_radar.path_margin(43.5, 7.9, 300.0, &detection, &snapshot).unwrap();
+        let clear = itm_radar.path_margin(43.5, 7.9, 1_200.0, &detection, &snapshot).unwrap();
+        assert!(shadowed.terrain_loss_db > clear.terrain_loss_db + 10.0, "{:?} vs {:?}", shadowed, clear);
+        assert!(shadowed.margin_db < clear.margin_db);
+        // (1200 m is above the validated 1000 m: questionable, but used)
+        assert!(matches!(clear.loss_model, LossModel::Itm { warnings: 1 }), "{:?}", clear);
+
+        // An aircraft 3000 m above the ground is out of ITM's range: knife edge, flagged
+        let airborne = itm_radar.path_margin(43.5, 7.9, 4_000.0, &detection, &snapshot).unwrap();
+        let knife_edge = radar.path_margin(43.5, 7.9, 4_000.0, &detection, &snapshot).unwrap();
+        assert_eq!(airborne.loss_model, LossModel::ItmOutOfRange);
+        assert_eq!(airborne.terrain_loss_db, knife_edge.terrain_loss_db);
+    }
+
+    #[test]
+    fn test_path_margin_by_target() {
+        let snapshot = ridge_snapshot();
+        let radar = radar_at(43.5, 7.3, 400.0);
+        let light = DetectionSettings::default();
+        let airliner = DetectionSettings { target: crate::detection::TargetClass::Airliner, ..light };
+
+        // Free space: the margin follows the TARGET_RCS
+        let front = |detection: &DetectionSettings| radar.path_margin(43.5, 7.5, 400.0, detection, &snapshot).unwrap();
+        let gain = front(&airliner).margin_db - front(&light).margin_db;
+        assert!((gain - 10.0 * (40.0_f64 / 2.0).log10()).abs() < 1e-6, "{}", gain);
+
+        // Beyond the light aircraft's range the airliner still has a margin, up to its Pd 0.5 contour
+        let light_rcs = light.target.rcs_m2();
+        let mut weak = radar_at(43.5, 7.02, 400.0);
+        weak.transmit_power_dbm -= 40.0 * (weak.calculate_max_range(light_rcs) / 25_000.0).log10();
+        let reach = crate::detection::range_at_pd(&weak, &airliner, 0.5);
+        let lon = |distance: f64| 7.02 + distance / (111_320.0 * 43.5_f64.to_radians().cos());
+        let inside = weak.path_margin(43.5, lon(0.9 * reach), 1_000.0, &airliner, &snapshot).unwrap();
+        assert!(0.9 * reach > weak.calculate_max_range(light_rcs) && inside.margin_db > 0.0, "{:?}", inside);
+        let outside = weak.path_margin(43.5, lon(1.1 * reach), 1_000.0, &airliner, &snapshot).unwrap();
+        assert!(outside.margin_db < 0.0, "{:?}", outside);
+
+        // and a drone that far is past the floor
+        let drone = DetectionSettings { target: crate::detection::TargetClass::Drone, ..light };
+        assert!(weak.path_margin(43.5, lon(0.9 * reach), 1_000.0, &drone, &snapshot).is_none());
+    }
+
+    #[test]
+    fn test_site_elevation() {
+        let radars = Radars::default();
+
+        // On a mast: the antenna follows the ground of the site, and so do its look angles
+        let mut mast = radars.stations[0].clone();
+        mast.antenna_height_agl = Some(30.0);
+        let before = mast.elevation_angle(20_000.0, 3_000.0);
+        mast.set_site_elevation(500.0);
+        assert_eq!(mast.position.z, 530.0);
+        assert_eq!(mast.height_above_ground(), Some(30.0));
+        assert!(!mast.is_below_terrain());
+        assert!(mast.elevation_angle(20_000.0, 3_000.0) > before);
+
+        // Configured AMSL: kept, and flagged when the DEM puts the site higher
+        let mut amsl = radars.stations[0].clone();
+        amsl.position.z = 400.0;
+        amsl.set_site_elevation(300.0);
+        assert_eq!((amsl.position.z, amsl.height_above_ground()), (400.0, Some(100.0)));
+        assert!(!amsl.is_below_terrain());
+        amsl.set_site_elevation(450.0);
+        assert_eq!(amsl.position.z, 400.0);
+        assert!(amsl.is_below_terrain());
+    }
+
+    #[test]
+    fn test_antenna_limits_visibility() {
+        let mut radar = radar_at(43.5, 7.5, 500.0);
+        radar.antenna = Antenna::en_route();
+        radar.antenna.blanked_sectors.push((80.0, 100.0));
+
+        // Straight above the radar: in the cone of silence
+        assert!(!radar.is_visible(43.51, 7.5, 8_000.0, TARGET_RCS));
+        assert!(radar.is_visible(43.51, 7.5, 1_000.0, TARGET_RCS));
+        // Steeply below the beam
+        assert!(!radar.is_visible(43.51, 7.5, 0.0, TARGET_RCS));
+        // East is blanked
+        assert!(!radar.is_visible(43.5, 7.6, 1_000.0, TARGET_RCS));
+        assert!(radar.is_visible(43.5, 7.4, 1_000.0, TARGET_RCS));
+
+        // Elevation and its inverse agree
+        let alt = radar.altitude_at_elevation(20_000.0, 12.0);
+        assert!((radar.elevation_angle(20_000.0, alt as f32) - 12.0).abs() < 1e-3);
+
+        // The echo is weaker off the peak of the beam (two-way pattern)
+        let (dist, azimuth, elevation) = radar.look_angles(43.7, 7.4, 10_000.0);
+        let boresight = radar.snr(dist.hypot(10_000.0 - 500.0), 2.0);
+        let gain_db = 10.0 * (radar.snr_at(43.7, 7.4, 10_000.0, 2.0) / boresight).log10();
+        let expected = 2.0 * radar.antenna.relative_gain_db(azimuth, elevation);
+        assert!(expected < -10.0 && (gain_db - expected).abs() < 1e-6, "{} vs {}", gain_db, expected);
+    }
+
+    #[test]
+    fn test_preset_cycle_restores_stations() {
+        let mut radars = Radars::default();
+        radars.stations[1].antenna = Antenna::en_route();
+        let own: Vec<Antenna> = radars.stations.iter().map(|radar| radar.antenna.clone()).collect();
+        let field: fn(&mut Radar) -> &mut Antenna = |radar| &mut radar.antenna;
+        let next = |antenna: &Antenna| {
+            if *antenna == Antenna::isotropic() { Antenna::en_route() } else { Antenna::isotropic() }
+        };
+
+        // The first station is isotropic: en-route, isotropic, then each station's own
+        let mut cycle = PresetCycle::default();
+        assert_eq!(cycle.step(&mut radars, field, next), Some(Antenna::en_route()));
+        assert!(radars.stations.iter().all(|radar| radar.antenna == Antenna::en_route()));
+        assert_eq!(cycle.step(&mut radars, field, next), Some(Antenna::isotropic()));
+        assert!(radars.stations.iter().all(|radar| radar.antenna == Antenna::isotropic()));
+        assert_eq!(cycle.current(), Some(&Antenna::isotropic()));
+        assert_eq!(cycle.step(&mut radars, field, next), None);
+        let restored: Vec<Antenna> = radars.stations.iter().map(|radar| radar.antenna.clone()).collect();
+        assert_eq!(restored, own);
+        assert_eq!(cycle.current(), None);
+
+        // A station committed during the cycle gets its own antenna back too
+        cycle.step(&mut radars, field, next);
+        let mut candidate = radars.stations[1].clone();
+        candidate.name = "Candidate 1".to_string();
+        candidate.antenna = Antenna::en_route();
+        radars.stations.push(candidate);
+        cycle.step(&mut radars, field, next);
+        assert_eq!(radars.stations.last().unwrap().antenna, Antenna::isotropic());
+        cycle.restore(&mut radars, field);
+        assert_eq!(radars.stations.last().unwrap().antenna, Antenna::en_route());
+        assert_eq!(radars.stations[1].antenna, Antenna::en_route());
+        assert_eq!(radars.stations[0].antenna, Antenna::isotropic());
+    }
 }
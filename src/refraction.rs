@@ -0,0 +1,172 @@
+// Atmospheric refraction models for radar line of sight
+use crate::radar::{EARTH_RADIUS, EFFECTIVE_EARTH_FACTOR};
+
+/// Refractivity gradient (N-units/km) at which rays curve exactly like the Earth
+/// (k = infinity); anything steeper traps them in a duct.
+const DUCTING_GRADIENT: f64 = -1e9 / EARTH_RADIUS;
+
+/// Refractivity gradient (N-units/km) beyond which the atmosphere is super-refractive (k = 2)
+const SUPER_REFRACTIVE_GRADIENT: f64 = -0.5e9 / EARTH_RADIUS;
+
+/// Atmospheric refraction as an effective Earth radius factor k
+///
+/// ALGORITHM: Effective Earth Radius
+/// A refractivity gradient dN/dh bends rays with a curvature of -dN/dh * 1e-6 per meter.
+/// Subtracting it from the Earth's curvature lets rays be drawn as straight lines over
+/// an Earth of radius k R, with
+///     k = 1 / (1 + R dN/dh 1e-6)    (dN/dh in N-units per meter)
+/// The standard atmosphere (about -39 N/km) gives the classic k = 4/3. When the gradient
+/// reaches -157 N/km the rays follow the Earth (k = infinity); beyond that, k is negative
+/// and the effective Earth curves the other way (ducting).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Refraction {
+    /// Effective Earth radius factor (infinite or negative when ducting)
+    pub k: f64,
+}
+
+/// Classes of refractivity gradient (ITU-R P.453)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RefractionCondition {
+    /// dN/dh > 0: rays bend upwards, the radio horizon shrinks
+    SubRefractive,
+    /// 0 >= dN/dh > -79 N/km
+    Normal,
+    /// -79 >= dN/dh > -157 N/km: rays bend down more than usual
+    SuperRefractive,
+    /// dN/dh <= -157 N/km: rays are trapped near the surface
+    Ducting,
+}
+
+impl Refraction {
+    /// Standard atmosphere (4/3 Earth)
+    pub const STANDARD: Self = Self { k: EFFECTIVE_EARTH_FACTOR };
+    /// Sub-refractive (k = 2/3, about +78 N/km)
+    pub const SUB_REFRACTIVE: Self = Self { k: 2.0 / 3.0 };
+    /// Super-refractive (k = 4, about -118 N/km)
+    pub const SUPER_REFRACTIVE: Self = Self { k: 4.0 };
+    /// Presets offered by the UI, in cycling order
+    pub const PRESETS: [Self; 4] = [
+        Self::STANDARD,
+        Self::SUB_REFRACTIVE,
+        Self::SUPER_REFRACTIVE,
+        // Ducting
+        Self::from_gradient(-200.0),
+    ];
+
+    /// Model for a vertical refractivity gradient in N-units/km
+    pub const fn from_gradient(dn_dh: f64) -> Self {
+        // 1 / 0 gives the infinite k of the ducting threshold
+        Self { k: 1.0 / (1.0 + EARTH_RADIUS * dn_dh * 1e-9) }
+    }
+
+    /// Vertical refractivity gradient in N-units/km
+    pub fn gradient(self) -> f64 {
+        (1.0 / self.k - 1.0) * 1e9 / EARTH_RADIUS
+    }
+
+    /// Class of the refractivity gradient
+    pub fn condition(self) -> RefractionCondition {
+        let gradient = self.gradient();
+        if gradient > 0.0 {
+            RefractionCondition::SubRefractive
+        } else if gradient > SUPER_REFRACTIVE_GRADIENT {
+            RefractionCondition::Normal
+        } else if gradient > DUCTING_GRADIENT {
+            RefractionCondition::SuperRefractive
+        } else {
+            RefractionCondition::Ducting
+        }
+    }
+
+    /// Effective Earth radius (meters, infinite or negative when ducting)
+    pub fn effective_radius(self) -> f64 {
+        EARTH_RADIUS * self.k
+    }
+
+    /// Distance to the radio horizon from a height above the surface (meters)
+    /// Infinite when ducting: the effective Earth doesn't curve away from the ray.
+    pub fn radio_horizon(self, height: f64) -> f64 {
+        let r_eff = self.effective_radius();
+        if r_eff.is_finite() && r_eff > 0.0 {
+            (2.0 * height * r_eff).sqrt()
+        } else {
+            f64::INFINITY
+        }
+    }
+
+    /// Next preset (cycled from the keyboard)
+    pub fn next_preset(self) -> Self {
+        let index = Self::PRESETS.iter().position(|preset| *preset == self);
+        Self::PRESETS[index.map_or(0, |i| (i + 1) % Self::PRESETS.len())]
+    }
+}
+
+impl Default for Refraction {
+    fn default() -> Self {
+        Self::STANDARD
+    }
+}
+
+impl std::fmt::Display for Refraction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let condition = match self.condition() {
+            RefractionCondition::SubRefractive => "sub-refractive",
+            RefractionCondition::Normal if *self == Self::STANDARD => "standard",
+            RefractionCondition::Normal => "normal",
+            RefractionCondition::SuperRefractive => "super-refractive",
+            RefractionCondition::Ducting => "ducting",
+        };
+        if self.k.is_finite() && self.k > 0.0 {
+            write!(f, "k={:.2} ({})", self.k, condition)
+        } else {
+            write!(f, "dN/dh={:.0} N/km ({})", self.gradient(), condition)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gradient_conversion() {
+        // The standard atmosphere is about -39 N/km
+        assert!((Refraction::STANDARD.gradient() + 39.2).abs() < 0.1);
+        let k = Refraction::from_gradient(Refraction::STANDARD.gradient()).k;
+        assert!((k - 4.0 / 3.0).abs() < 1e-12);
+
+        assert!(Refraction::from_gradient(DUCTING_GRADIENT).k.abs() > 1e6);
+        assert!(Refraction::from_gradient(-200.0).k < 0.0);
+    }
+
+    #[test]
+    fn test_conditions() {
+        use RefractionCondition::*;
+
+        let classify = |dn_dh: f64| Refraction::from_gradient(dn_dh).condition();
+        assert_eq!(classify(40.0), SubRefractive);
+        assert_eq!(classify(-39.0), Normal);
+        assert_eq!(classify(-100.0), SuperRefractive);
+        assert_eq!(classify(-156.0), SuperRefractive);
+        assert_eq!(classify(-158.0), Ducting);
+        assert_eq!(classify(-300.0), Ducting);
+
+        let presets: Vec<_> = Refraction::PRESETS.iter().map(|r| r.condition()).collect();
+        assert_eq!(presets, [Normal, SubRefractive, SuperRefractive, Ducting]);
+    }
+
+    #[test]
+    fn test_radio_horizon() {
+        // The standard model reproduces the former hard-coded 4/3 Earth bit for bit
+        let r_eff = EARTH_RADIUS * EFFECTIVE_EARTH_FACTOR;
+        assert_eq!(Refraction::STANDARD.effective_radius(), r_eff);
+        assert_eq!(Refraction::STANDARD.radio_horizon(100.0), (2.0 * 100.0 * r_eff).sqrt());
+
+        // 4/3 Earth: about 4.12 sqrt(h) km
+        let horizon = Refraction::STANDARD.radio_horizon(100.0);
+        assert!((horizon - 41_200.0).abs() < 100.0);
+        assert!(Refraction::SUB_REFRACTIVE.radio_horizon(100.0) < horizon);
+        assert!(Refraction::SUPER_REFRACTIVE.radio_horizon(100.0) > horizon);
+        assert_eq!(Refraction::PRESETS[3].radio_horizon(100.0), f64::INFINITY);
+    }
+}
@@ -0,0 +1,719 @@
+// Scenario files: radar stations loaded from and saved to CSV
+use crate::antenna::Antenna;
+use crate::itm::{ItmSettings, Polarization, Propagation, RadioClimate};
+use crate::radar::{Radar, Radars};
+use crate::refraction::Refraction;
+use crate::station::StationType;
+use bevy::math::DVec3;
+use bevy::prelude::*;
+use serde::de::{self, value::MapDeserializer, IntoDeserializer, Visitor};
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+
+/// Command line option selecting the scenario file
+const RADARS_OPTION: &str = "--radars";
+
+/// Where F5 saves the stations when no scenario file was given
+const DEFAULT_SCENARIO: &str = "scenarios/radars.csv";
+
+/// Error reading a scenario file
+#[derive(Debug, thiserror::Error)]
+pub enum ScenarioError {
+    #[error("{path}: {source}")]
+    Io { path: PathBuf, source: std::io::Error },
+    /// A row that can't be split into the header's columns
+    #[error("line {line}: {message}")]
+    Format { line: usize, message: String },
+    /// A bad value, or a missing or unknown column
+    #[error("line {line}, field '{field}': {message}")]
+    Invalid { line: usize, field: String, message: String },
+}
+
+/// Scenario file the stations were loaded from, and are saved to (F5)
+#[derive(Resource, Debug, Clone)]
+pub struct ScenarioPath(pub PathBuf);
+
+impl Default for ScenarioPath {
+    fn default() -> Self {
+        Self(PathBuf::from(DEFAULT_SCENARIO))
+    }
+}
+
+/// One row of a scenario file
+///
+/// Columns are matched by name, in any order. Cells that only apply to some stations are
+/// optional (empty): the transponder and aircraft parameters of cooperative station types,
+/// and the ITM settings. Tables are written inline: antenna patterns as
+/// `angle:dB;angle:dB;...` and blanked sectors as `from:to;...`.
+#[derive(Debug, Clone, PartialEq, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct StationRecord {
+    name: String,
+    enabled: bool,
+    /// sRGB hex, e.g. `#00FFFF`
+    color: String,
+    latitude: f64,
+    longitude: f64,
+    /// Antenna altitude AMSL; with a mast height, only used until the site's ground is known
+    altitude_m: f64,
+    /// Antenna height above the site ground (empty: `altitude_m` is the antenna altitude)
+    antenna_height_agl_m: Option<f64>,
+    /// `primary`, `ssr`, `adsb` or `vhf`
+    station_type: String,
+    transponder_power_dbm: Option<f64>,
+    transponder_mtl_dbm: Option<f64>,
+    aircraft_power_dbm: Option<f64>,
+    aircraft_sensitivity_dbm: Option<f64>,
+    frequency_hz: f64,
+    transmit_power_dbm: f64,
+    gain_dbi: f64,
+    sensitivity_dbm: f64,
+    noise_figure_db: f64,
+    bandwidth_hz: f64,
+    processing_gain_db: f64,
+    azimuth_pattern: String,
+    elevation_pattern: String,
+    min_elevation_deg: f64,
+    max_elevation_deg: f64,
+    blanked_sectors: String,
+    beamwidth_deg: f64,
+    tilt_deg: f64,
+    refraction_k: f64,
+    /// `knife_edge` or `itm`
+    propagation: String,
+    itm_climate: Option<String>,
+    itm_polarization: Option<String>,
+    itm_permittivity: Option<f64>,
+    itm_conductivity: Option<f64>,
+    itm_surface_refractivity: Option<f64>,
+    itm_confidence: Option<f64>,
+    itm_reliability: Option<f64>,
+}
+
+impl StationRecord {
+    /// Row describing `radar`
+    fn from_radar(radar: &Radar) -> Self {
+        let (mut transponder_power_dbm, mut transponder_mtl_dbm) = (None, None);
+        let (mut aircraft_power_dbm, mut aircraft_sensitivity_dbm) = (None, None);
+        let station_type = match radar.station_type {
+            StationType::Primary => "primary",
+            StationType::Ssr { transponder_power_dbm: power, transponder_mtl_dbm: mtl } => {
+                transponder_power_dbm = Some(power);
+                transponder_mtl_dbm = Some(mtl);
+                "ssr"
+            }
+            StationType::AdsB { transponder_power_dbm: power } => {
+                transponder_power_dbm = Some(power);
+                "adsb"
+            }
+            StationType::VhfRadio { aircraft_power_dbm: power, aircraft_sensitivity_dbm: sensitivity } => {
+                aircraft_power_dbm = Some(power);
+                aircraft_sensitivity_dbm = Some(sensitivity);
+                "vhf"
+            }
+        };
+        let itm = match radar.propagation {
+            Propagation::KnifeEdge => None,
+            Propagation::Itm(settings) => Some(settings),
+        };
+
+        Self {
+            name: radar.name.clone(),
+            enabled: radar.enabled,
+            color: radar.color.to_srgba().to_hex(),
+            latitude: radar.position.x,
+            longitude: radar.position.y,
+            altitude_m: radar.position.z,
+            antenna_height_agl_m: radar.antenna_height_agl,
+            station_type: station_type.to_string(),
+            transponder_power_dbm,
+            transponder_mtl_dbm,
+            aircraft_power_dbm,
+            aircraft_sensitivity_dbm,
+            frequency_hz: radar.frequency,
+            transmit_power_dbm: radar.transmit_power_dbm,
+            gain_dbi: radar.gain_dbi,
+            sensitivity_dbm: radar.sensitivity_dbm,
+            noise_figure_db: radar.noise_figure_db,
+            bandwidth_hz: radar.bandwidth_hz,
+            processing_gain_db: radar.processing_gain_db,
+            azimuth_pattern: format_pairs(&radar.antenna.azimuth_pattern),
+            elevation_pattern: format_pairs(&radar.antenna.elevation_pattern),
+            min_elevation_deg: radar.antenna.min_elevation_deg,
+            max_elevation_deg: radar.antenna.max_elevation_deg,
+            blanked_sectors: format_pairs(&radar.antenna.blanked_sectors),
+            beamwidth_deg: radar.antenna.beamwidth_deg,
+            tilt_deg: radar.antenna.tilt_deg,
+            refraction_k: radar.refraction.k,
+            propagation: if itm.is_some() { "itm" } else { "knife_edge" }.to_string(),
+            itm_climate: itm.map(|settings| climate_name(settings.climate).to_string()),
+            itm_polarization: itm.map(|settings| {
+                match settings.polarization {
+                    Polarization::Horizontal => "horizontal",
+                    Polarization::Vertical => "vertical",
+                }
+                .to_string()
+            }),
+            itm_permittivity: itm.map(|settings| settings.permittivity),
+            itm_conductivity: itm.map(|settings| settings.conductivity),
+            itm_surface_refractivity: itm.map(|settings| settings.surface_refractivity),
+            itm_confidence: itm.map(|settings| settings.confidence),
+            itm_reliability: itm.map(|settings| settings.reliability),
+        }
+    }
+
+    /// (column, cell) in file order; the header is the column names
+    fn cells(&self) -> Vec<(&'static str, String)> {
+        let number = |value: f64| value.to_string();
+        let optional = |value: Option<f64>| value.map_or(String::new(), number);
+        vec![
+            ("name", self.name.clone()),
+            ("enabled", self.enabled.to_string()),
+            ("color", self.color.clone()),
+            ("latitude", number(self.latitude)),
+            ("longitude", number(self.longitude)),
+            ("altitude_m", number(self.altitude_m)),
+            ("antenna_height_agl_m", optional(self.antenna_height_agl_m)),
+            ("station_type", self.station_type.clone()),
+            ("transponder_power_dbm", optional(self.transponder_power_dbm)),
+            ("transponder_mtl_dbm", optional(self.transponder_mtl_dbm)),
+            ("aircraft_power_dbm", optional(self.aircraft_power_dbm)),
+            ("aircraft_sensitivity_dbm", optional(self.aircraft_sensitivity_dbm)),
+            ("frequency_hz", number(self.frequency_hz)),
+            ("transmit_power_dbm", number(self.transmit_power_dbm)),
+            ("gain_dbi", number(self.gain_dbi)),
+            ("sensitivity_dbm", number(self.sensitivity_dbm)),
+            ("noise_figure_db", number(self.noise_figure_db)),
+            ("bandwidth_hz", number(self.bandwidth_hz)),
+            ("processing_gain_db", number(self.processing_gain_db)),
+            ("azimuth_pattern", self.azimuth_pattern.clone()),
+            ("elevation_pattern", self.elevation_pattern.clone()),
+            ("min_elevation_deg", number(self.min_elevation_deg)),
+            ("max_elevation_deg", number(self.max_elevation_deg)),
+            ("blanked_sectors", self.blanked_sectors.clone()),
+            ("beamwidth_deg", number(self.beamwidth_deg)),
+            ("tilt_deg", number(self.tilt_deg)),
+            ("refraction_k", number(self.refraction_k)),
+            ("propagation", self.propagation.clone()),
+            ("itm_climate", self.itm_climate.clone().unwrap_or_default()),
+            ("itm_polarization", self.itm_polarization.clone().unwrap_or_default()),
+            ("itm_permittivity", optional(self.itm_permittivity)),
+            ("itm_conductivity", optional(self.itm_conductivity)),
+            ("itm_surface_refractivity", optional(self.itm_surface_refractivity)),
+            ("itm_confidence", optional(self.itm_confidence)),
+            ("itm_reliability", optional(self.itm_reliability)),
+        ]
+    }
+
+    /// Station described by the row, after checking every value
+    fn to_radar(&self, line: usize) -> Result<Radar, ScenarioError> {
+        let invalid = |field: &str, message: String| ScenarioError::Invalid { line, field: field.to_string(), message };
+        let required = |field: &str, value: Option<f64>| {
+            value.ok_or_else(|| invalid(field, format!("required for station type '{}'", self.station_type)))
+        };
+        let positive = |field: &str, value: f64| {
+            if value > 0.0 && value.is_finite() {
+                Ok(value)
+            } else {
+                Err(invalid(field, format!("must be positive, got {}", value)))
+            }
+        };
+        let fraction = |field: &str, value: Option<f64>, default: f64| match value {
+            Some(value) if !(value > 0.0 && value < 1.0) => Err(invalid(field, format!("must be between 0 and 1, got {}", value))),
+            value => Ok(value.unwrap_or(default)),
+        };
+
+        if self.name.trim().is_empty() {
+            return Err(invalid("name", "must not be empty".to_string()));
+        }
+        if !(-90.0..=90.0).contains(&self.latitude) {
+            return Err(invalid("latitude", format!("must be between -90 and 90, got {}", self.latitude)));
+        }
+        if !(-180.0..=180.0).contains(&self.longitude) {
+            return Err(invalid("longitude", format!("must be between -180 and 180, got {}", self.longitude)));
+        }
+        if let Some(agl) = self.antenna_height_agl_m.filter(|agl| *agl < 0.0 || agl.is_nan()) {
+            return Err(invalid("antenna_height_agl_m", format!("must not be negative, got {}", agl)));
+        }
+        let color = Srgba::hex(&self.color).map_err(|e| invalid("color", format!("'{}' is not a hex color ({})", self.color, e)))?;
+
+        let station_type = match self.station_type.as_str() {
+            "primary" => StationType::Primary,
+            "ssr" => StationType::Ssr {
+                transponder_power_dbm: required("transponder_power_dbm", self.transponder_power_dbm)?,
+                transponder_mtl_dbm: required("transponder_mtl_dbm", self.transponder_mtl_dbm)?,
+            },
+            "adsb" => StationType::AdsB {
+                transponder_power_dbm: required("transponder_power_dbm", self.transponder_power_dbm)?,
+            },
+            "vhf" => StationType::VhfRadio {
+                aircraft_power_dbm: required("aircraft_power_dbm", self.aircraft_power_dbm)?,
+                aircraft_sensitivity_dbm: required("aircraft_sensitivity_dbm", self.aircraft_sensitivity_dbm)?,
+            },
+            other => {
+                return Err(invalid("station_type", format!("unknown type '{}' (primary, ssr, adsb or vhf)", other)));
+            }
+        };
+
+        let antenna = Antenna {
+            azimuth_pattern: parse_pairs(&self.azimuth_pattern).map_err(|e| invalid("azimuth_pattern", e))?,
+            elevation_pattern: parse_pairs(&self.elevation_pattern).map_err(|e| invalid("elevation_pattern", e))?,
+            min_elevation_deg: self.min_elevation_deg,
+            max_elevation_deg: self.max_elevation_deg,
+            blanked_sectors: parse_pairs(&self.blanked_sectors).map_err(|e| invalid("blanked_sectors", e))?,
+            beamwidth_deg: positive("beamwidth_deg", self.beamwidth_deg)?,
+            tilt_deg: self.tilt_deg,
+        };
+        for (field, table) in [("azimuth_pattern", &antenna.azimuth_pattern), ("elevation_pattern", &antenna.elevation_pattern)] {
+            if table.windows(2).any(|pair| pair[0].0 >= pair[1].0) {
+                return Err(invalid(field, "angles must be increasing".to_string()));
+            }
+        }
+        if antenna.min_elevation_deg > antenna.max_elevation_deg {
+            return Err(invalid(
+                "max_elevation_deg",
+                format!("below min_elevation_deg ({} < {})", antenna.max_elevation_deg, antenna.min_elevation_deg),
+            ));
+        }
+
+        // k = 0 has no meaning; negative k is ducting
+        if self.refraction_k == 0.0 || self.refraction_k.is_nan() {
+            return Err(invalid("refraction_k", "must not be zero".to_string()));
+        }
+
+        let propagation = match self.propagation.as_str() {
+            "knife_edge" => Propagation::KnifeEdge,
+            "itm" => {
+                let default = ItmSettings::default();
+                let climate = match self.itm_climate.as_deref() {
+                    None => default.climate,
+                    Some(name) => parse_climate(name).ok_or_else(|| invalid("itm_climate", format!("unknown climate '{}'", name)))?,
+                };
+                let polarization = match self.itm_polarization.as_deref() {
+                    None => default.polarization,
+                    Some("horizontal") => Polarization::Horizontal,
+                    Some("vertical") => Polarization::Vertical,
+                    Some(other) => {
+                        return Err(invalid("itm_polarization", format!("'{}' is not horizontal or vertical", other)));
+                    }
+                };
+                Propagation::Itm(ItmSettings {
+                    climate,
+                    polarization,
+                    permittivity: positive("itm_permittivity", self.itm_permittivity.unwrap_or(default.permittivity))?,
+                    conductivity: positive("itm_conductivity", self.itm_conductivity.unwrap_or(default.conductivity))?,
+                    surface_refractivity: positive(
+                        "itm_surface_refractivity",
+                        self.itm_surface_refractivity.unwrap_or(default.surface_refractivity),
+                    )?,
+                    confidence: fraction("itm_confidence", self.itm_confidence, default.confidence)?,
+                    reliability: fraction("itm_reliability", self.itm_reliability, default.reliability)?,
+                })
+            }
+            other => {
+                return Err(invalid("propagation", format!("unknown model '{}' (knife_edge or itm)", other)));
+            }
+        };
+
+        Ok(Radar {
+            name: self.name.clone(),
+            position: DVec3::new(self.latitude, self.longitude, self.altitude_m),
+            antenna_height_agl: self.antenna_height_agl_m,
+            site_elevation: None,
+            enabled: self.enabled,
+            color: color.into(),
+            station_type,
+            frequency: positive("frequency_hz", self.frequency_hz)?,
+            transmit_power_dbm: self.transmit_power_dbm,
+            gain_dbi: self.gain_dbi,
+            sensitivity_dbm: self.sensitivity_dbm,
+            noise_figure_db: self.noise_figure_db,
+            bandwidth_hz: positive("bandwidth_hz", self.bandwidth_hz)?,
+            processing_gain_db: self.processing_gain_db,
+            antenna,
+            refraction: Refraction { k: self.refraction_k },
+            propagation,
+        })
+    }
+}
+
+/// Name of a radio climate in scenario files
+fn climate_name(climate: RadioClimate) -> &'static str {
+    match climate {
+        RadioClimate::Equatorial => "equatorial",
+        RadioClimate::ContinentalSubtropical => "continental_subtropical",
+        RadioClimate::MaritimeSubtropical => "maritime_subtropical",
+        RadioClimate::Desert => "desert",
+        RadioClimate::ContinentalTemperate => "continental_temperate",
+        RadioClimate::MaritimeTemperateOverLand => "maritime_temperate_land",
+        RadioClimate::MaritimeTemperateOverSea => "maritime_temperate_sea",
+    }
+}
+
+fn parse_climate(name: &str) -> Option<RadioClimate> {
+    let mut climate = RadioClimate::Equatorial;
+    for _ in 0..7 {
+        if climate_name(climate) == name {
+            return Some(climate);
+        }
+        climate = climate.next();
+    }
+    None
+}
+
+/// `a:b;a:b;...` (empty for no entries)
+fn format_pairs(pairs: &[(f64, f64)]) -> String {
+    pairs.iter().map(|(a, b)| format!("{}:{}", a, b)).collect::<Vec<_>>().join(";")
+}
+
+fn parse_pairs(text: &str) -> Result<Vec<(f64, f64)>, String> {
+    text.split(';')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| {
+            let parsed = entry
+                .split_once(':')
+                .and_then(|(a, b)| Some((a.trim().parse().ok()?, b.trim().parse().ok()?)));
+            parsed.ok_or_else(|| format!("'{}' is not a pair of numbers 'a:b'", entry))
+        })
+        .collect()
+}
+
+/// Stations of a scenario file
+/// The first line that isn't blank or a `#` comment is the header.
+pub fn parse_radars(text: &str) -> Result<Radars, ScenarioError> {
+    let mut rows = split_rows(text)?.into_iter();
+    let (_, header) = rows.next().ok_or(ScenarioError::Format { line: 1, message: "no header".to_string() })?;
+
+    let mut stations = Vec::new();
+    for (line, cells) in rows {
+        if cells.len() != header.len() {
+            return Err(ScenarioError::Format {
+                line,
+                message: format!("{} cells for {} columns", cells.len(), header.len()),
+            });
+        }
+
+        let columns = header.iter().zip(&cells).map(|(field, value)| (field.as_str(), Cell { field, value }));
+        let record = StationRecord::deserialize(MapDeserializer::new(columns)).map_err(|e| ScenarioError::Invalid {
+            line,
+            field: e.field.unwrap_or_else(|| "-".to_string()),
+            message: e.message,
+        })?;
+        stations.push(record.to_radar(line)?);
+    }
+    Ok(Radars { stations })
+}
+
+/// Scenario file for the stations
+pub fn format_radars(radars: &Radars) -> String {
+    let records: Vec<Vec<(&str, String)>> = radars.stations.iter().map(|radar| StationRecord::from_radar(radar).cells()).collect();
+    let names: Vec<&str> = StationRecord::default().cells().into_iter().map(|(name, _)| name).collect();
+
+    let mut out = String::from("# Radar stations (see the README for the columns)\n");
+    out.push_str(&names.join(","));
+    out.push('\n');
+    for cells in records {
+        let values: Vec<String> = cells.into_iter().map(|(_, value)| quote(&value)).collect();
+        out.push_str(&values.join(","));
+        out.push('\n');
+    }
+    out
+}
+
+/// Read a scenario file
+pub fn load_radars(path: &Path) -> Result<Radars, ScenarioError> {
+    let text = std::fs::read_to_string(path).map_err(|source| ScenarioError::Io { path: path.to_path_buf(), source })?;
+    parse_radars(&text)
+}
+
+/// Write a scenario file (creating its directory)
+pub fn save_radars(path: &Path, radars: &Radars) -> Result<(), ScenarioError> {
+    let io = |source| ScenarioError::Io { path: path.to_path_buf(), source };
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        std::fs::create_dir_all(dir).map_err(io)?;
+    }
+    std::fs::write(path, format_radars(radars)).map_err(io)
+}
+
+/// Stations and scenario path selected on the command line (`--radars <file>`)
+/// Without the option, the built-in stations. A file that can't be read is fatal: running
+/// silently with other stations would be worse.
+pub fn radars_from_args() -> (Radars, ScenarioPath) {
+    let args: Vec<String> = std::env::args().collect();
+    let Some(index) = args.iter().position(|arg| arg == RADARS_OPTION) else {
+        return (Radars::default(), ScenarioPath::default());
+    };
+    let Some(path) = args.get(index + 1).map(PathBuf::from) else {
+        eprintln!("{} needs a file name", RADARS_OPTION);
+        std::process::exit(2);
+    };
+
+    match load_radars(&path) {
+        Ok(radars) => (radars, ScenarioPath(path)),
+        Err(e) => {
+            eprintln!("Invalid scenario {:?}: {}", path, e);
+            std::process::exit(2);
+        }
+    }
+}
+
+/// System to save the current stations to the scenario file (F5)
+pub fn save_scenario_system(keys: Res<ButtonInput<KeyCode>>, radars: Res<Radars>, path: Res<ScenarioPath>) {
+    if !keys.just_pressed(KeyCode::F5) {
+        return;
+    }
+
+    match save_radars(&path.0, &radars) {
+        Ok(()) => info!("Saved {} stations to {:?}", radars.stations.len(), path.0),
+        Err(e) => error!("Failed to save stations: {}", e),
+    }
+}
+
+/// Split a CSV file into rows of cells, with the line each row starts on
+/// Double quotes may hold commas, line breaks and edge whitespace ("" inside quotes is a
+/// quote); unquoted cells are trimmed. Blank lines and `#` comments between rows are skipped.
+fn split_rows(text: &str) -> Result<Vec<(usize, Vec<String>)>, ScenarioError> {
+    let mut rows = Vec::new();
+    let mut cells = Vec::new();
+    let mut cell = String::new();
+    // Whether the current cell was quoted, and whether its quotes are still open
+    let (mut quoted_cell, mut quoted) = (false, false);
+    let (mut line, mut row_line) = (1, 1);
+    // A final line break ends the last row like the others
+    let mut chars = text.chars().chain(std::iter::once('\n')).peekable();
+
+    let end_cell = |cell: &mut String, quoted_cell: &mut bool| {
+        let cell = std::mem::take(cell);
+        if std::mem::take(quoted_cell) { cell } else { cell.trim().to_string() }
+    };
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                chars.next();
+                cell.push('"');
+            }
+            '"' if quoted => quoted = false,
+            // Opening quote: whatever whitespace came before it isn't part of the cell
+            '"' if !quoted_cell && cell.trim().is_empty() => {
+                cell.clear();
+                (quoted_cell, quoted) = (true, true);
+            }
+            // Whitespace after the closing quote
+            c if quoted_cell && !quoted && c.is_whitespace() && c != '\n' => {}
+            ',' if !quoted => cells.push(end_cell(&mut cell, &mut quoted_cell)),
+            '\n' if !quoted => {
+                let first = cells.is_empty() && !quoted_cell;
+                if first && (cell.trim().is_empty() || cell.trim_start().starts_with('#')) {
+                    cell.clear();
+                } else {
+                    cells.push(end_cell(&mut cell, &mut quoted_cell));
+                    rows.push((row_line, std::mem::take(&mut cells)));
+                }
+                line += 1;
+                row_line = line;
+            }
+            _ => {
+                if c == '\n' {
+                    line += 1;
+                }
+                cell.push(c);
+            }
+        }
+    }
+    if quoted {
+        return Err(ScenarioError::Format { line: row_line, message: "unterminated quote".to_string() });
+    }
+    Ok(rows)
+}
+
+/// Cell as written to a scenario file: quoted when `split_rows` would otherwise change it
+fn quote(value: &str) -> String {
+    let edge_whitespace = value.trim() != value;
+    if value.contains([',', '"', '\n', '\r']) || edge_whitespace || value.starts_with('#') {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Error decoding a row, with the column it concerns when known
+#[derive(Debug)]
+struct CellError {
+    field: Option<String>,
+    message: String,
+}
+
+impl std::fmt::Display for CellError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for CellError {}
+
+impl de::Error for CellError {
+    fn custom<T: std::fmt::Display>(msg: T) -> Self {
+        Self { field: None, message: msg.to_string() }
+    }
+
+    fn missing_field(field: &'static str) -> Self {
+        Self { field: Some(field.to_string()), message: "missing column".to_string() }
+    }
+
+    fn unknown_field(field: &str, _expected: &'static [&'static str]) -> Self {
+        Self { field: Some(field.to_string()), message: "unknown column".to_string() }
+    }
+}
+
+/// A CSV cell, decoded into whatever type the record asks for
+struct Cell<'a> {
+    field: &'a str,
+    value: &'a str,
+}
+
+impl Cell<'_> {
+    fn error(&self, message: String) -> CellError {
+        CellError { field: Some(self.field.to_string()), message }
+    }
+}
+
+impl<'de> IntoDeserializer<'de, CellError> for Cell<'_> {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+impl<'de> de::Deserializer<'de> for Cell<'_> {
+    type Error = CellError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, CellError> {
+        visitor.visit_str(self.value)
+    }
+
+    fn deserialize_f64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, CellError> {
+        match self.value.parse::<f64>() {
+            Ok(value) if value.is_finite() => visitor.visit_f64(value),
+            Ok(_) => Err(self.error(format!("expected a finite number, got '{}'", self.value))),
+            Err(_) => Err(self.error(format!("expected a number, got '{}'", self.value))),
+        }
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, CellError> {
+        match self.value.to_ascii_lowercase().as_str() {
+            "true" | "yes" | "1" => visitor.visit_bool(true),
+            "false" | "no" | "0" => visitor.visit_bool(false),
+            _ => Err(self.error(format!("expected true or false, got '{}'", self.value))),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, CellError> {
+        if self.value.is_empty() {
+            visitor.visit_none()
+        } else {
+            visitor.visit_some(self)
+        }
+    }
+
+    serde::forward_to_deserialize_any! {
+        i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 char str string bytes byte_buf unit
+        unit_struct newtype_struct seq tuple tuple_struct map struct enum identifier ignored_any
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let mut radars = Radars::default();
+        radars.stations[0].antenna_height_agl = Some(30.0);
+        radars.stations[1].antenna = Antenna::en_route();
+        radars.stations[1].antenna.blanked_sectors.push((350.0, 10.0));
+        radars.stations[2].propagation = Propagation::Itm(ItmSettings::SEA_WATER);
+        radars.stations[2].name = "Lyon, \"Mont Verdun\"".to_string();
+
+        let text = format_radars(&radars);
+        let loaded = parse_radars(&text).unwrap();
+        assert_eq!(loaded.stations.len(), radars.stations.len());
+        for (original, loaded) in radars.stations.iter().zip(&loaded.stations) {
+            // Colors go through 8-bit hex
+            assert_eq!(original.color.to_srgba().to_hex(), loaded.color.to_srgba().to_hex());
+            let loaded = Radar { color: original.color, ..loaded.clone() };
+            assert_eq!(format!("{:?}", original), format!("{:?}", loaded));
+        }
+        // Saving again gives the same file
+        assert_eq!(format_radars(&loaded), text);
+    }
+
+    #[test]
+    fn test_quoted_cells() {
+        // Line breaks, edge whitespace and a leading # survive a round trip
+        let mut radars = Radars::default();
+        radars.stations[0].name = "Mont Agel\nnorth face".to_string();
+        radars.stations[1].name = "  Sainte-Baume ".to_string();
+        radars.stations[2].name = "# 3".to_string();
+        let loaded = parse_radars(&format_radars(&radars)).unwrap();
+        let names: Vec<&str> = loaded.stations.iter().map(|radar| radar.name.as_str()).collect();
+        assert_eq!(names[..3], ["Mont Agel\nnorth face", "  Sainte-Baume ", "# 3"]);
+
+        // Rows keep the line they start on after a multi-line cell, blank lines and comments
+        let rows = split_rows("a,b\r\n\r\n\"x\ny\", 1 \r\n# note\n z , \" w \"\r\n").unwrap();
+        assert_eq!(
+            rows,
+            vec![
+                (1, vec!["a".to_string(), "b".to_string()]),
+                (3, vec!["x\ny".to_string(), "1".to_string()]),
+                (6, vec!["z".to_string(), " w ".to_string()]),
+            ]
+        );
+        assert_eq!(split_rows("a,\"b\n").unwrap_err().to_string(), "line 1: unterminated quote");
+    }
+
+    #[test]
+    fn test_errors_name_line_and_field() {
+        let text = format_radars(&Radars::default());
+        let lines: Vec<&str> = text.lines().collect();
+        // Line 3 is the first station (after the comment and the header)
+        let with_row = |row: String| format!("{}\n{}\n{}\n", lines[0], lines[1], row);
+        let error = |row: String| parse_radars(&with_row(row)).unwrap_err().to_string();
+
+        let bad_number = lines[2].replacen(",1300000000,", ",1.3 GHz,", 1);
+        assert_eq!(error(bad_number), "line 3, field 'frequency_hz': expected a number, got '1.3 GHz'");
+
+        // NaN and infinities parse as f64, but no field takes them
+        let nan = lines[2].replacen(",43.77528,", ",NaN,", 1);
+        assert_eq!(error(nan), "line 3, field 'latitude': expected a finite number, got 'NaN'");
+        let infinite = lines[2].replacen(",80,", ",inf,", 1);
+        assert_eq!(error(infinite), "line 3, field 'transmit_power_dbm': expected a finite number, got 'inf'");
+
+        let bad_type = lines[2].replacen(",primary,", ",lidar,", 1);
+        assert!(error(bad_type).starts_with("line 3, field 'station_type': unknown type 'lidar'"));
+
+        let bad_range = lines[2].replacen(",43.77528,", ",143.77528,", 1);
+        assert!(error(bad_range).starts_with("line 3, field 'latitude':"));
+
+        let short_row = lines[2].rsplit_once(',').unwrap().0.to_string();
+        assert!(error(short_row).starts_with("line 3: "));
+
+        // A cooperative station without its transponder parameters
+        let ssr = StationRecord::from_radar(&Radars::default().stations[3]);
+        let cells: Vec<String> = ssr
+            .cells()
+            .into_iter()
+            .map(|(name, value)| if name == "transponder_mtl_dbm" { String::new() } else { quote(&value) })
+            .collect();
+        assert_eq!(
+            error(cells.join(",")),
+            "line 3, field 'transponder_mtl_dbm': required for station type 'ssr'"
+        );
+
+        // Unknown and missing columns
+        let renamed = text.replacen("gain_dbi", "gain_db", 1);
+        assert_eq!(parse_radars(&renamed).unwrap_err().to_string(), "line 3, field 'gain_db': unknown column");
+    }
+}
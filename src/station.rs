@@ -0,0 +1,207 @@
+// Station types and their one-way link budgets
+use crate::radar::{Radar, SPEED_OF_LIGHT};
+
+/// Mode S / ATCRBS reply frequency (Hz); interrogations use the station's own frequency (1030 MHz)
+pub const SSR_REPLY_FREQUENCY: f64 = 1.09e9;
+
+/// Gain of an aircraft's blade antenna (dBi)
+const AIRCRAFT_ANTENNA_GAIN_DBI: f64 = 0.0;
+
+/// Kind of station, which decides how its range is computed
+///
+/// A primary radar sees the echo of the target itself (two-way, radar range equation with
+/// the RCS). The other types are cooperative: each direction of the exchange is a one-way
+/// link budget between the station and the aircraft's avionics, and the RCS plays no part.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum StationType {
+    /// Primary surveillance radar
+    #[default]
+    Primary,
+    /// Secondary surveillance radar: 1030 MHz interrogation up, 1090 MHz transponder reply down
+    Ssr {
+        /// Transponder reply power (dBm)
+        transponder_power_dbm: f64,
+        /// Transponder minimum triggering level (dBm)
+        transponder_mtl_dbm: f64,
+    },
+    /// 1090ES ADS-B ground receiver (listen only)
+    AdsB {
+        /// Transponder extended squitter power (dBm)
+        transponder_power_dbm: f64,
+    },
+    /// VHF air-ground radio site: ground to air and air to ground must both close
+    VhfRadio {
+        /// Aircraft transmitter power (dBm)
+        aircraft_power_dbm: f64,
+        /// Aircraft receiver sensitivity (dBm)
+        aircraft_sensitivity_dbm: f64,
+    },
+}
+
+/// One direction of a cooperative exchange
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Link {
+    /// Transmitter power plus transmit antenna gain (dBm)
+    pub eirp_dbm: f64,
+    /// Receive antenna gain (dBi)
+    pub receive_gain_dbi: f64,
+    /// Receiver sensitivity (dBm)
+    pub sensitivity_dbm: f64,
+    /// Carrier frequency (Hz)
+    pub frequency: f64,
+    /// Whether the station is the receiver (downlink)
+    pub to_ground: bool,
+}
+
+impl StationType {
+    /// Class A1 transponder (250 W, MTL -74 dBm per DO-181)
+    pub fn ssr() -> Self {
+        Self::Ssr { transponder_power_dbm: 54.0, transponder_mtl_dbm: -74.0 }
+    }
+
+    /// Extended squitter from a class A1 transponder (250 W)
+    pub fn adsb() -> Self {
+        Self::AdsB { transponder_power_dbm: 54.0 }
+    }
+
+    /// Typical airliner VHF radio (25 W, -101 dBm)
+    pub fn vhf_radio() -> Self {
+        Self::VhfRadio { aircraft_power_dbm: 44.0, aircraft_sensitivity_dbm: -101.0 }
+    }
+
+    /// One-way links between `radar` and an aircraft (empty for a primary radar)
+    pub fn links(&self, radar: &Radar) -> Vec<Link> {
+        let uplink = |sensitivity_dbm: f64| Link {
+            eirp_dbm: radar.transmit_power_dbm + radar.gain_dbi,
+            receive_gain_dbi: AIRCRAFT_ANTENNA_GAIN_DBI,
+            sensitivity_dbm,
+            frequency: radar.frequency,
+            to_ground: false,
+        };
+        let downlink = |power_dbm: f64, frequency: f64| Link {
+            eirp_dbm: power_dbm + AIRCRAFT_ANTENNA_GAIN_DBI,
+            receive_gain_dbi: radar.gain_dbi,
+            sensitivity_dbm: radar.sensitivity_dbm,
+            frequency,
+            to_ground: true,
+        };
+
+        match *self {
+            Self::Primary => Vec::new(),
+            Self::Ssr { transponder_power_dbm, transponder_mtl_dbm } => vec![
+                uplink(transponder_mtl_dbm),
+                downlink(transponder_power_dbm, SSR_REPLY_FREQUENCY),
+            ],
+            Self::AdsB { transponder_power_dbm } => vec![downlink(transponder_power_dbm, radar.frequency)],
+            Self::VhfRadio { aircraft_power_dbm, aircraft_sensitivity_dbm } => vec![
+                uplink(aircraft_sensitivity_dbm),
+                downlink(aircraft_power_dbm, radar.frequency),
+            ],
+        }
+    }
+
+    /// Every parameter as numbers, for cache keys
+    pub fn key_values(&self) -> Vec<f64> {
+        match *self {
+            Self::Primary => vec![0.0],
+            Self::Ssr { transponder_power_dbm, transponder_mtl_dbm } => vec![1.0, transponder_power_dbm, transponder_mtl_dbm],
+            Self::AdsB { transponder_power_dbm } => vec![2.0, transponder_power_dbm],
+            Self::VhfRadio { aircraft_power_dbm, aircraft_sensitivity_dbm } => {
+                vec![3.0, aircraft_power_dbm, aircraft_sensitivity_dbm]
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for StationType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Self::Primary => "primary radar",
+            Self::Ssr { .. } => "SSR",
+            Self::AdsB { .. } => "ADS-B receiver",
+            Self::VhfRadio { .. } => "VHF radio",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl Link {
+    /// Free-space path loss over `range_m` (dB)
+    pub fn path_loss_db(&self, range_m: f64) -> f64 {
+        let lambda = SPEED_OF_LIGHT / self.frequency;
+        20.0 * (4.0 * std::f64::consts::PI * range_m / lambda).log10()
+    }
+
+    /// Power at the receiver at `range_m` (dBm)
+    pub fn received_dbm(&self, range_m: f64) -> f64 {
+        self.eirp_dbm + self.receive_gain_dbi - self.path_loss_db(range_m)
+    }
+
+    /// Range at which the received power falls to the sensitivity (meters)
+    ///
+    /// ALGORITHM: One-Way Link Budget (Friis)
+    ///     P_r = EIRP + G_r - 20 log10(4 pi R / lambda)
+    /// Setting P_r to the sensitivity S gives
+    ///     R_max = lambda / (4 pi) * 10^((EIRP + G_r - S) / 20)
+    /// The range grows with the square root of the power, against the fourth root for the
+    /// two-way echo of a primary radar.
+    pub fn max_range(&self) -> f64 {
+        let lambda = SPEED_OF_LIGHT / self.frequency;
+        lambda / (4.0 * std::f64::consts::PI)
+            * 10.0_f64.powf((self.eirp_dbm + self.receive_gain_dbi - self.sensitivity_dbm) / 20.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{default_radar, TARGET_RCS};
+
+    #[test]
+    fn test_link_budget() {
+        let link = Link {
+            eirp_dbm: 54.0,
+            receive_gain_dbi: 0.0,
+            sensitivity_dbm: -74.0,
+            frequency: 1.03e9,
+            to_ground: false,
+        };
+        // Free-space loss at 1 km and 1030 MHz is about 92.7 dB
+        assert!((link.path_loss_db(1_000.0) - 92.7).abs() < 0.1);
+        let range = link.max_range();
+        assert!((link.received_dbm(range) - link.sensitivity_dbm).abs() < 1e-9);
+        // 6 dB more doubles the range
+        let stronger = Link { eirp_dbm: 60.0, ..link };
+        assert!((stronger.max_range() / range - 2.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_station_ranges() {
+        let mut radar = default_radar();
+        radar.frequency = 1.03e9;
+        radar.transmit_power_dbm = 63.0; // 2 kW interrogator
+        radar.gain_dbi = 27.0;
+        radar.sensitivity_dbm = -80.0;
+        radar.station_type = StationType::ssr();
+
+        // The downlink (250 W against -80 dBm) closes before the uplink (MTL -74 dBm)
+        let links = radar.station_type.links(&radar);
+        assert_eq!(links.len(), 2);
+        assert!(links[1].max_range() < links[0].max_range());
+        assert_eq!(radar.calculate_max_range(TARGET_RCS), links[1].max_range());
+        // Hundreds of kilometers in free space, unlike a primary radar on the same budget
+        assert!(radar.calculate_max_range(TARGET_RCS) > 300_000.0);
+
+        // Listening only: the interrogator power doesn't matter
+        radar.station_type = StationType::adsb();
+        radar.frequency = SSR_REPLY_FREQUENCY;
+        let range = radar.calculate_max_range(TARGET_RCS);
+        radar.transmit_power_dbm = 0.0;
+        assert_eq!(radar.calculate_max_range(TARGET_RCS), range);
+
+        // Cooperative targets ignore the RCS, and fall silent beyond range
+        assert_eq!(radar.snr(100_000.0, 0.01), radar.snr(100_000.0, 40.0));
+        assert!(radar.snr(100_000.0, 1.0) > 1.0);
+        assert_eq!(radar.snr(2.0 * range, 1.0), 0.0);
+    }
+}
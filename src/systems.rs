@@ -1,68 +1,105 @@
 // Systems for coordinating tile loading and mesh updates
 use bevy::prelude::*;
+use bevy::camera::primitives::{Aabb, Frustum};
+use bevy::math::{Affine3A, DVec3};
 use bevy::tasks::{AsyncComputeTaskPool, Task};
 use futures_lite::future;
 use crate::cache::TileCache;
 use crate::colormap::ColorMap;
+use crate::coverage::{CoverageCache, CoverageSettings, CoverageView};
 use crate::downloader::TileDownloader;
 use crate::lod::LodManager;
-use crate::mesh_builder::TerrainMeshBuilder;
+use crate::mesh_builder::{PlaceholderKind, TerrainMeshBuilder};
+use crate::placement::Placement;
+use crate::radar::Radars;
 use crate::tile::{TileCoord, TileState};
+use crate::tile_manager::{TileManager, TilePhase};
+use crate::world::{FloatingOrigin, WorldMode, WorldPosition};
+
+/// Tiles out of view for longer than this are evicted (seconds)
+const EVICT_DELAY_SECS: f64 = 5.0;
+
+/// Tiles closer than this (flat-mode units) are never evicted, so turning around is instant
+const KEEP_DISTANCE: f32 = 20000.0;
 
 /// Component marking a terrain tile entity
 #[derive(Component)]
 pub struct TerrainTile {
     pub coord: TileCoord,
+    /// Bounding box relative to the tile anchor (from `TileData::height_range`)
+    pub bounds: Aabb,
+    /// Last time (seconds since startup) the tile intersected the camera frustum
+    pub last_seen: f64,
 }
 
-/// Marker for tiles that need mesh regeneration
-#[derive(Component)]
-pub struct NeedsRegen;
+/// Number of tiles to keep around the camera, from its altitude (meters)
+pub fn view_radius_tiles(camera_alt: f64) -> i32 {
+    // Calculate visible range with a much more generous scale
+    // At 1000m (approx 33 units height), we want to see at least 2 tiles radius (220km)
+    // At 10,000m (333 units), we want to see 10 tiles (1100km)
+    // Formula: y / 30.0 gives approx altitude in meters if scale=30m/unit? 
+    // Wait, tile_size=3601 units = 1 degree = 111km. So 1 unit = 30 meters.
+    // cam altitude of 1000 units = 30km altitude.
+    // Horizon at 30km is 600km = 6 tiles.
+    // So y/150 gives approx tiles.
+    // Let's be very generous: y / 100.0
+    let view_distance = (camera_alt as f32 / 100.0).clamp(2.0, 40.0);
+    view_distance.ceil() as i32
+}
+
+/// Bounding box of a loaded tile (None until its height range is known)
+fn tile_bounds(cache: &TileCache, mode: WorldMode, coord: TileCoord) -> Option<Aabb> {
+    let (min_h, max_h) = cache.height_range(&coord)?;
+    Some(mode.tile_bounds(coord, min_h as f64, max_h as f64))
+}
+
+/// ALGORITHM: Frustum Culling
+/// The tile AABB is expressed relative to its anchor; placing the anchor in render space
+/// turns it into an oriented box that we test against the 6 planes of the camera frustum.
+fn tile_in_frustum(frustum: &Frustum, origin: &FloatingOrigin, anchor: DVec3, bounds: &Aabb) -> bool {
+    let world_from_local = Affine3A::from_translation(origin.to_local(anchor));
+    frustum.intersects_obb(bounds, &world_from_local, true, false)
+}
 
-/// Component for tracking background mesh generation tasks
+/// Chebyshev distance in tiles between two tile coordinates
+fn tile_distance(a: TileCoord, b: TileCoord) -> i32 {
+    (a.lat - b.lat).abs().max((a.lon - b.lon).abs())
+}
+
+/// Component for tracking a background mesh generation task on a tile entity
+/// Inserting a new one replaces (and cancels) the previous task.
 #[derive(Component)]
 pub struct MeshGenTask {
     task: Task<Mesh>,
-    coord: TileCoord,
+}
+
+/// Component for tracking a background recolor task on a tile entity
+/// Only the color attribute is recomputed; the geometry stays in place.
+#[derive(Component)]
+pub struct RecolorTask {
+    task: Task<Vec<[f32; 4]>>,
 }
 
 /// System to determine visible tiles and request loading
 pub fn tile_loader_system(
-    camera_query: Query<&Transform, With<Camera>>,
+    camera_query: Query<&WorldPosition, With<Camera>>,
     mut cache: ResMut<TileCache>,
+    mut manager: ResMut<TileManager>,
     downloader: Res<TileDownloader>,
+    mode: Res<WorldMode>,
 ) {
-    let Ok(camera_transform) = camera_query.single() else {
+    let Ok(camera_world) = camera_query.single() else {
         return;
     };
 
     // Calculate which tile the camera is over
-    let cam_pos = camera_transform.translation;
-    
-    // Calculate tile coordinate from camera position
     // COORDINATE MAPPING:
     // World space Z corresponds to negative Latitude (North is negative Z).
     // The SRTM tile naming convention (e.g., N43) refers to the bottom-left corner.
-    // However, our world space origin 0,0 is N0E0.
-    // So: Lat_idx = ceil(-Z / 3601) - 1.
-    let tile_size = 3601.0;
-    let lat_idx = (-cam_pos.z / tile_size).ceil() as i32 - 1;
-    let center_coord = TileCoord::new(
-        lat_idx,
-        (cam_pos.x / tile_size).floor() as i32,
-    );
-
-    // Calculate visible range with a much more generous scale
-    // At 1000m (approx 33 units height), we want to see at least 2 tiles radius (220km)
-    // At 10,000m (333 units), we want to see 10 tiles (1100km)
-    // Formula: y / 30.0 gives approx altitude in meters if scale=30m/unit? 
-    // Wait, tile_size=3601 units = 1 degree = 111km. So 1 unit = 30 meters.
-    // cam_pos.y of 1000 units = 30km altitude.
-    // Horizon at 30km is 600km = 6 tiles.
-    // So y/150 gives approx tiles.
-    // Let's be very generous: y / 100.0
-    let view_distance = (cam_pos.y / 100.0).max(2.0).min(40.0); 
-    let tile_radius = view_distance.ceil() as i32;
+    // Absolute world positions are kept in f64, so we can go straight to lat/lon.
+    let cam_geo = mode.world_to_geo(camera_world.0);
+    let center_coord = TileCoord::from_world_coords(cam_geo.x, cam_geo.y);
+    let tile_radius = view_radius_tiles(cam_geo.z);
     
     // Load all tiles within viewing distance
     let mut tiles_to_load = Vec::new();
@@ -80,6 +117,7 @@ pub fn tile_loader_system(
         if cache.has_tile(&coord) {
             continue;
         }
+        manager.set_phase(coord, TilePhase::Requested);
 
         // Check disk cache first
         if cache.as_ref().is_cached_on_disk(&coord) {
@@ -87,15 +125,18 @@ pub fn tile_loader_system(
                 Ok(tile_data) => {
                     info!("Loaded tile from disk cache: {:?}", coord);
                     cache.insert_tile(coord, TileState::Loaded(std::sync::Arc::new(tile_data)));
+                    manager.on_data_loaded(coord, true);
                 }
                 Err(e) => {
                     error!("Failed to load tile from disk ({}): {}", coord.filename(), e);
                     cache.insert_tile(coord, TileState::Error(e));
+                    manager.on_data_loaded(coord, false);
                 }
             }
         } else {
             // Request download
             cache.mark_loading(coord);
+            manager.set_phase(coord, TilePhase::Loading);
             downloader.request_download(coord);
             //info!("Requesting download for tile: {:?}", coord);
         }
@@ -103,35 +144,40 @@ pub fn tile_loader_system(
 }
 
 /// System to queue mesh generation tasks
+#[allow(clippy::too_many_arguments)]
 pub fn mesh_update_system(
     mut commands: Commands,
     cache: Res<TileCache>,
     colormap: Res<ColorMap>,
     lod_manager: Res<LodManager>,
-    tile_query: Query<(Entity, &TerrainTile)>,
-    task_query: Query<&MeshGenTask>,
-    radars: Res<crate::radar::Radars>,
-    regen_query: Query<Entity, With<NeedsRegen>>,
-    camera_query: Query<&Transform, With<Camera>>,
+    mut manager: ResMut<TileManager>,
+    radars: Res<Radars>,
+    placement: Res<Placement>,
+    coverage_cache: Res<CoverageCache>,
+    coverage_settings: Res<CoverageSettings>,
+    camera_query: Query<(&Frustum, &WorldPosition), With<Camera>>,
+    mode: Res<WorldMode>,
+    origin: Res<FloatingOrigin>,
+    time: Res<Time>,
 ) {
     // Check if LOD changed globaly - if so, mark all tiles for regeneration
     // Note: With per-tile LOD, we might not need global triggers as much, 
     // but useful if user manually changes settings.
+    // Existing meshes stay on screen until their replacement is ready.
     if lod_manager.is_changed() {
-        for (entity, _) in tile_query.iter() {
-            commands.entity(entity).insert(NeedsRegen);
-        }
-    }
-    
-    // Regenerate meshes (remove existing, trigger new task)
-    for entity in regen_query.iter() {
-         commands.entity(entity).despawn(); 
+        manager.mark_all_stale();
     }
 
-    let Ok(camera_transform) = camera_query.single() else {
+    let Ok((frustum, camera_world)) = camera_query.single() else {
         return;
     };
-    let camera_pos = camera_transform.translation;
+    let camera_pos = camera_world.0;
+    let camera_geo = mode.world_to_geo(camera_pos);
+    let camera_coord = TileCoord::from_world_coords(camera_geo.x, camera_geo.y);
+    let max_tile_distance = view_radius_tiles(camera_geo.z);
+    // Tiles are colored for the stations plus any candidate being placed
+    let candidate = placement.candidate_index(&radars);
+    let radars = placement.radars(&radars);
 
     // Prepare snapshot of cache for background threads (Lazy)
     let mut snapshot: Option<std::sync::Arc<std::collections::HashMap<TileCoord, std::sync::Arc<crate::tile::TileData>>>> = None;
@@ -140,184 +186,411 @@ pub fn mesh_update_system(
     let mut tasks_spawned = 0;
     const MAX_TASKS_PER_FRAME: usize = 2;
 
-    // Iterate loaded tiles and check if we need to spawn a task
-    for (coord, tile_state) in cache.tiles.iter() {
-        if let TileState::Loaded(data_arc) = tile_state {
-            // Check if entity already exists
-            // Optimization: We could store entities in a map for faster lookup, but iteration is okay for <100 tiles
-            let exists = tile_query.iter().any(|(_, tile)| tile.coord == *coord);
-            let pending = task_query.iter().any(|t| t.coord == *coord);
-            
-            if !exists && !pending {
-                // Throttle check
-                if tasks_spawned >= MAX_TASKS_PER_FRAME {
-                    break; 
-                }
+    // Iterate tiles that need a (new) mesh
+    for coord in manager.tiles_to_mesh() {
+        // Tiles without elevation data get a placeholder instead of terrain
+        let (data_arc, placeholder) = match cache.get_tile(&coord) {
+            Some(TileState::Loaded(data)) => (Some(data.clone()), PlaceholderKind::Water),
+            Some(TileState::Missing) => (None, PlaceholderKind::Water),
+            Some(TileState::Error(_)) => (None, PlaceholderKind::Error),
+            _ => continue,
+        };
 
-                // Lazy Snapshot Creation
-                if snapshot.is_none() {
-                     snapshot = Some(std::sync::Arc::new(cache.get_snapshot()));
-                }
+        // Beyond the view radius: leave it in the cache but don't build a mesh
+        if tile_distance(coord, camera_coord) > max_tile_distance {
+            continue;
+        }
+
+        // Throttle check
+        if tasks_spawned >= MAX_TASKS_PER_FRAME {
+            break; 
+        }
+
+        // Calculate Distance-based LOD
+        // Center of tile in world space (f64 to stay exact far from N0E0)
+        let tile_center = mode.tile_center(coord);
+        
+        // Distances are expressed in flat-mode units so LOD thresholds hold on the globe
+        let distance = (camera_pos.distance(tile_center) / mode.unit_scale()) as f32;
+        let lod_level = lod_manager.calculate_lod(distance);
+
+        // Only build tiles whose bounding box intersects the camera frustum
+        let anchor = mode.tile_anchor(coord);
+        let bounds = tile_bounds(&cache, *mode, coord)
+            .unwrap_or_else(|| mode.tile_bounds(coord, 0.0, 0.0));
+        let is_visible = tile_in_frustum(frustum, &origin, anchor, &bounds);
+
+        // Exception: Always generate very close tiles regardless of direction (for rotating)
+        let is_close = distance < KEEP_DISTANCE;
+        
+        if !is_visible && !is_close {
+            continue;
+        }
+
+        // Lazy Snapshot Creation (only terrain meshes raycast against neighbours)
+        if data_arc.is_some() && snapshot.is_none() {
+             snapshot = Some(std::sync::Arc::new(cache.get_snapshot()));
+        }
+
+        // Spawn Mesh Generation Task
+        let thread_pool = AsyncComputeTaskPool::get();
+
+        // Coverage is computed with the terrain loaded so far; remember what was missing
+        // so the tile is rebuilt when it arrives
+        let deps = match (&data_arc, &snapshot) {
+            (Some(_), Some(snap)) => radars
+                .tiles_between(coord, coverage_settings.detection.target.rcs_m2())
+                .into_iter()
+                .filter(|dep| !snap.contains_key(dep))
+                .collect(),
+            _ => Default::default(),
+        };
+        manager.set_dependencies(coord, deps);
 
-                // Calculate Distance-based LOD
-                let tile_size = 3601.0;
-                // Center of tile in world space
-                // x = (lon + 0.5) * size
-                // z = -(lat + 0.5) * size
-                let center_x = (coord.lon as f32 + 0.5) * tile_size;
-                let center_z = -((coord.lat as f32 + 0.5) * tile_size);
-                let tile_center = Vec3::new(center_x, 0.0, center_z);
-                
-                let distance = camera_pos.distance(tile_center);
-                let lod_level = lod_manager.calculate_lod(distance);
-
-                // ALGORITHM: Frustum Culling (Approximate)
-                // Instead of full AABB frustum checks, we use a simple Dot Product check.
-                // 1. Calculate vector from Camera to Tile Center.
-                // 2. Calculate Camera Forward vector.
-                // 3. Dot Product > Threshold implies the tile is roughly "in front" of the camera.
-                // Threshold 0.2 approx corresponds to a wide FOV (allowing peripherals to load).
-                let cam_forward = camera_transform.forward();
-                let dir_to_tile = (tile_center - camera_pos).normalize_or_zero();
-                
-                let is_visible = cam_forward.dot(dir_to_tile) > 0.2;
-
-                // Exception: Always generate very close tiles regardless of direction (for rotating)
-                // Exception: Always generate very close tiles regardless of direction (for rotating)
-                let is_close = distance < 20000.0; // Increased to 20km for better rotation feel
-                
-                if !is_visible && !is_close {
-                    continue;
+        let colormap = colormap.clone();
+        let radars = radars.clone();
+        let mode = *mode;
+        let cache_snapshot = snapshot.clone();
+        let coverage_cache = coverage_cache.clone();
+        let settings = *coverage_settings;
+        
+        let task = thread_pool.spawn(async move {
+            let builder = TerrainMeshBuilder::new(lod_level).with_mode(mode);
+            match (data_arc, cache_snapshot) {
+                (Some(data), Some(snap)) => {
+                    // Coverage comes from the cache when this tile was covered before
+                    let coverage = coverage_cache.tile_coverage(&radars, candidate, &data, &settings, &snap);
+                    builder.build_mesh(&data, &colormap, Some(&radars), Some(&coverage))
                 }
+                _ => builder.build_placeholder_mesh(coord, placeholder),
+            }
+        });
 
-                // Spawn Mesh Generation Task
-                let thread_pool = AsyncComputeTaskPool::get();
-                
-
-                let coord = *coord;
-                let data = data_arc.clone();
-                let colormap = colormap.clone();
-                let radars = radars.clone();
-                let cache_snapshot = snapshot.as_ref().unwrap().clone();
-                
-                let task = thread_pool.spawn(async move {
-                    let builder = TerrainMeshBuilder::new(lod_level);
-                    builder.build_mesh(&data, &colormap, Some(&radars), Some(cache_snapshot.as_ref()))
-                });
-
-                commands.spawn(MeshGenTask { task, coord });
-                tasks_spawned += 1;
-                
-                info!("Queued mesh generation for {:?} (LOD {}, Dist {:.0})", coord, lod_level, distance);
+        // Reuse the tile entity if it exists: the task is swapped in place
+        let entity = match manager.entity(&coord) {
+            Some(entity) => {
+                commands.entity(entity).insert(MeshGenTask { task });
+                entity
             }
+            None => commands
+                .spawn((
+                    Transform::from_translation(origin.to_local(anchor)),
+                    Visibility::default(),
+                    WorldPosition(anchor),
+                    TerrainTile { coord, bounds, last_seen: time.elapsed_secs_f64() },
+                    MeshGenTask { task },
+                ))
+                .id(),
+        };
+        manager.on_mesh_queued(coord, entity, lod_level);
+        tasks_spawned += 1;
+        
+        info!("Queued mesh generation for {:?} (LOD {}, Dist {:.0})", coord, lod_level, distance);
+    }
+}
+
+/// System to poll mesh tasks and swap the finished meshes in place
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+pub fn process_mesh_tasks(
+    mut commands: Commands,
+    mut tasks: Query<(Entity, &mut MeshGenTask, &mut TerrainTile, &mut WorldPosition, Has<MeshMaterial3d<StandardMaterial>>)>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut manager: ResMut<TileManager>,
+    cache: Res<TileCache>,
+    mode: Res<WorldMode>,
+) {
+    for (entity, mut mesh_task, mut tile, mut world_pos, has_material) in &mut tasks {
+        let Some(mesh) = future::block_on(future::poll_once(&mut mesh_task.task)) else {
+            continue;
+        };
+        let coord = tile.coord;
+        commands.entity(entity).remove::<MeshGenTask>();
+
+        // Settings changed while this mesh was being built; a newer task is on its way
+        if manager.is_stale(&coord) {
+            continue;
+        }
+
+        // Refresh placement (the world layout may have changed since the entity was spawned)
+        world_pos.0 = mode.tile_anchor(coord);
+        tile.bounds = tile_bounds(&cache, *mode, coord)
+            .unwrap_or_else(|| mode.tile_bounds(coord, 0.0, 0.0));
+
+        // Replacing Mesh3d drops the previous handle, freeing the old mesh
+        let mut entity_commands = commands.entity(entity);
+        entity_commands.insert((Mesh3d(meshes.add(mesh)), Visibility::Inherited));
+        if !has_material {
+            entity_commands.insert(MeshMaterial3d(materials.add(StandardMaterial {
+                base_color: Color::WHITE,
+                perceptual_roughness: 0.8,
+                metallic: 0.0,
+                cull_mode: None,
+                alpha_mode: AlphaMode::Blend,
+                ..default()
+            })));
         }
+        manager.on_mesh_ready(coord);
+        
+        info!("Finished mesh generation for {:?}", coord);
     }
+}
 
-    // Handle missing tiles (placeholders)
-    for (coord, state) in cache.tiles.iter() {
-        if matches!(state, TileState::Missing) {
-            let exists = tile_query.iter().any(|(_, tile)| tile.coord == *coord);
-            if !exists {
-                 // For now, continue to spawn missing tiles on main thread (simple)
-                 // Or we could adapt spawn_missing_tile to return a Mesh and do it here?
-                 // Let's defer implementation of spawn_missing_tile or assume it exists
-            }
+/// Everything a recolor task needs besides its tile, shared by the tiles of one frame
+struct RecolorContext {
+    radars: Radars,
+    /// Index of the candidate station in `radars` (see `Placement::candidate_index`)
+    candidate: Option<usize>,
+    snapshot: std::sync::Arc<std::collections::HashMap<TileCoord, std::sync::Arc<crate::tile::TileData>>>,
+    colormap: ColorMap,
+    coverage_cache: CoverageCache,
+    settings: CoverageSettings,
+    mode: WorldMode,
+}
+
+impl RecolorContext {
+    /// Recolor a meshed tile in the background; returns whether a task was spawned
+    /// Replaces (and cancels) a recolor still running for previous settings. A rebuild in
+    /// flight would overwrite the new colors, so it is restarted instead.
+    fn spawn_recolor(
+        &self,
+        commands: &mut Commands,
+        manager: &mut TileManager,
+        cache: &TileCache,
+        entity: Entity,
+        coord: TileCoord,
+        rebuilding: bool,
+    ) -> bool {
+        if rebuilding {
+            manager.mark_stale(coord);
+            return false;
         }
+
+        // Placeholders (water, errors) don't depend on the radars
+        let Some(TileState::Loaded(data_arc)) = cache.get_tile(&coord) else {
+            return false;
+        };
+        let Some(lod_level) = manager.lod(&coord) else {
+            return false;
+        };
+
+        // The radar set may cross different terrain
+        let deps = self
+            .radars
+            .tiles_between(coord, self.settings.detection.target.rcs_m2())
+            .into_iter()
+            .filter(|dep| !self.snapshot.contains_key(dep))
+            .collect();
+        manager.set_dependencies(coord, deps);
+
+        let data = data_arc.clone();
+        let colormap = self.colormap.clone();
+        let radars = self.radars.clone();
+        let candidate = self.candidate;
+        let mode = self.mode;
+        let cache_snapshot = self.snapshot.clone();
+        let coverage_cache = self.coverage_cache.clone();
+        let settings = self.settings;
+
+        let task = AsyncComputeTaskPool::get().spawn(async move {
+            // Unchanged stations are served from the coverage cache
+            let coverage = coverage_cache.tile_coverage(&radars, candidate, &data, &settings, &cache_snapshot);
+            let builder = TerrainMeshBuilder::new(lod_level).with_mode(mode);
+            builder.build_colors(&data, &colormap, Some(&radars), Some(&coverage))
+        });
+        commands.entity(entity).insert(RecolorTask { task });
+        true
     }
 }
 
-/// Spawn a tile entity with mesh
-fn spawn_tile_entity(
-    commands: &mut Commands,
-    meshes: &mut Assets<Mesh>,
-    materials: &mut Assets<StandardMaterial>,
-    colormap: &ColorMap,
-    lod_manager: &LodManager,
-    radars: Option<&crate::radar::Radars>,
-    cache: Option<&TileCache>,
-    coord: TileCoord,
-    tile_data: Option<&crate::tile::TileData>,
+/// System to recolor terrain meshes in the background when the radars or the
+/// coverage settings (e.g. the altitude layer) change
+#[allow(clippy::too_many_arguments)]
+pub fn radar_recolor_system(
+    mut commands: Commands,
+    radars: Res<Radars>,
+    placement: Res<Placement>,
+    cache: Res<TileCache>,
+    colormap: Res<ColorMap>,
+    coverage_cache: Res<CoverageCache>,
+    coverage_settings: Res<CoverageSettings>,
+    mode: Res<WorldMode>,
+    mut manager: ResMut<TileManager>,
+    tile_query: Query<(Entity, &TerrainTile, Has<MeshGenTask>), With<Mesh3d>>,
+    pending_query: Query<&TerrainTile, (With<MeshGenTask>, Without<Mesh3d>)>,
 ) {
-    let builder = TerrainMeshBuilder::new(lod_manager.current_level);
-    
-    // Create a snapshot of the cache for parallel access
-    // This avoids accessing the Res<TileCache> from multiple threads
-    let snapshot = cache.map(|c| c.get_snapshot());
-    
-    let mesh = if let Some(data) = tile_data {
-        builder.build_mesh(data, colormap, radars, snapshot.as_ref())
-    } else {
-        builder.build_missing_mesh()
+    let radars_changed = radars.is_changed() && !radars.is_added();
+    let settings_changed = coverage_settings.is_changed() && !coverage_settings.is_added();
+    if !radars_changed && !settings_changed {
+        return;
+    }
+
+    // First meshes still being built were started with the old settings: rebuild them
+    for tile in pending_query.iter() {
+        manager.mark_stale(tile.coord);
+    }
+
+    let context = RecolorContext {
+        radars: placement.radars(&radars),
+        candidate: placement.candidate_index(&radars),
+        snapshot: std::sync::Arc::new(cache.get_snapshot()),
+        colormap: colormap.clone(),
+        coverage_cache: coverage_cache.clone(),
+        settings: *coverage_settings,
+        mode: *mode,
     };
 
-    // Position the tile in world space
-    // Coordinate System:
-    // X = Longitude (East+)
-    // Z = Latitude (North is -Z, South is +Z)
-    // SRTM Tile Origin is South-West corner (lat, lon)
-    // Mesh generates pz=0 (North edge) to pz=size (South edge)
-    // So we need to place the tile origin at -(lat + 1)
-    let tile_size = 3601.0;
-    let x_offset = coord.lon as f32 * tile_size;
-    let z_offset = -((coord.lat + 1) as f32) * tile_size;
-    
+    // Horizons of stations as they were before this change are not needed any more
+    let compared = (coverage_settings.view == CoverageView::RefractionComparison)
+        .then(|| context.radars.with_refraction(coverage_settings.comparison));
+    coverage_cache.retain_horizons(context.radars.stations.iter().chain(compared.iter().flat_map(|compared| &compared.stations)));
 
+    let mut tasks_spawned = 0;
+    for (entity, tile, rebuilding) in tile_query.iter() {
+        if context.spawn_recolor(&mut commands, &mut manager, &cache, entity, tile.coord, rebuilding) {
+            tasks_spawned += 1;
+        }
+    }
 
-    commands.spawn((
-        Mesh3d(meshes.add(mesh)),
-        MeshMaterial3d(materials.add(StandardMaterial {
-            base_color: Color::WHITE,
-            perceptual_roughness: 0.8,
-            metallic: 0.0,
-            cull_mode: None,  // Disable backface culling - normals point inward
-            alpha_mode: AlphaMode::Blend,
-            ..default()
-        })),
-        Transform::from_xyz(x_offset, 0.0, z_offset),
-        TerrainTile { coord },
-    ));
-    
-    
+    info!("Coverage changed: recoloring {} tiles", tasks_spawned);
+}
+
+/// System to preview the coverage of a candidate station progressively: once it is placed
+/// (or rests while dragged), the tiles in its range are recolored nearest first, a few per
+/// frame, so the area around the site updates before the far tiles
+#[allow(clippy::too_many_arguments)]
+pub fn placement_preview_system(
+    mut commands: Commands,
+    mut placement: ResMut<Placement>,
+    radars: Res<Radars>,
+    cache: Res<TileCache>,
+    colormap: Res<ColorMap>,
+    coverage_cache: Res<CoverageCache>,
+    coverage_settings: Res<CoverageSettings>,
+    mode: Res<WorldMode>,
+    mut manager: ResMut<TileManager>,
+    tile_query: Query<(&TerrainTile, Has<MeshGenTask>), With<Mesh3d>>,
+    pending_query: Query<&TerrainTile, (With<MeshGenTask>, Without<Mesh3d>)>,
+    time: Res<Time>,
+) {
+    // Tiles recolored per frame (each is a background task)
+    const PREVIEW_TILES_PER_FRAME: usize = 2;
 
+    if placement.preview_due(time.elapsed_secs_f64()) {
+        // The candidate moved (or was discarded): its previous sites are not needed any more
+        coverage_cache.evict_candidates(placement.candidate.as_ref());
+        placement.start_preview(
+            tile_query.iter().map(|(tile, _)| tile.coord),
+            coverage_settings.detection.target.rcs_m2(),
+        );
+        // First meshes still being built were started with the previous candidate
+        for tile in pending_query.iter() {
+            manager.mark_stale(tile.coord);
+        }
+        info!("Coverage preview: recoloring {} tiles around the candidate", placement.remaining());
+    }
+    if placement.remaining() == 0 {
+        return;
+    }
 
-    info!("Spawned tile entity: {:?}", coord);
+    // Only the candidate is computed: the other stations come from the coverage cache
+    let context = RecolorContext {
+        radars: placement.radars(&radars),
+        candidate: placement.candidate_index(&radars),
+        snapshot: std::sync::Arc::new(cache.get_snapshot()),
+        colormap: colormap.clone(),
+        coverage_cache: coverage_cache.clone(),
+        settings: *coverage_settings,
+        mode: *mode,
+    };
+    for coord in placement.next_tiles(PREVIEW_TILES_PER_FRAME) {
+        let Some(entity) = manager.entity(&coord) else {
+            continue;
+        };
+        // Evicted since the preview started
+        let Ok((_, rebuilding)) = tile_query.get(entity) else {
+            continue;
+        };
+        context.spawn_recolor(&mut commands, &mut manager, &cache, entity, coord, rebuilding);
+    }
 }
 
-/// System to poll mesh tasks and propagate results
-pub fn process_mesh_tasks(
+/// System to poll recolor tasks and update the color attribute of the tile meshes
+pub fn process_recolor_tasks(
     mut commands: Commands,
-    mut tasks: Query<(Entity, &mut MeshGenTask)>,
+    mut tasks: Query<(Entity, &mut RecolorTask, &Mesh3d)>,
     mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<StandardMaterial>>,
 ) {
-    for (entity, mut mesh_task) in &mut tasks {
-        if let Some(mesh) = future::block_on(future::poll_once(&mut mesh_task.task)) {
-            // Task finished, spawn the real entity
-            let coord = mesh_task.coord;
-            
-            // Calculate transform
-            let tile_size = 3601.0;
-            let x_offset = coord.lon as f32 * tile_size;
-            let z_offset = -((coord.lat + 1) as f32) * tile_size;
-
-            commands.spawn((
-                Mesh3d(meshes.add(mesh)),
-                MeshMaterial3d(materials.add(StandardMaterial {
-                    base_color: Color::WHITE,
-                    perceptual_roughness: 0.8,
-                    metallic: 0.0,
-                    cull_mode: None,
-                    alpha_mode: AlphaMode::Blend,
-                    ..default()
-                })),
-                Transform::from_xyz(x_offset, 0.0, z_offset),
-                TerrainTile { coord },
-            ));
+    for (entity, mut recolor_task, mesh_handle) in &mut tasks {
+        let Some(colors) = future::block_on(future::poll_once(&mut recolor_task.task)) else {
+            continue;
+        };
+        commands.entity(entity).remove::<RecolorTask>();
+
+        let Some(mesh) = meshes.get_mut(&mesh_handle.0) else {
+            continue;
+        };
+        // The mesh was rebuilt at another LOD meanwhile (and got fresh colors): drop these
+        if mesh.count_vertices() != colors.len() {
+            continue;
+        }
+        mesh.insert_attribute(Mesh::ATTRIBUTE_COLOR, colors);
+    }
+}
+
+/// System to free tile meshes that are out of view or beyond the view radius
+#[allow(clippy::too_many_arguments)]
+pub fn tile_eviction_system(
+    mut commands: Commands,
+    time: Res<Time>,
+    origin: Res<FloatingOrigin>,
+    mode: Res<WorldMode>,
+    coverage_cache: Res<CoverageCache>,
+    mut manager: ResMut<TileManager>,
+    camera_query: Query<(&Frustum, &WorldPosition), With<Camera>>,
+    mut tile_query: Query<(Entity, &mut TerrainTile, &WorldPosition)>,
+) {
+    let Ok((frustum, camera_world)) = camera_query.single() else {
+        return;
+    };
+
+    // The frustum is computed from last frame's transforms; right after a re-centre
+    // it doesn't line up with the new origin, so skip this frame.
+    if origin.is_changed() {
+        return;
+    }
+
+    let now = time.elapsed_secs_f64();
+    let camera_geo = mode.world_to_geo(camera_world.0);
+    let camera_coord = TileCoord::from_world_coords(camera_geo.x, camera_geo.y);
+    let max_tile_distance = view_radius_tiles(camera_geo.z);
+
+    for (entity, mut tile, tile_pos) in tile_query.iter_mut() {
+        // POLICY:
+        // 1. Beyond the view radius -> evict immediately.
+        // 2. Outside the frustum for EVICT_DELAY_SECS and not close -> evict.
+        //    The delay avoids rebuilding tiles while the user looks around.
+        // Despawning drops the last mesh/material handles, which frees the GPU buffers
+        // (and cancels a pending mesh task, if any).
+        if tile_distance(tile.coord, camera_coord) > max_tile_distance {
+            commands.entity(entity).despawn();
+            manager.on_evicted(tile.coord);
+            coverage_cache.evict_tile(tile.coord);
+            info!("Evicted tile {:?} (beyond view radius)", tile.coord);
+            continue;
+        }
+
+        if tile_in_frustum(frustum, &origin, tile_pos.0, &tile.bounds) {
+            tile.last_seen = now;
+            continue;
+        }
 
-            // Remove the task entity
+        let distance = (camera_world.0.distance(mode.tile_center(tile.coord)) / mode.unit_scale()) as f32;
+        if distance > KEEP_DISTANCE && now - tile.last_seen > EVICT_DELAY_SECS {
             commands.entity(entity).despawn();
-            
-            info!("Finished mesh generation for {:?}", coord);
+            manager.on_evicted(tile.coord);
+            coverage_cache.evict_tile(tile.coord);
+            info!("Evicted tile {:?} (out of view)", tile.coord);
         }
     }
 }
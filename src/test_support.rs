@@ -0,0 +1,55 @@
+// Shared fixtures for the unit tests
+use crate::radar::{Radar, Radars};
+use crate::tile::{TileCoord, TileData};
+use bevy::math::DVec3;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// RCS of the default target class (light aircraft, m^2)
+pub const TARGET_RCS: f64 = 2.0;
+
+/// Flat 100 m terrain (N43E007, 361 samples) with an 800 m north-south ridge at E7.64-E7.65
+pub fn ridge_tile() -> TileData {
+    let mut tile = TileData::new(TileCoord::new(43, 7), 361);
+    for y in 0..tile.size {
+        for x in 0..tile.size {
+            tile.heights[y * tile.size + x] = if (230..236).contains(&x) { 800 } else { 100 };
+        }
+    }
+    tile
+}
+
+/// `ridge_tile` with 0-6 m of roughness off the ridge
+pub fn rough_ridge_tile() -> TileData {
+    let mut tile = ridge_tile();
+    for y in 0..tile.size {
+        for x in 0..tile.size {
+            if !(230..236).contains(&x) {
+                tile.heights[y * tile.size + x] += (x as i16 + y as i16) % 7;
+            }
+        }
+    }
+    tile
+}
+
+/// Terrain snapshot holding `tile` alone
+pub fn snapshot(tile: &TileData) -> HashMap<TileCoord, Arc<TileData>> {
+    HashMap::from([(tile.coord, Arc::new(tile.clone()))])
+}
+
+/// Terrain snapshot holding `ridge_tile` alone
+pub fn ridge_snapshot() -> HashMap<TileCoord, Arc<TileData>> {
+    snapshot(&ridge_tile())
+}
+
+/// The first default station (Mont Agel)
+pub fn default_radar() -> Radar {
+    Radars::default().stations[0].clone()
+}
+
+/// `default_radar` with its antenna at `lat`, `lon`, `alt`
+pub fn radar_at(lat: f64, lon: f64, alt: f64) -> Radar {
+    let mut radar = default_radar();
+    radar.position = DVec3::new(lat, lon, alt);
+    radar
+}
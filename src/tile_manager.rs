@@ -0,0 +1,262 @@
+// Tile lifecycle tracking and entity index
+use crate::tile::TileCoord;
+use bevy::prelude::*;
+use std::collections::{HashMap, HashSet};
+
+/// Lifecycle phase of a tile
+/// Requested -> Loading -> Loaded -> Meshing -> Visible -> Evicted
+/// (tiles found in the disk cache go straight from Requested to Loaded,
+/// and an evicted tile goes back to Meshing when it comes into view again)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TilePhase {
+    /// Tile is in view range and has been asked for
+    Requested,
+    /// Tile data is being downloaded
+    Loading,
+    /// Tile data is in the cache (or known to be missing), no mesh yet
+    Loaded,
+    /// A mesh is being generated in the background
+    Meshing,
+    /// A mesh is displayed
+    Visible,
+    /// The mesh was despawned to free GPU memory; data is still cached
+    Evicted,
+}
+
+/// Book-keeping for a single tile
+#[derive(Debug, Clone)]
+pub struct ManagedTile {
+    pub phase: TilePhase,
+    /// LOD stride of the displayed (or pending) mesh
+    pub lod: Option<usize>,
+    /// The mesh must be rebuilt (LOD or world layout changed)
+    pub stale: bool,
+}
+
+/// Resource tracking every tile through its lifecycle
+#[derive(Resource, Default)]
+pub struct TileManager {
+    tiles: HashMap<TileCoord, ManagedTile>,
+    /// Tile entity for each coordinate (exists from the first Meshing until eviction)
+    entities: HashMap<TileCoord, Entity>,
+    /// Tiles whose terrain was missing when a tile's coverage was computed
+    dependencies: HashMap<TileCoord, HashSet<TileCoord>>,
+    /// Reverse index: tiles whose coverage must be recomputed once a tile loads
+    dependents: HashMap<TileCoord, HashSet<TileCoord>>,
+}
+
+impl TileManager {
+    /// Get the phase of a tile
+    pub fn phase(&self, coord: &TileCoord) -> Option<TilePhase> {
+        self.tiles.get(coord).map(|t| t.phase)
+    }
+
+    /// Get the entity of a tile
+    pub fn entity(&self, coord: &TileCoord) -> Option<Entity> {
+        self.entities.get(coord).copied()
+    }
+
+    /// LOD stride of the displayed (or pending) mesh of a tile
+    pub fn lod(&self, coord: &TileCoord) -> Option<usize> {
+        self.tiles.get(coord).and_then(|t| t.lod)
+    }
+
+    /// Move a tile to a new phase (creating its entry if needed)
+    pub fn set_phase(&mut self, coord: TileCoord, phase: TilePhase) {
+        let tile = self.tiles.entry(coord).or_insert(ManagedTile {
+            phase,
+            lod: None,
+            stale: false,
+        });
+        if tile.phase != phase {
+            debug!("Tile {:?}: {:?} -> {:?}", coord, tile.phase, phase);
+            tile.phase = phase;
+        }
+    }
+
+    /// Tile data arrived (from disk or download); meshes that were built without
+    /// this terrain on their radar paths are marked stale
+    pub fn on_data_loaded(&mut self, coord: TileCoord, has_terrain: bool) {
+        self.set_phase(coord, TilePhase::Loaded);
+        // Missing tiles (ocean) don't change coverage; keep waiting for real terrain
+        if !has_terrain {
+            return;
+        }
+        for dependent in self.dependents.remove(&coord).unwrap_or_default() {
+            if let Some(deps) = self.dependencies.get_mut(&dependent) {
+                deps.remove(&coord);
+            }
+            debug!("Tile {:?}: dependency {:?} loaded, recomputing", dependent, coord);
+            self.mark_stale(dependent);
+        }
+    }
+
+    /// Record the tiles a mesh was built without (replaces the previous set)
+    pub fn set_dependencies(&mut self, coord: TileCoord, deps: HashSet<TileCoord>) {
+        self.clear_dependencies(coord);
+        for dep in &deps {
+            self.dependents.entry(*dep).or_default().insert(coord);
+        }
+        if !deps.is_empty() {
+            self.dependencies.insert(coord, deps);
+        }
+    }
+
+    /// Forget the dependencies of a tile
+    fn clear_dependencies(&mut self, coord: TileCoord) {
+        for dep in self.dependencies.remove(&coord).unwrap_or_default() {
+            if let Some(dependents) = self.dependents.get_mut(&dep) {
+                dependents.remove(&coord);
+                if dependents.is_empty() {
+                    self.dependents.remove(&dep);
+                }
+            }
+        }
+    }
+
+    /// Tiles that need a mesh: never meshed, evicted, or stale
+    pub fn tiles_to_mesh(&self) -> Vec<TileCoord> {
+        self.tiles
+            .iter()
+            .filter(|(_, t)| match t.phase {
+                TilePhase::Loaded | TilePhase::Evicted => true,
+                TilePhase::Meshing | TilePhase::Visible => t.stale,
+                TilePhase::Requested | TilePhase::Loading => false,
+            })
+            .map(|(coord, _)| *coord)
+            .collect()
+    }
+
+    /// A mesh task was queued on `entity`
+    pub fn on_mesh_queued(&mut self, coord: TileCoord, entity: Entity, lod: usize) {
+        self.entities.insert(coord, entity);
+        let phase = match self.phase(&coord) {
+            // Keep showing the old mesh while the new one is built
+            Some(TilePhase::Visible) => TilePhase::Visible,
+            _ => TilePhase::Meshing,
+        };
+        self.set_phase(coord, phase);
+        if let Some(tile) = self.tiles.get_mut(&coord) {
+            tile.lod = Some(lod);
+            tile.stale = false;
+        }
+    }
+
+    /// The mesh for a tile was swapped in
+    pub fn on_mesh_ready(&mut self, coord: TileCoord) {
+        self.set_phase(coord, TilePhase::Visible);
+    }
+
+    /// Whether a finished mesh is outdated and should be dropped
+    pub fn is_stale(&self, coord: &TileCoord) -> bool {
+        self.tiles.get(coord).is_some_and(|t| t.stale)
+    }
+
+    /// The tile entity was despawned
+    pub fn on_evicted(&mut self, coord: TileCoord) {
+        self.entities.remove(&coord);
+        // Recomputed from a fresh snapshot when the tile is meshed again
+        self.clear_dependencies(coord);
+        self.set_phase(coord, TilePhase::Evicted);
+        if let Some(tile) = self.tiles.get_mut(&coord) {
+            tile.lod = None;
+            tile.stale = false;
+        }
+    }
+
+    /// Rebuild the mesh of a single tile in place
+    pub fn mark_stale(&mut self, coord: TileCoord) {
+        if let Some(tile) = self.tiles.get_mut(&coord) {
+            if matches!(tile.phase, TilePhase::Meshing | TilePhase::Visible) {
+                tile.stale = true;
+            }
+        }
+    }
+
+    /// Rebuild every existing mesh in place (e.g. LOD or world layout changed)
+    pub fn mark_all_stale(&mut self) {
+        for tile in self.tiles.values_mut() {
+            if matches!(tile.phase, TilePhase::Meshing | TilePhase::Visible) {
+                tile.stale = true;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lifecycle() {
+        let mut manager = TileManager::default();
+        let coord = TileCoord::new(43, 7);
+        let entity = Entity::from_raw_u32(1).unwrap();
+
+        manager.set_phase(coord, TilePhase::Requested);
+        manager.set_phase(coord, TilePhase::Loading);
+        assert!(manager.tiles_to_mesh().is_empty());
+
+        manager.set_phase(coord, TilePhase::Loaded);
+        assert_eq!(manager.tiles_to_mesh(), vec![coord]);
+
+        manager.on_mesh_queued(coord, entity, 8);
+        assert_eq!(manager.phase(&coord), Some(TilePhase::Meshing));
+        assert_eq!(manager.entity(&coord), Some(entity));
+        assert!(manager.tiles_to_mesh().is_empty());
+
+        manager.on_mesh_ready(coord);
+        assert_eq!(manager.phase(&coord), Some(TilePhase::Visible));
+
+        manager.on_evicted(coord);
+        assert_eq!(manager.phase(&coord), Some(TilePhase::Evicted));
+        assert_eq!(manager.entity(&coord), None);
+        assert_eq!(manager.tiles_to_mesh(), vec![coord]);
+    }
+
+    #[test]
+    fn test_rebuild_keeps_visible() {
+        let mut manager = TileManager::default();
+        let coord = TileCoord::new(43, 7);
+        let entity = Entity::from_raw_u32(1).unwrap();
+
+        manager.set_phase(coord, TilePhase::Loaded);
+        manager.on_mesh_queued(coord, entity, 8);
+        manager.on_mesh_ready(coord);
+
+        manager.mark_all_stale();
+        assert_eq!(manager.tiles_to_mesh(), vec![coord]);
+
+        // The old mesh stays on screen until the new one is swapped in
+        manager.on_mesh_queued(coord, entity, 20);
+        assert_eq!(manager.phase(&coord), Some(TilePhase::Visible));
+        assert!(!manager.is_stale(&coord));
+    }
+
+    #[test]
+    fn test_dependency_invalidation() {
+        let mut manager = TileManager::default();
+        let coord = TileCoord::new(43, 7);
+        let dep = TileCoord::new(43, 6);
+        let entity = Entity::from_raw_u32(1).unwrap();
+
+        manager.set_phase(coord, TilePhase::Loaded);
+        manager.on_mesh_queued(coord, entity, 8);
+        manager.on_mesh_ready(coord);
+        manager.set_dependencies(coord, HashSet::from([dep]));
+
+        // Ocean tiles don't change coverage
+        manager.set_phase(dep, TilePhase::Loading);
+        manager.on_data_loaded(dep, false);
+        assert!(!manager.is_stale(&coord));
+
+        manager.on_data_loaded(dep, true);
+        assert!(manager.is_stale(&coord));
+        assert!(manager.tiles_to_mesh().contains(&coord));
+
+        // The dependency is resolved: loading it again changes nothing
+        manager.on_mesh_queued(coord, entity, 8);
+        manager.on_data_loaded(dep, true);
+        assert!(!manager.is_stale(&coord));
+    }
+}
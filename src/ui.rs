@@ -1,11 +1,27 @@
 use bevy::prelude::*;
+use bevy::math::DVec3;
+use bevy::tasks::{AsyncComputeTaskPool, Task};
 use bevy::window::PrimaryWindow;
+use futures_lite::future;
+use std::collections::HashMap;
+use std::sync::Arc;
 use crate::cache::TileCache;
-use crate::tile::TileCoord;
+use crate::coverage::{CoverageSettings, CoverageView};
+use crate::detection::PD_CONTOURS;
+use crate::geodesy;
+use crate::mda::{network_mda_raycast, FEET_TO_METERS};
+use crate::mlat::tdoa_dop;
+use crate::placement::Placement;
+use crate::tile::{TileCoord, TileData};
+use crate::world::{FloatingOrigin, WorldMode};
 
 #[derive(Component)]
 pub struct MouseCoordinatesText;
 
+/// Text showing the coverage altitude layer
+#[derive(Component)]
+pub struct CoverageLayerText;
+
 pub fn setup_ui(mut commands: Commands) {
     commands.spawn((
         Text::new("Lat: --\nLon: --\nAlt: --\nDist: --"),
@@ -23,130 +39,366 @@ pub fn setup_ui(mut commands: Commands) {
         BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.5)),
         MouseCoordinatesText,
     ));
+
+    commands.spawn((
+        Text::new("Coverage: Ground"),
+        TextFont {
+            font_size: 20.0,
+            ..default()
+        },
+        TextColor(Color::WHITE),
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(10.0),
+            left: Val::Px(10.0),
+            ..default()
+        },
+        BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.5)),
+        CoverageLayerText,
+    ));
+}
+
+/// Show the selected coverage layer (PageUp/PageDown), refraction model (K) and the
+/// candidate station being placed (N)
+pub fn update_coverage_layer_text_system(
+    settings: Res<CoverageSettings>,
+    radars: Res<crate::radar::Radars>,
+    placement: Res<Placement>,
+    mut text_query: Query<&mut Text, With<CoverageLayerText>>,
+) {
+    if !settings.is_changed() && !radars.is_changed() && !placement.is_changed() {
+        return;
+    }
+    for mut text in text_query.iter_mut() {
+        let coverage = match settings.view {
+            CoverageView::Visibility => format!("Coverage: {}\nRefraction: {}", settings.altitude, radars.refraction()),
+            CoverageView::RefractionComparison => format!(
+                "Coverage: {}\nRefraction: {} (blue) vs {} (orange)",
+                settings.altitude,
+                radars.refraction(),
+                settings.comparison
+            ),
+            CoverageView::DetectionProbability => format!(
+                "Coverage: Pd at {}\nTarget: {}\nRefraction: {}",
+                settings.altitude,
+                settings.detection,
+                radars.refraction()
+            ),
+            CoverageView::SignalMargin => format!(
+                "Coverage: signal margin at {} (over Pd {})\nTarget: {}\nRefraction: {}",
+                settings.altitude,
+                PD_CONTOURS[0],
+                settings.detection,
+                radars.refraction()
+            ),
+            CoverageView::Multilateration => format!(
+                "Coverage: MLAT HDOP at {} ({})\nRefraction: {}",
+                settings.altitude,
+                settings.mlat,
+                radars.refraction()
+            ),
+            view @ (CoverageView::Overlap | CoverageView::UniqueCoverage | CoverageView::SinglePointOfFailure) => format!(
+                "Coverage: {} at {}\nRefraction: {}",
+                view,
+                settings.altitude,
+                radars.refraction()
+            ),
+            view => format!("Coverage: {}\nRefraction: {}", view, radars.refraction()),
+        };
+        text.0 = match placement.status() {
+            Some(status) => format!("{}\n{}", coverage, status),
+            None => coverage,
+        };
+    }
+}
+
+/// Terrain under the cursor: latitude, longitude and ground elevation (meters) where the
+/// ray through the cursor first goes below a loaded tile
+pub fn pick_terrain(
+    camera: &Camera,
+    camera_transform: &GlobalTransform,
+    cursor_position: Vec2,
+    floating_origin: &FloatingOrigin,
+    mode: WorldMode,
+    cache: &TileCache,
+) -> Option<DVec3> {
+    let ray = camera.viewport_to_world(camera_transform, cursor_position).ok()?;
+    let origin = ray.origin;
+    let direction = ray.direction;
+
+    // ALGORITHM: Linear Raymarching
+    // We cast a ray from the camera through the mouse cursor.
+    // We march along this ray in fixed step sizes (50m) to check for terrain intersections.
+    // Optimization: A variable step size or Binary Search refinement could be used here for better performance.
+    let max_dist = 50_000.0;
+    let step_size = 50.0; // 50m precision to start
+    let num_steps = (max_dist / step_size) as usize;
+
+    for i in 0..num_steps {
+        let dist = i as f32 * step_size;
+
+        // The ray lives in render space (relative to the floating origin).
+        // Go back to absolute f64 world coordinates before converting to lat/lon.
+        let pos = floating_origin.to_world(origin + direction * dist);
+
+        // Check if point is below terrain
+        // COORDINATE SYSTEM MAPPING:
+        // World Z maps to Latitude (North-South).
+        //   - Latitude increases as Z becomes MORE NEGATIVE (North direction)
+        //   - Therefore: Lat = -pos.z / TILE_SIZE
+        // World X maps to Longitude (East-West).
+        //   - Longitude increases as X increases (East direction)
+        // On the globe the mapping is ECEF -> geodetic (see WorldMode).
+        let geo = mode.world_to_geo(pos);
+        let lat = geo.x;
+        let lon = geo.y;
+
+        // Find tile
+        let coord = TileCoord::from_world_coords(lat, lon);
+
+        if let Some(crate::tile::TileState::Loaded(data)) = cache.tiles.get(&coord) {
+            // Sample Exact Height
+            let lat_base = coord.lat as f64;
+            let lon_base = coord.lon as f64;
+            let d_lat = lat - lat_base;
+            let d_lon = lon - lon_base;
+
+            let y_pct = 1.0 - d_lat;
+            let x_pct = d_lon;
+
+            // Boundary check
+            if y_pct >= 0.0 && y_pct <= 1.0 && x_pct >= 0.0 && x_pct <= 1.0 {
+                let px = (x_pct * 3600.0) as usize;
+                let py = (y_pct * 3600.0) as usize;
+
+                if let Some(h) = data.get_height(px, py) {
+                    if geo.z <= h as f64 {
+                        // HIT!
+                        // Refine intersection? (Binary search could be added here)
+                        return Some(DVec3::new(lat, lon, h as f64));
+                    }
+                }
+            }
+        }
+    }
+    None
 }
 
+/// Line of sight, and the MDA, margin or MLAT readout of the selected view, at a picked
+/// terrain point (raycasts per radar: computed in the background, see `CursorAnalysis`)
+fn cursor_analysis(
+    radars: &crate::radar::Radars,
+    lat: f64,
+    lon: f64,
+    h: f64,
+    settings: &CoverageSettings,
+    snapshot: &HashMap<TileCoord, Arc<TileData>>,
+) -> String {
+    // Line of sight at the selected layer: margin of the best radar,
+    // or the ridge that hides the target from it
+    let target_alt = settings.altitude.target_altitude(h as f32);
+    let report = radars.check_visibility(lat, lon, target_alt, settings.detection.target.rcs_m2(), snapshot);
+    let los_display = match report.best() {
+        Some((index, los)) if los.visible => format!(
+            "\nLOS: {} +{:.0} m ({} radars)",
+            radars.stations[index].name,
+            los.clearance_m.min(99_999.0),
+            report.visible_count()
+        ),
+        Some((index, los)) => match los.critical {
+            Some(ridge) => format!(
+                "\nLOS: hidden {:.0} m by {} m ridge\n  {:.1} km from {} at {:.2} deg",
+                -los.clearance_m,
+                ridge.height,
+                ridge.range_m / 1000.0,
+                radars.stations[index].name,
+                ridge.elevation_deg
+            ),
+            None => "\nLOS: hidden".to_string(),
+        },
+        None => "\nLOS: --".to_string(),
+    };
+
+    // Exact minimum detectable altitude under the cursor
+    // (raycast reference, only in the MDA view)
+    let mda_display = if settings.view == CoverageView::MinimumAltitude {
+        match network_mda_raycast(radars, lat, lon, h as f32, settings.detection.target.rcs_m2(), snapshot) {
+            Some(mda) => format!(
+                "\nMDA: {:.0} ft ({:.0} m)",
+                mda / FEET_TO_METERS,
+                mda
+            ),
+            None => "\nMDA: --".to_string(),
+        }
+    } else {
+        String::new()
+    };
+
+    // Best margin with the terrain loss, and the Fresnel clearance of its path
+    // (margin view)
+    let margin_display = if settings.view == CoverageView::SignalMargin {
+        let best = radars
+            .stations
+            .iter()
+            .filter_map(|radar| {
+                radar.path_margin(lat, lon, target_alt, &settings.detection, snapshot).map(|path| (radar, path))
+            })
+            .max_by(|a, b| a.1.margin_db.total_cmp(&b.1.margin_db));
+        match best {
+            Some((radar, path)) => format!(
+                "\nMargin: {:+.1} dB from {}\n  terrain loss {:.1} dB ({}), F1 clearance {:.0}%",
+                path.margin_db,
+                radar.name,
+                path.terrain_loss_db,
+                path.loss_model,
+                (path.fresnel_clearance * 100.0).clamp(-999.0, 999.0)
+            ),
+            None => "\nMargin: --".to_string(),
+        }
+    } else {
+        String::new()
+    };
+
+    // Receivers in view and HDOP of a multilateration fix (MLAT view)
+    let mlat_display = if settings.view == CoverageView::Multilateration {
+        let receivers: Vec<_> = report
+            .per_radar
+            .iter()
+            .filter(|(_, los)| los.is_some_and(|los| los.visible))
+            .map(|(index, _)| radars.stations[*index].position)
+            .collect();
+        let target = DVec3::new(lat, lon, target_alt as f64);
+        match tdoa_dop(&receivers, target) {
+            Some(dop) => format!(
+                "\nMLAT: {} receivers, HDOP {:.1}, VDOP {:.1}",
+                receivers.len(),
+                dop.hdop,
+                dop.vdop
+            ),
+            None => format!("\nMLAT: {} receivers, no fix", receivers.len()),
+        }
+    } else {
+        String::new()
+    };
+
+    format!("{}{}{}{}", los_display, mda_display, margin_display, mlat_display)
+}
+
+/// Readout of the point under the cursor that needs raycasts
+/// At most one computation runs at a time; the next one starts when it finishes, from the
+/// latest cursor position, so a moving cursor doesn't pile up work.
+#[derive(Default)]
+pub struct CursorAnalysis {
+    /// Point of the shown or running analysis
+    hit: Option<DVec3>,
+    /// The stations, settings or terrain changed since it started
+    dirty: bool,
+    task: Option<Task<String>>,
+    text: String,
+}
+
+#[allow(clippy::too_many_arguments)]
 pub fn update_mouse_coordinates_system(
     window_query: Query<&Window, With<PrimaryWindow>>,
     camera_query: Query<(&Camera, &GlobalTransform)>,
     cache: Res<TileCache>,
     radars: Res<crate::radar::Radars>,
+    placement: Res<Placement>,
+    floating_origin: Res<FloatingOrigin>,
+    mode: Res<WorldMode>,
+    coverage_settings: Res<CoverageSettings>,
+    mut analysis: Local<CursorAnalysis>,
     mut text_query: Query<&mut Text, With<MouseCoordinatesText>>,
 ) {
     let (camera, camera_transform) = camera_query.single().expect("Primary camera not found");
     let window = window_query.single().expect("Primary window not found");
-    
-    if let Some(cursor_position) = window.cursor_position() {
-        if let Ok(ray) = camera.viewport_to_world(camera_transform, cursor_position) {
-             let origin = ray.origin;
-             let direction = ray.direction;
-             
-             // ALGORITHM: Linear Raymarching
-             // We cast a ray from the camera through the mouse cursor.
-             // We march along this ray in fixed step sizes (50m) to check for terrain intersections.
-             // Optimization: A variable step size or Binary Search refinement could be used here for better performance.
-             let max_dist = 50_000.0;
-             let step_size = 50.0; // 50m precision to start
-             let num_steps = (max_dist / step_size) as usize;
-             
-             let tile_size = 3601.0;
-             
-             for i in 0..num_steps {
-                 let dist = i as f32 * step_size;
-                 let pos = origin + direction * dist;
-                 
-                 // Check if point is below terrain
-                 // COORDINATE SYSTEM MAPPING:
-                 // World Z maps to Latitude (North-South).
-                 //   - Latitude 43N starts at Z = -44 * tile_size
-                 //   - Latitude increases as Z becomes MORE NEGATIVE (North direction)
-                 //   - Therefore: Lat = -pos.z / tile_size
-                 // World X maps to Longitude (East-West).
-                 //   - Longitude increases as X increases (East direction)
-                 // In our Mesh, Z goes from 0 (North/Top) to Size (South/Bottom).
-                 // And we offset the tile by z_offset.
-                 
-                 // Let's use the exact inverse:
-                 // Lat = -pos.z / tile_size.  (If pos.z is -158444 then Lat is 44.0).
-                 // But Lat 43.7 is in Tile 43.
-                 // -43.7 * 3601 = -157363.
-                 // So yes: Lat = -pos.z / tile_size.
-                 
-                 let lat = -pos.z / tile_size;
-                 let lon = pos.x / tile_size;
-                 
-                 // Find tile
-                 let coord = TileCoord::from_world_coords(lat as f64, lon as f64);
-                 
-                 if let Some(crate::tile::TileState::Loaded(data)) = cache.tiles.get(&coord) {
-                     // Sample Exact Height
-                     let lat_base = coord.lat as f64;
-                     let lon_base = coord.lon as f64;
-                     let d_lat = lat as f64 - lat_base;
-                     let d_lon = lon as f64 - lon_base;
-                     
-                     let y_pct = 1.0 - d_lat;
-                     let x_pct = d_lon;
-                     
-                     // Boundary check
-                     if y_pct >= 0.0 && y_pct <= 1.0 && x_pct >= 0.0 && x_pct <= 1.0 {
-                         let px = (x_pct * 3600.0) as usize;
-                         let py = (y_pct * 3600.0) as usize;
-                         
-                         if let Some(h) = data.get_height(px, py) {
-                             if pos.y <= h as f32 {
-                                 // HIT!
-                                 // Refine intersection? (Binary search could be added here)
-                                 
-                                 // Calculate distance to Nearest Radar
-                                 let mut min_dist_nm = f64::MAX;
-                                 let mut nearest_name = "None";
-
-                                 for radar in &radars.stations {
-                                     if !radar.enabled { continue; }
-                                     
-                                     // Haversine distance
-                                     let r_earth = 6_371_000.0;
-                                     let d_lat = (lat as f64 - radar.position.x).to_radians();
-                                     let d_lon = (lon as f64 - radar.position.y).to_radians();
-                                     let lat1 = radar.position.x.to_radians();
-                                     let lat2 = (lat as f64).to_radians();
-
-                                     let a = (d_lat / 2.0).sin().powi(2)
-                                         + lat1.cos() * lat2.cos() * (d_lon / 2.0).sin().powi(2);
-                                     let c = 2.0 * a.sqrt().asin();
-                                     let dist_m = r_earth * c;
-                                     let dist_nm = dist_m / 1852.0;
-
-                                     if dist_nm < min_dist_nm {
-                                         min_dist_nm = dist_nm;
-                                         nearest_name = &radar.name;
-                                     }
-                                 }
-                                 
-                                 let dist_display = if min_dist_nm < f64::MAX {
-                                     format!("{}: {:.1} NM", nearest_name, min_dist_nm)
-                                 } else {
-                                     "Dist: --".to_string()
-                                 };
-
-                                 // Update Text
-                                 for mut text in text_query.iter_mut() {
-                                     text.0 = format!(
-                                         "Lat: {:.5}\nLon: {:.5}\nAlt: {}m\n{}", 
-                                         lat, lon, h, dist_display
-                                     );
-                                 }
-                                 return;
-                             }
-                         }
-                     }
-                 }
-             }
-             
-             // No hit
-             for mut text in text_query.iter_mut() {
-                 text.0 = "Lat: --\nLon: --\nAlt: --\nDist: --".to_string();
-             }
+
+    if radars.is_changed() || placement.is_changed() || coverage_settings.is_changed() || cache.is_changed() {
+        analysis.dirty = true;
+    }
+    if let Some(task) = analysis.task.as_mut() {
+        if let Some(text) = future::block_on(future::poll_once(task)) {
+            analysis.text = text;
+            analysis.task = None;
+        }
+    }
+
+    let Some(cursor_position) = window.cursor_position() else {
+        return;
+    };
+    let Some(hit) = pick_terrain(camera, camera_transform, cursor_position, &floating_origin, *mode, &cache) else {
+        // No hit
+        for mut text in text_query.iter_mut() {
+            text.0 = "Lat: --\nLon: --\nAlt: --\nDist: --".to_string();
         }
+        return;
+    };
+    let (lat, lon, h) = (hit.x, hit.y, hit.z);
+
+    // A candidate being placed counts like the other stations
+    let radars = placement.radars(&radars);
+
+    if (analysis.hit != Some(hit) || analysis.dirty) && analysis.task.is_none() {
+        let radars = radars.clone();
+        let settings = *coverage_settings;
+        let snapshot = cache.get_snapshot();
+        analysis.task = Some(AsyncComputeTaskPool::get().spawn(async move {
+            cursor_analysis(&radars, lat, lon, h, &settings, &snapshot)
+        }));
+        analysis.hit = Some(hit);
+        analysis.dirty = false;
+    }
+
+    // Calculate distance to Nearest Radar
+    let mut min_dist_nm = f64::MAX;
+    let mut nearest_name = "None";
+
+    for radar in &radars.stations {
+        if !radar.enabled { continue; }
+
+        // Geodesic distance on the WGS84 ellipsoid
+        let dist_m = geodesy::distance(radar.position.x, radar.position.y, lat, lon);
+        let dist_nm = dist_m / 1852.0;
+
+        if dist_nm < min_dist_nm {
+            min_dist_nm = dist_nm;
+            nearest_name = &radar.name;
+        }
+    }
+
+    let dist_display = if min_dist_nm < f64::MAX {
+        format!("{}: {:.1} NM", nearest_name, min_dist_nm)
+    } else {
+        "Dist: --".to_string()
+    };
+
+    // Update Text
+    for mut text in text_query.iter_mut() {
+        text.0 = format!("Lat: {:.5}\nLon: {:.5}\nAlt: {:.0}m\n{}{}", lat, lon, h, dist_display, analysis.text);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::radar::Radars;
+    use crate::test_support::ridge_snapshot;
+
+    #[test]
+    fn test_cursor_analysis() {
+        let snapshot = ridge_snapshot();
+        let mut radars = Radars::default();
+        radars.stations[0].position = DVec3::new(43.5, 7.3, 400.0);
+        let settings = CoverageSettings::default();
+
+        // Behind the ridge on the ground: the readout names the ridge
+        let behind = cursor_analysis(&radars, 43.5, 7.9, 100.0, &settings, &snapshot);
+        assert!(behind.starts_with("\nLOS: hidden"), "{}", behind);
+        assert!(behind.contains("800 m ridge"), "{}", behind);
+
+        // Only the views that need them add the MDA and margin lines
+        assert!(!behind.contains("MDA") && !behind.contains("Margin"));
+        let margin_view = CoverageSettings { view: CoverageView::SignalMargin, ..settings };
+        assert!(cursor_analysis(&radars, 43.5, 7.9, 100.0, &margin_view, &snapshot).contains("\nMargin: "));
     }
 }
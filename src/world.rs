@@ -0,0 +1,352 @@
+// World-space coordinate mapping and floating origin
+use crate::geodesy;
+use crate::radar::Radars;
+use crate::refraction::Refraction;
+use crate::tile::TileCoord;
+use bevy::camera::primitives::Aabb;
+use bevy::math::DVec3;
+use bevy::prelude::*;
+
+/// World units per degree of latitude/longitude (one SRTM 1" sample per unit)
+pub const TILE_SIZE: f64 = 3601.0;
+
+/// Distance (flat-mode units) the camera may drift from the render origin
+/// before everything is re-centred around it.
+pub const RECENTER_THRESHOLD: f64 = 2.0 * TILE_SIZE;
+
+/// WGS84 semi-major axis (meters)
+pub const WGS84_A: f64 = 6_378_137.0;
+/// WGS84 flattening
+pub const WGS84_F: f64 = 1.0 / 298.257_223_563;
+
+/// Flattest globe drawn (super-refractive k = 4); ducting has no globe equivalent
+pub const MAX_CURVATURE_FACTOR: f64 = 4.0;
+
+/// How geographic positions are laid out in world space
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Default)]
+pub enum WorldMode {
+    /// Plate carrée plane: 1 unit per arc-second horizontally, 1 unit per meter vertically
+    #[default]
+    Flat,
+    /// Ellipsoidal globe in meters (ECEF, rotated so that Y points to the North pole)
+    /// The WGS84 radii are multiplied by `curvature_factor`. Using the effective
+    /// Earth factor k of the scenario's refraction model makes a straight line in the
+    /// scene follow the same path as a radio ray in `Radar::is_visible_raycast`.
+    Globe { curvature_factor: f64 },
+}
+
+impl WorldMode {
+    /// Globe mode with the curvature of a refraction model, as used by the radar raycast
+    /// Ducting (k negative or infinite: rays bend at least as much as the Earth) cannot be
+    /// drawn as a globe, and neither can very large k: both use `MAX_CURVATURE_FACTOR`.
+    pub fn globe(refraction: Refraction) -> Self {
+        let k = refraction.k;
+        let curvature_factor = if k > 0.0 { k.min(MAX_CURVATURE_FACTOR) } else { MAX_CURVATURE_FACTOR };
+        WorldMode::Globe { curvature_factor }
+    }
+
+    /// Convert a geographic position to an absolute world position
+    /// COORDINATE SYSTEM (Flat):
+    /// X = Longitude (East+), Y = Altitude (meters), Z = Latitude (North is -Z)
+    /// COORDINATE SYSTEM (Globe):
+    /// X = ECEF Y (90°E), Y = ECEF Z (North pole), Z = ECEF X (0°E)
+    pub fn geo_to_world(self, lat: f64, lon: f64, alt: f64) -> DVec3 {
+        match self {
+            WorldMode::Flat => DVec3::new(lon * TILE_SIZE, alt, -lat * TILE_SIZE),
+            WorldMode::Globe { curvature_factor } => {
+                let ecef = geodesy::scaled_to_ecef(curvature_factor, lat, lon, alt);
+                // Cyclic permutation keeps the frame right-handed
+                DVec3::new(ecef.y, ecef.z, ecef.x)
+            }
+        }
+    }
+
+    /// Convert an absolute world position back to (lat, lon, alt)
+    pub fn world_to_geo(self, world: DVec3) -> DVec3 {
+        match self {
+            WorldMode::Flat => DVec3::new(-world.z / TILE_SIZE, world.x / TILE_SIZE, world.y),
+            WorldMode::Globe { curvature_factor } => {
+                geodesy::scaled_from_ecef(curvature_factor, DVec3::new(world.z, world.x, world.y))
+            }
+        }
+    }
+
+    /// Local "up" direction (ellipsoid normal on the globe) at a world position
+    pub fn up(self, world: DVec3) -> DVec3 {
+        match self {
+            WorldMode::Flat => DVec3::Y,
+            WorldMode::Globe { .. } => {
+                let geo = self.world_to_geo(world);
+                let (sin_lat, cos_lat) = geo.x.to_radians().sin_cos();
+                let (sin_lon, cos_lon) = geo.y.to_radians().sin_cos();
+                DVec3::new(cos_lat * sin_lon, sin_lat, cos_lat * cos_lon)
+            }
+        }
+    }
+
+    /// Size of one flat-mode unit (one arc-second) in this mode's world units.
+    /// Used to keep camera speed, LOD distances and re-centring consistent.
+    pub fn unit_scale(self) -> f64 {
+        match self {
+            WorldMode::Flat => 1.0,
+            WorldMode::Globe { curvature_factor } => {
+                WGS84_A * curvature_factor * (1.0 / TILE_SIZE).to_radians()
+            }
+        }
+    }
+
+    /// Absolute world position of a tile mesh origin
+    /// SRTM Tile Origin is South-West corner (lat, lon), but the mesh generates
+    /// pz=0 (North edge) to pz=size (South edge), so the anchor is at -(lat + 1).
+    pub fn tile_anchor(self, coord: TileCoord) -> DVec3 {
+        self.geo_to_world((coord.lat + 1) as f64, coord.lon as f64, 0.0)
+    }
+
+    /// Absolute world position of the tile center (at sea level)
+    pub fn tile_center(self, coord: TileCoord) -> DVec3 {
+        self.geo_to_world(coord.lat as f64 + 0.5, coord.lon as f64 + 0.5, 0.0)
+    }
+
+    /// Bounding box of a tile mesh, relative to its anchor, for an elevation range
+    pub fn tile_bounds(self, coord: TileCoord, min_height: f64, max_height: f64) -> Aabb {
+        match self {
+            WorldMode::Flat => Aabb::from_min_max(
+                Vec3::new(0.0, min_height as f32, 0.0),
+                Vec3::new(TILE_SIZE as f32, max_height as f32, TILE_SIZE as f32),
+            ),
+            WorldMode::Globe { .. } => {
+                // The surface bulges between the corners, so sample a small grid.
+                // The leftover sagitta between samples is well under 100 m; pad for it.
+                const SAMPLES: usize = 5;
+                const PADDING: f32 = 100.0;
+                let anchor = self.tile_anchor(coord);
+                let mut min = Vec3::splat(f32::MAX);
+                let mut max = Vec3::splat(f32::MIN);
+                for i in 0..SAMPLES {
+                    for j in 0..SAMPLES {
+                        let lat = coord.lat as f64 + i as f64 / (SAMPLES - 1) as f64;
+                        let lon = coord.lon as f64 + j as f64 / (SAMPLES - 1) as f64;
+                        for h in [min_height, max_height] {
+                            let p = (self.geo_to_world(lat, lon, h) - anchor).as_vec3();
+                            min = min.min(p);
+                            max = max.max(p);
+                        }
+                    }
+                }
+                Aabb::from_min_max(min - PADDING, max + PADDING)
+            }
+        }
+    }
+}
+
+/// Resource holding the absolute (f64) world position of the render origin.
+/// All `Transform`s are relative to this point so that f32 precision is spent
+/// around the camera instead of around N0E0.
+#[derive(Resource, Debug, Clone, Copy, Default)]
+pub struct FloatingOrigin {
+    pub origin: DVec3,
+}
+
+impl FloatingOrigin {
+    /// Absolute world position -> render-space translation
+    pub fn to_local(self, world: DVec3) -> Vec3 {
+        (world - self.origin).as_vec3()
+    }
+
+    /// Render-space translation -> absolute world position
+    pub fn to_world(self, local: Vec3) -> DVec3 {
+        self.origin + local.as_dvec3()
+    }
+}
+
+/// Absolute (f64) world position of an entity.
+/// `Transform::translation` is derived from it and the `FloatingOrigin`.
+/// The camera is the exception: it moves in render space and its
+/// `WorldPosition` is written back every frame.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct WorldPosition(pub DVec3);
+
+/// System to track the camera in f64 and re-centre the origin on it
+pub fn floating_origin_system(
+    mode: Res<WorldMode>,
+    mut origin: ResMut<FloatingOrigin>,
+    mut camera_query: Query<(&mut Transform, &mut WorldPosition), With<Camera>>,
+) {
+    let Ok((mut transform, mut world_pos)) = camera_query.single_mut() else {
+        return;
+    };
+
+    world_pos.0 = origin.to_world(transform.translation);
+
+    // ALGORITHM: Floating Origin
+    // f32 has ~7 significant digits. At lon 180 (X = 648,180 units) that leaves
+    // well under a unit of precision and vertices start to jitter.
+    // Once the camera wanders far enough, we move the origin onto the camera
+    // and shift everything else by the same amount (see sync_world_transforms_system).
+    let threshold = (RECENTER_THRESHOLD * mode.unit_scale()) as f32;
+    if transform.translation.length() > threshold {
+        origin.origin = world_pos.0;
+        transform.translation = Vec3::ZERO;
+        info!("Floating origin re-centred at {:?}", mode.world_to_geo(origin.origin));
+    }
+}
+
+/// Lay the scene out in `new_mode`: the camera moves over the same place on Earth and every
+/// tile is rebuilt. Returns the camera position as (lat, lon, alt).
+fn relayout(
+    new_mode: WorldMode,
+    mode: &mut WorldMode,
+    origin: &mut FloatingOrigin,
+    manager: &mut crate::tile_manager::TileManager,
+    transform: &mut Transform,
+    world_pos: &mut WorldPosition,
+    tile_query: &mut Query<&mut Visibility, With<crate::systems::TerrainTile>>,
+) -> DVec3 {
+    let cam_geo = mode.world_to_geo(origin.to_world(transform.translation));
+    let eye = new_mode.geo_to_world(cam_geo.x, cam_geo.y, cam_geo.z);
+    origin.origin = eye;
+    world_pos.0 = eye;
+    transform.translation = Vec3::ZERO;
+    *mode = new_mode;
+
+    // Every mesh was built for the old layout: hide them until they are rebuilt
+    manager.mark_all_stale();
+    for mut visibility in tile_query.iter_mut() {
+        *visibility = Visibility::Hidden;
+    }
+    cam_geo
+}
+
+/// System to switch between the flat map and the globe (G key)
+pub fn toggle_world_mode_system(
+    keys: Res<ButtonInput<KeyCode>>,
+    radars: Res<Radars>,
+    mut mode: ResMut<WorldMode>,
+    mut origin: ResMut<FloatingOrigin>,
+    mut manager: ResMut<crate::tile_manager::TileManager>,
+    mut camera_query: Query<(&mut Transform, &mut WorldPosition), With<Camera>>,
+    mut tile_query: Query<&mut Visibility, With<crate::systems::TerrainTile>>,
+) {
+    if !keys.just_pressed(KeyCode::KeyG) {
+        return;
+    }
+    let Ok((mut transform, mut world_pos)) = camera_query.single_mut() else {
+        return;
+    };
+
+    let new_mode = match *mode {
+        WorldMode::Flat => WorldMode::globe(radars.refraction()),
+        WorldMode::Globe { .. } => WorldMode::Flat,
+    };
+    let cam_geo = relayout(new_mode, &mut mode, &mut origin, &mut manager, &mut transform, &mut world_pos, &mut tile_query);
+
+    // Look north and down
+    let look_ahead_deg = (cam_geo.z / TILE_SIZE).clamp(0.05, 5.0);
+    let target = new_mode.geo_to_world(cam_geo.x + look_ahead_deg, cam_geo.y, 0.0);
+    *transform = Transform::IDENTITY.looking_at(origin.to_local(target), new_mode.up(origin.origin).as_vec3());
+
+    info!("World mode: {:?}", new_mode);
+}
+
+/// System to keep the globe curvature on the scenario's refraction model (K changes it)
+pub fn follow_refraction_system(
+    radars: Res<Radars>,
+    mut mode: ResMut<WorldMode>,
+    mut origin: ResMut<FloatingOrigin>,
+    mut manager: ResMut<crate::tile_manager::TileManager>,
+    mut camera_query: Query<(&mut Transform, &mut WorldPosition), With<Camera>>,
+    mut tile_query: Query<&mut Visibility, With<crate::systems::TerrainTile>>,
+) {
+    if !radars.is_changed() || *mode == WorldMode::Flat {
+        return;
+    }
+    let new_mode = WorldMode::globe(radars.refraction());
+    if new_mode == *mode {
+        return;
+    }
+    let Ok((mut transform, mut world_pos)) = camera_query.single_mut() else {
+        return;
+    };
+
+    // The local vertical doesn't depend on the radius: the camera keeps its orientation
+    relayout(new_mode, &mut mode, &mut origin, &mut manager, &mut transform, &mut world_pos, &mut tile_query);
+    info!("World mode: {:?} (refraction {})", new_mode, radars.refraction());
+}
+
+/// System to derive render-space transforms from absolute world positions
+pub fn sync_world_transforms_system(
+    origin: Res<FloatingOrigin>,
+    mut query: Query<(Ref<WorldPosition>, &mut Transform), Without<Camera>>,
+) {
+    let origin_changed = origin.is_changed();
+    for (world_pos, mut transform) in query.iter_mut() {
+        if origin_changed || world_pos.is_changed() {
+            transform.translation = origin.to_local(world_pos.0);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_geo_world_roundtrip() {
+        let mode = WorldMode::Flat;
+        let world = mode.geo_to_world(43.77528, 7.42639, 1248.0);
+        let geo = mode.world_to_geo(world);
+        assert!((geo.x - 43.77528).abs() < 1e-12);
+        assert!((geo.y - 7.42639).abs() < 1e-12);
+        assert_eq!(geo.z, 1248.0);
+    }
+
+    #[test]
+    fn test_globe_roundtrip() {
+        for mode in [WorldMode::Globe { curvature_factor: 1.0 }, WorldMode::globe(Refraction::STANDARD)] {
+            let world = mode.geo_to_world(-33.8688, 151.2093, 2500.0);
+            let geo = mode.world_to_geo(world);
+            assert!((geo.x + 33.8688).abs() < 1e-9);
+            assert!((geo.y - 151.2093).abs() < 1e-9);
+            assert!((geo.z - 2500.0).abs() < 1e-3);
+        }
+    }
+
+    #[test]
+    fn test_globe_follows_refraction() {
+        let factor = |refraction| match WorldMode::globe(refraction) {
+            WorldMode::Globe { curvature_factor } => curvature_factor,
+            WorldMode::Flat => unreachable!(),
+        };
+        assert_eq!(factor(Refraction::STANDARD), 4.0 / 3.0);
+        assert_eq!(factor(Refraction::SUB_REFRACTIVE), 2.0 / 3.0);
+        assert_eq!(factor(Refraction::SUPER_REFRACTIVE), 4.0);
+        // Ducting (negative k) and the ducting threshold (infinite k): the flattest globe
+        assert_eq!(factor(Refraction::from_gradient(-200.0)), MAX_CURVATURE_FACTOR);
+        assert_eq!(factor(Refraction::from_gradient(-1e9 / crate::radar::EARTH_RADIUS)), MAX_CURVATURE_FACTOR);
+    }
+
+    #[test]
+    fn test_globe_wgs84_axes() {
+        let mode = WorldMode::Globe { curvature_factor: 1.0 };
+        // Equator / prime meridian lies on +Z at the semi-major axis
+        let p = mode.geo_to_world(0.0, 0.0, 0.0);
+        assert!((p - DVec3::new(0.0, 0.0, WGS84_A)).length() < 1e-6);
+        // North pole lies on +Y at the semi-minor axis
+        let b = WGS84_A * (1.0 - WGS84_F);
+        let p = mode.geo_to_world(90.0, 0.0, 0.0);
+        assert!((p.y - b).abs() < 1e-6);
+        // Up points away from the center
+        assert!(mode.up(p).dot(DVec3::Y) > 0.999);
+    }
+
+    #[test]
+    fn test_local_precision_far_from_zero() {
+        // Near the antimeridian absolute f32 coordinates lose sub-unit precision,
+        // but positions relative to a nearby origin stay exact.
+        let mode = WorldMode::Flat;
+        let origin = FloatingOrigin { origin: mode.geo_to_world(-16.5, 179.5, 0.0) };
+        let a = mode.geo_to_world(-16.5, 179.5 + 0.1 / TILE_SIZE, 0.0);
+        let local = origin.to_local(a);
+        assert!((local.x - 0.1).abs() < 1e-4);
+    }
+}